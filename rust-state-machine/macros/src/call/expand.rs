@@ -3,56 +3,363 @@ use quote::quote;
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-	let CallDef { pallet_struct, methods } = def;
+	let CallDef { generics, self_ty, methods, errors } = def;
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let where_predicates = where_clause.iter().flat_map(|clause| clause.predicates.iter()).collect::<Vec<_>>();
+
+	// The `Call` enum does not necessarily use all the type parameters of the pallet, like its
+	// instance, so it has a hidden variant which uses all of them. Since it also holds an
+	// `Infallible`, it can never be created. We give it the smallest index no call uses.
+	let type_params = generics.type_params().map(|param| &param.ident).collect::<Vec<_>>();
+	let ignore_index = (0..=u8::MAX)
+		.find(|index| methods.iter().all(|method| method.call_index != *index))
+		.expect("there are less than 256 calls; qed");
 
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
-	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
-	// first two parameters to these calls.
+	// not include the `self` and `caller: T::AccountId` (or `origin: OriginFor<T>`) parameters,
+	// which we always assume are the first two parameters to these calls.
 	let args_name = methods
 		.iter()
-		.map(|method| method.args.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>())
+		.map(|method| method.args.iter().map(|arg| arg.name.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
 	// This is a nested vector of all the types for all the arguments for each of the functions in
 	// `fn_name`. It has the same assumptions as `args_name`.
 	let args_type = methods
 		.iter()
-		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
+		.map(|method| method.args.iter().map(|arg| arg.ty.clone()).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// The names we give to the arguments of the other call, when comparing two calls.
+	let args_other = args_name
+		.iter()
+		.map(|args| args.iter().map(|name| quote::format_ident!("other_{}", name)).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// The text written before each argument in the `Display` of the calls.
+	let args_separator = args_name
+		.iter()
+		.map(|args| (0..args.len()).map(|i| if i == 0 { "" } else { ", " }).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// How each argument is written in the `Display` of the calls, and the bound this needs on its
+	// type. Arguments marked with `#[runtime_call]` hold other calls of the runtime, which are
+	// written with their own `Display`.
+	let (args_fmt, args_fmt_bound): (Vec<Vec<_>>, Vec<Vec<_>>) = methods
+		.iter()
+		.map(|method| {
+			method
+				.args
+				.iter()
+				.map(|arg| {
+					let (name, type_) = (&arg.name, &arg.ty);
+					if arg.runtime_call {
+						(
+							quote! { crate::support::FormatCallArg::fmt_call_arg(#name, f) },
+							quote! { #type_: crate::support::FormatCallArg },
+						)
+					} else {
+						(quote! { crate::support::fmt_call_arg(#name, f) }, quote! { #type_: core::fmt::Debug })
+					}
+				})
+				.unzip()
+		})
+		.unzip();
+
+	// This is a vector of the second argument given to each of the functions in `fn_name`, from the
+	// `origin` of the call. Functions taking `caller: T::AccountId` can only be called by a signed
+	// origin, while functions taking `origin: OriginFor<T>` check the origin themselves.
+	let fn_caller = methods
+		.iter()
+		.map(|method| {
+			if method.takes_origin {
+				quote! { origin }
+			} else {
+				quote! { crate::support::ensure::ensure_signed(origin)? }
+			}
+		})
 		.collect::<Vec<_>>();
 
+	// This is a vector of the expressions calling each of the functions in `fn_name` from the
+	// `RuntimeDispatch` logic. Functions taking a variant of `self` are called on the pallet, which
+	// we get from the runtime, while functions taking `runtime: &mut T` are given the runtime.
+	let fn_call = methods
+		.iter()
+		.zip(&fn_caller)
+		.map(|(method, caller)| {
+			let name = &method.name;
+			let args_name = method.args.iter().map(|arg| &arg.name);
+			if method.takes_runtime {
+				quote! { Self::#name(runtime, #caller, #( #args_name ),*) }
+			} else {
+				quote! {
+					<T as crate::support::GetPallet<Self>>::pallet_mut(runtime)
+						.#name(#caller, #( #args_name ),*)
+				}
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// The metadata of each call.
+	let fn_index = methods.iter().map(|method| method.call_index).collect::<Vec<_>>();
+	let fn_docs = methods.iter().map(|method| method.docs.clone()).collect::<Vec<_>>();
+	let metadata_impl = quote! {
+		impl #impl_generics #self_ty #where_clause {
+			/// Get the metadata of the calls of this pallet.
+			pub fn call_metadata() -> Vec<crate::support::metadata::CallMetadata> {
+				vec![
+					#(
+						crate::support::metadata::CallMetadata {
+							name: stringify!(#fn_name),
+							index: #fn_index,
+							args: vec![
+								#(
+									crate::support::metadata::FieldMetadata {
+										name: stringify!(#args_name),
+										ty: core::any::type_name::<#args_type>(),
+									},
+								)*
+							],
+							docs: vec![ #( #fn_docs ),* ],
+						},
+					)*
+				]
+			}
+
+			/// Get the errors the calls of this pallet can return, as declared with `errors(..)`.
+			pub fn error_metadata() -> Vec<&'static str> {
+				vec![ #( #errors ),* ]
+			}
+		}
+	};
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
-	// and the `Dispatch` trait logic to route a `caller` to access those functions.
-	let dispatch_impl = quote! {
-		// The callable functions exposed by this pallet.
-		//
-		// The parsed function names will be `snake_case`, and that will show up in the enum.
+	// and the `RuntimeDispatch` trait logic to route a `caller` to access those functions.
+	let call_enum = quote! {
+		#metadata_impl
+
+		// The parsed function names will be `snake_case`, and that will show up in the enum. The
+		// discriminant of each variant is its call index, so it does not depend on the order of
+		// the functions. Each variant has the doc comments of its function.
+		/// The callable functions exposed by this pallet.
 		#[allow(non_camel_case_types)]
-		pub enum Call<T: Config> {
+		#[repr(u8)]
+		pub enum Call #generics #where_clause {
 			#(
-				#fn_name { #( #args_name: #args_type),* },
+				#( #[doc = #fn_docs] )*
+				#fn_name { #( #args_name: #args_type),* } = #fn_index,
 			)*
+			#[doc(hidden)]
+			__Ignore(core::marker::PhantomData<(#( #type_params, )*)>, core::convert::Infallible) = #ignore_index,
+		}
+
+		impl #impl_generics Call #ty_generics #where_clause {
+			/// Get the index of the call in this pallet.
+			pub fn call_index(&self) -> u8 {
+				match self {
+					#( Call::#fn_name { .. } => #fn_index, )*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+
+			/// Get the name of the function of the call.
+			pub fn call_name(&self) -> &'static str {
+				match self {
+					#( Call::#fn_name { .. } => stringify!(#fn_name), )*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		// We implement `Debug`, `Clone`, `PartialEq`, `Eq` and `Hash` by hand rather than deriving
+		// them, since a derive would require `T` itself to implement those traits. Instead, we only
+		// require it of the types of the arguments.
+		impl #impl_generics core::fmt::Debug for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: core::fmt::Debug, )* )*
+		{
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => f
+							.debug_struct(stringify!(#fn_name))
+							#( .field(stringify!(#args_name), #args_name) )*
+							.finish(),
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl #impl_generics Clone for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: Clone, )* )*
+		{
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => Call::#fn_name {
+							#( #args_name: #args_name.clone() ),*
+						},
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
 		}
 
+		impl #impl_generics PartialEq for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: PartialEq, )* )*
+		{
+			fn eq(&self, other: &Self) -> bool {
+				match (self, other) {
+					#(
+						(
+							Call::#fn_name { #( #args_name ),* },
+							Call::#fn_name { #( #args_name: #args_other ),* },
+						) => true #( && #args_name == #args_other )*,
+					)*
+					(Call::__Ignore(_, never), _) => match *never {},
+					#[allow(unreachable_patterns)]
+					_ => false,
+				}
+			}
+		}
+
+		impl #impl_generics Eq for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: Eq, )* )*
+		{
+		}
+
+		// Calls are written like a function call, with the names of the arguments, for example
+		// `transfer(to=bob, amount=20)`.
+		impl #impl_generics core::fmt::Display for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_fmt_bound, )* )*
+		{
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							write!(f, "{}(", stringify!(#fn_name))?;
+							#(
+								write!(f, "{}{}=", #args_separator, stringify!(#args_name))?;
+								#args_fmt?;
+							)*
+							write!(f, ")")
+						},
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl #impl_generics core::hash::Hash for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: core::hash::Hash, )* )*
+		{
+			fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+				core::hash::Hash::hash(&self.call_index(), state);
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							#( core::hash::Hash::hash(#args_name, state); )*
+						},
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		// Calls are encoded as their call index followed by their arguments, so that the encoding
+		// stays the same when functions are reordered or renamed.
+		impl #impl_generics crate::support::storage::Encode for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: crate::support::storage::Encode, )* )*
+		{
+			// The destination is named so that it cannot collide with the name of an argument.
+			fn encode_to(&self, __dest: &mut Vec<u8>) {
+				crate::support::storage::Encode::encode_to(&self.call_index(), __dest);
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							#( crate::support::storage::Encode::encode_to(#args_name, __dest); )*
+						},
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		// Dispatch logic at the runtime level, mapping each of the items in the `Call` enum to the
+		// appropriate function call with all arguments, including the `caller`.
+		impl #impl_generics crate::support::RuntimeDispatch<T> for #self_ty
+		where
+			#( #where_predicates, )*
+			T: crate::support::GetPallet<Self>,
+		{
+			type Caller = crate::support::ensure::RawOrigin<T::AccountId>;
+			type Call = Call #ty_generics;
+
+			fn dispatch(
+				runtime: &mut T,
+				origin: Self::Caller,
+				call: Self::Call,
+			) -> crate::support::DispatchResult {
+				match call {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							// Note that we assume the first argument of every call is the `caller`,
+							// or its `origin`.
+							#fn_call?;
+						},
+					)*
+					Call::__Ignore(_, never) => match never {},
+				}
+				Ok(())
+			}
+		}
+	};
+
+	// Pallets where every call takes a variant of `self` don't need the rest of the runtime, so we
+	// also let them be dispatched on their own.
+	if methods.iter().any(|method| method.takes_runtime) {
+		return call_enum
+	}
+
+	let dispatch_impl = quote! {
+		#call_enum
+
 		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
 		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
-			type Call = Call<T>;
+		impl #impl_generics crate::support::Dispatch for #self_ty #where_clause {
+			type Caller = crate::support::ensure::RawOrigin<T::AccountId>;
+			type Call = Call #ty_generics;
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
+			fn dispatch(&mut self, origin: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
 				match call {
 					#(
 						Call::#fn_name { #( #args_name ),* } => {
 							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
+								// Note that we assume the first argument of every call is the `caller`,
+								// or its `origin`.
+								#fn_caller,
 								#( #args_name ),*
 							)?;
 						},
 					)*
+					Call::__Ignore(_, never) => match never {},
 				}
 				Ok(())
 			}
@@ -60,5 +367,5 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 	};
 
 	// Return the generated code.
-	dispatch_impl.into()
+	dispatch_impl
 }
@@ -5,17 +5,35 @@ use syn::spanned::Spanned;
 mod keyword {
 	syn::custom_keyword!(T);
 	syn::custom_keyword!(AccountId);
+	syn::custom_keyword!(OriginFor);
 }
 
 /// This object will collect all the information we need to keep while parsing the callable
 /// functions.
 #[derive(Debug)]
 pub struct CallDef {
-	/// This is the name of the pallet struct where the callable functions are implemented. We
-	/// mostly assume it is `Pallet`.
-	pub pallet_struct: syn::Ident,
+	/// The generics of the impl block, which are also the generics of the `Call` enum. Pallets
+	/// with instances have an instance parameter besides `T`, like `impl<T: Config<I>, I: Instance>`.
+	pub generics: syn::Generics,
+	/// This is the type of the pallet struct where the callable functions are implemented, like
+	/// `Pallet<T>` or `Pallet<T, I>`.
+	pub self_ty: Box<syn::Type>,
 	/// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
 	pub methods: Vec<CallVariantDef>,
+	/// The errors the calls can return, declared with `errors(..)`.
+	pub errors: Vec<syn::LitStr>,
+}
+
+/// An argument of a callable function.
+#[derive(Debug)]
+pub struct CallArg {
+	/// The name of the argument.
+	pub name: syn::Ident,
+	/// The type of the argument.
+	pub ty: Box<syn::Type>,
+	/// Whether the argument is marked with `#[runtime_call]`, because it holds calls of the
+	/// runtime. It is then written with `support::FormatCallArg` in the `Display` of the call.
+	pub runtime_call: bool,
 }
 
 /// This is the metadata we keep about each callable function in our pallet.
@@ -23,12 +41,71 @@ pub struct CallDef {
 pub struct CallVariantDef {
 	/// The function name.
 	pub name: syn::Ident,
-	/// Information on args of the function: `(name, type)`.
-	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// The index of the call in the pallet, given with `#[call_index(..)]`, or the position of the
+	/// function otherwise.
+	pub call_index: u8,
+	/// Whether the function takes `runtime: &mut T` as its first argument, rather than a variant
+	/// of `self`.
+	pub takes_runtime: bool,
+	/// Whether the function takes `origin: OriginFor<T>` as its second argument, rather than the
+	/// account of a signed `caller`.
+	pub takes_origin: bool,
+	/// Information on args of the function. See `CallArg`.
+	pub args: Vec<CallArg>,
+	/// The doc comments of the function, one line per item.
+	pub docs: Vec<String>,
+}
+
+/// The arguments of `#[macros::call(..)]`.
+#[derive(Debug, Default)]
+pub struct CallArgs {
+	/// In strict mode, every call must be given an explicit index.
+	pub strict: bool,
+	/// With helpers, only the functions which are `pub` or marked with `#[call]` are calls, and the
+	/// other functions are left as they are.
+	pub helpers: bool,
+	/// The errors the calls can return, like `errors("Not enough funds.", "Overflow")`.
+	pub errors: Vec<syn::LitStr>,
+}
+
+impl CallArgs {
+	/// Parse the arguments of the attribute, which can be `strict`, `helpers` and `errors(..)`, in
+	/// any order.
+	fn parse(attr: proc_macro2::TokenStream) -> syn::Result<Self> {
+		let mut args = Self::default();
+		let parser = syn::meta::parser(|meta| {
+			if meta.path.is_ident("strict") {
+				args.strict = true;
+				Ok(())
+			} else if meta.path.is_ident("helpers") {
+				args.helpers = true;
+				Ok(())
+			} else if meta.path.is_ident("errors") {
+				let content;
+				syn::parenthesized!(content in meta.input);
+				let errors = content.parse_terminated(<syn::LitStr as syn::parse::Parse>::parse, syn::Token![,])?;
+				for error in errors {
+					if args.errors.iter().any(|other| other.value() == error.value()) {
+						return Err(syn::Error::new(error.span(), "Invalid pallet::call, duplicate error"))
+					}
+					args.errors.push(error);
+				}
+				Ok(())
+			} else {
+				Err(meta.error("Invalid pallet::call, expected `strict`, `helpers` or `errors(..)`"))
+			}
+		});
+		syn::parse::Parser::parse2(parser, attr)?;
+		Ok(args)
+	}
 }
 
 impl CallDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	/// Parse the calls of `item`, removing the `#[call_index(..)]`, `#[call]` and `#[runtime_call]`
+	/// attributes from it.
+	pub fn try_from(attr: proc_macro2::TokenStream, item: &mut syn::Item) -> syn::Result<Self> {
+		let CallArgs { strict, helpers, errors } = CallArgs::parse(attr)?;
+
 		// First we check that we are parsing an `impl`.
 		let item_impl = if let syn::Item::Impl(item) = item {
 			item
@@ -36,106 +113,447 @@ impl CallDef {
 			return Err(syn::Error::new(item.span(), "Invalid pallet::call, expected item impl"))
 		};
 
-		// Extract the name of the struct. We mostly assume it is `Pallet`, but we can handle it
-		// when it isn't.
-		let pallet_struct = match &*item_impl.self_ty {
-			syn::Type::Path(tp) => tp.path.segments.first().unwrap().ident.clone(),
-			_ => panic!("not supported tokens"),
-		};
+		// We remove all the `#[call_index(..)]`, `#[call]` and `#[runtime_call]` attributes before
+		// anything else, so that the item we give back does not contain them even if the calls are
+		// invalid.
+		let call_attrs = item_impl
+			.items
+			.iter_mut()
+			.filter_map(|item| match item {
+				syn::ImplItem::Fn(method) => Some(take_call_attrs(method).and_then(|(call_index, marked)| {
+					Ok((call_index, marked, take_runtime_call_attrs(method)?))
+				})),
+				_ => None,
+			})
+			.collect::<syn::Result<Vec<_>>>()?;
+
+		// The generated code refers to the runtime as `T`, so it must be the first type parameter of
+		// the block. Other generics, like the instance of the pallet, are kept as they are.
+		let generics = item_impl.generics.clone();
+		if generics.type_params().next().is_none_or(|param| param.ident != "T") {
+			let msg = "Invalid pallet::call, expected the first type parameter of the impl to be `T`, \
+				like `impl<T: Config> Pallet<T>`";
+			return Err(syn::Error::new(generics.span(), msg))
+		}
+		if let Some(param) = generics.lifetimes().next() {
+			let msg = "Invalid pallet::call, lifetime parameters are not supported, since the \
+				arguments of the calls are stored in the `Call` enum";
+			return Err(syn::Error::new(param.span(), msg))
+		}
+		let self_ty = item_impl.self_ty.clone();
 
 		// Here is where we will store all the callable functions.
 		let mut methods = vec![];
-		for item in item_impl.items {
-			if let syn::ImplItem::Fn(method) = item {
-				// Here is where we will store all the args for each callable functions.
-				let mut args = vec![];
-
-				// First argument should be some variant of `self`.
-				match method.sig.inputs.first() {
-					Some(syn::FnArg::Receiver(_)) => {},
-					_ => {
-						let msg = "Invalid call, first argument must be a variant of self";
-						return Err(syn::Error::new(method.sig.span(), msg))
-					},
-				}
+		let methods_iter = item_impl.items.iter().filter_map(|item| match item {
+			syn::ImplItem::Fn(method) => Some(method),
+			_ => None,
+		});
+		for (method, (call_index, marked, runtime_call_args)) in methods_iter.zip(call_attrs) {
+			let fn_name = method.sig.ident.clone();
 
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
-					Some(syn::FnArg::Typed(arg)) => {
-						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
-					},
-					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
-						return Err(syn::Error::new(method.sig.span(), msg))
-					},
+			// Without helpers, every function is a call. With helpers, we remember why a function is
+			// a call, to explain it when its signature is wrong.
+			let reason = if !helpers {
+				None
+			} else if marked {
+				Some("it is marked with `#[call]`")
+			} else if matches!(method.vis, syn::Visibility::Public(_)) {
+				Some("it is `pub`")
+			} else {
+				if call_index.is_some() {
+					let msg = "Invalid pallet::call, `#[call_index(..)]` is only supported on calls, \
+						which are the functions that are `pub` or marked with `#[call]`";
+					return Err(syn::Error::new(fn_name.span(), msg))
+				}
+				if let Some(span) = runtime_call_args.iter().flatten().next() {
+					let msg = "Invalid pallet::call, `#[runtime_call]` is only supported on the \
+						arguments of calls, which are the functions that are `pub` or marked with `#[call]`";
+					return Err(syn::Error::new(*span, msg))
 				}
+				continue
+			};
 
-				let fn_name = method.sig.ident.clone();
-
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
-				for arg in method.sig.inputs.iter().skip(2) {
-					// All arguments should be typed.
-					let arg = if let syn::FnArg::Typed(arg) = arg {
-						arg
-					} else {
-						unreachable!("All args should be typed.");
-					};
-
-					// Extract the name of the argument.
-					let arg_ident = if let syn::Pat::Ident(pat) = &*arg.pat {
-						pat.ident.clone()
-					} else {
-						let msg = "Invalid pallet::call, argument must be ident";
-						return Err(syn::Error::new(arg.pat.span(), msg))
-					};
-
-					// Store the argument name and the argument type for generating code.
-					args.push((arg_ident, arg.ty.clone()));
+			let (takes_runtime, takes_origin, args) = parse_signature(method, &runtime_call_args).map_err(|mut e| {
+				if let Some(reason) = reason {
+					let msg = format!(
+						"`{}` is a call because {}; helper functions must be private and not marked \
+							with `#[call]`",
+						fn_name, reason,
+					);
+					e.combine(syn::Error::new(fn_name.span(), msg));
 				}
+				e
+			})?;
 
-				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+			let call_index = match call_index {
+				Some(call_index) => call_index,
+				None if strict => {
+					let msg = "Invalid pallet::call, missing `#[call_index(..)]`, which is required in \
+						strict mode";
+					return Err(syn::Error::new(fn_name.span(), msg))
+				},
+				// The implicit index is the position of the function among the calls.
+				None => u8::try_from(methods.len()).map_err(|_| {
+					syn::Error::new(fn_name.span(), "Invalid pallet::call, too many calls")
+				})?,
+			};
+			if let Some(other) = methods.iter().find(|other: &&CallVariantDef| other.call_index == call_index) {
+				let msg = format!(
+					"Invalid pallet::call, call index {} is already used by `{}`",
+					call_index, other.name,
+				);
+				return Err(syn::Error::new(fn_name.span(), msg))
 			}
+
+			// The errors the function returns directly must be declared, so that the declared errors
+			// only miss the ones propagated with `?` from other functions.
+			let mut found = vec![];
+			find_errors(method.block.to_token_stream(), &mut found);
+			if let Some(error) = found.iter().find(|found| errors.iter().all(|error| error.value() != found.value())) {
+				let msg = format!(
+					"Invalid pallet::call, the error {:?} is not declared, expected it in \
+						`#[macros::call(errors(..))]`",
+					error.value(),
+				);
+				return Err(syn::Error::new(error.span(), msg))
+			}
+
+			let docs = crate::utils::get_doc_literals(&method.attrs);
+
+			// Store all the function name and the arg data for the function.
+			methods.push(CallVariantDef { name: fn_name, call_index, takes_runtime, takes_origin, args, docs });
+		}
+
+		// The `Call` enum has a hidden variant besides the calls, which needs an index too.
+		if methods.len() > u8::MAX as usize {
+			return Err(syn::Error::new(item_impl.span(), "Invalid pallet::call, too many calls"))
 		}
 
 		// Return all callable functions for this pallet.
-		Ok(Self { pallet_struct, methods })
+		Ok(Self { generics, self_ty, methods, errors })
+	}
+}
+
+/// The shape of the signature of calls, which we show when a signature is wrong.
+const EXPECTED_SIGNATURE: &str = "expected a function like `fn name(&mut self, caller: T::AccountId, ..)`, \
+	or `fn name(runtime: &mut T, caller: T::AccountId, ..)` for calls which need the whole runtime, \
+	with `origin: OriginFor<T>` instead of `caller` for calls which are not only made by accounts";
+
+/// Check the signature of a call, and return whether it takes the runtime rather than a variant of
+/// `self`, whether it takes the origin rather than the `caller`, along with its arguments besides
+/// `self` (or `runtime`) and `caller` (or `origin`).
+///
+/// `runtime_call_args` has the span of the `#[runtime_call]` attribute (if any) of every argument,
+/// which `take_runtime_call_attrs` removed.
+fn parse_signature(
+	method: &syn::ImplItemFn,
+	runtime_call_args: &[Option<proc_macro2::Span>],
+) -> syn::Result<(bool, bool, Vec<CallArg>)> {
+	// Here is where we will store all the args for each callable functions.
+	let mut args = vec![];
+
+	// The first two arguments are not stored in the `Call` enum, so they cannot hold calls.
+	if let Some(span) = runtime_call_args.iter().take(2).flatten().next() {
+		let msg = "Invalid pallet::call, `#[runtime_call]` is only supported on the arguments \
+			stored in the `Call` enum, which come after `caller` (or `origin`)";
+		return Err(syn::Error::new(*span, msg))
+	}
+
+	// First argument should be some variant of `self`, or `runtime: &mut T` for calls
+	// which need access to the whole runtime.
+	let takes_runtime = match method.sig.inputs.first() {
+		Some(syn::FnArg::Receiver(_)) => false,
+		Some(syn::FnArg::Typed(arg)) => {
+			check_runtime_arg(arg)?;
+			true
+		},
+		_ => {
+			let msg = format!(
+				"Invalid pallet::call, first argument must be a variant of self, or `runtime: &mut T`; {}",
+				EXPECTED_SIGNATURE,
+			);
+			return Err(syn::Error::new(method.sig.ident.span(), msg))
+		},
+	};
+
+	// The second argument should be the `caller: T::AccountId` or `origin: OriginFor<T>` argument.
+	let takes_origin = match method.sig.inputs.iter().nth(1) {
+		Some(syn::FnArg::Typed(arg)) => {
+			// Here we specifically check that this argument is as we expect for
+			// `caller: T::AccountId` or `origin: OriginFor<T>`.
+			check_caller_arg(arg)?
+		},
+		_ => {
+			let msg = format!(
+				"Invalid pallet::call, second argument should be `caller: T::AccountId` or \
+					`origin: OriginFor<T>`; {}",
+				EXPECTED_SIGNATURE,
+			);
+			let span = method.sig.inputs.first().map_or(method.sig.ident.span(), |arg| arg.span());
+			return Err(syn::Error::new(span, msg))
+		},
+	};
+
+	if !method.sig.generics.params.is_empty() {
+		let msg = "Invalid pallet::call, calls cannot have generics of their own, put them on the \
+			impl block instead";
+		return Err(syn::Error::new(method.sig.generics.span(), msg))
+	}
+
+	// Parsing the rest of the args. Skipping 2 for `self` (or `runtime`) and `caller`.
+	for (arg, runtime_call) in method.sig.inputs.iter().zip(runtime_call_args).skip(2) {
+		// All arguments should be typed. Syn already rejects a variant of self after the first
+		// argument when parsing the impl block, as `tests/ui/call/self_after_caller.rs` checks, so
+		// this is only a safeguard.
+		let arg = if let syn::FnArg::Typed(arg) = arg {
+			arg
+		} else {
+			let msg = "Invalid pallet::call, only the first argument can be a variant of self";
+			return Err(syn::Error::new(arg.span(), msg))
+		};
+
+		// Extract the name of the argument.
+		let arg_ident = if let syn::Pat::Ident(pat) = &*arg.pat {
+			pat.ident.clone()
+		} else {
+			let msg = "Invalid pallet::call, argument must be ident";
+			return Err(syn::Error::new(arg.pat.span(), msg))
+		};
+
+		check_arg_type(&arg.ty)?;
+
+		// Store the argument name and the argument type for generating code.
+		args.push(CallArg { name: arg_ident, ty: arg.ty.clone(), runtime_call: runtime_call.is_some() });
 	}
+
+	Ok((takes_runtime, takes_origin, args))
+}
+
+/// Remove the `#[call_index(..)]` and `#[call]` attributes of a function, and return its index and
+/// whether it was marked as a call.
+fn take_call_attrs(method: &mut syn::ImplItemFn) -> syn::Result<(Option<u8>, bool)> {
+	let mut call_index = None;
+	let mut marked = false;
+	let mut result = Ok(());
+	method.attrs.retain(|attr| {
+		if attr.path().is_ident("call") {
+			if !matches!(attr.meta, syn::Meta::Path(_)) {
+				let msg = "Invalid pallet::call, `#[call]` takes no arguments";
+				result = Err(syn::Error::new(attr.span(), msg));
+			}
+			marked = true;
+			return false
+		}
+		if !attr.path().is_ident("call_index") {
+			return true
+		}
+		if call_index.is_some() {
+			let msg = "Invalid pallet::call, duplicate `#[call_index(..)]` attribute";
+			result = Err(syn::Error::new(attr.span(), msg));
+		} else {
+			match attr.parse_args::<syn::LitInt>().and_then(|index| index.base10_parse::<u8>()) {
+				Ok(index) => call_index = Some(index),
+				Err(e) => {
+					let msg = "Invalid pallet::call, expected a call index between 0 and 255, like \
+						`#[call_index(0)]`";
+					result = Err(syn::Error::new(e.span(), msg))
+				},
+			}
+		}
+		false
+	});
+	result.map(|_| (call_index, marked))
+}
+
+/// Remove the `#[runtime_call]` attributes of the arguments of a function, and return the span of
+/// the attribute (if any) of every argument.
+fn take_runtime_call_attrs(method: &mut syn::ImplItemFn) -> syn::Result<Vec<Option<proc_macro2::Span>>> {
+	let mut result = Ok(());
+	let marks = method
+		.sig
+		.inputs
+		.iter_mut()
+		.map(|arg| {
+			let attrs = match arg {
+				syn::FnArg::Receiver(receiver) => &mut receiver.attrs,
+				syn::FnArg::Typed(arg) => &mut arg.attrs,
+			};
+			let mut mark = None;
+			attrs.retain(|attr| {
+				if !attr.path().is_ident("runtime_call") {
+					return true
+				}
+				if !matches!(attr.meta, syn::Meta::Path(_)) {
+					let msg = "Invalid pallet::call, `#[runtime_call]` takes no arguments";
+					result = Err(syn::Error::new_spanned(&attr.meta, msg));
+				}
+				mark = Some(attr.path().span());
+				false
+			});
+			mark
+		})
+		.collect();
+	result.map(|_| marks)
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
+/// Check runtime arg is exactly: `runtime: &mut T`.
 ///
 /// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
-	pub struct CheckDispatchableFirstArg;
-	impl syn::parse::Parse for CheckDispatchableFirstArg {
+pub fn check_runtime_arg(arg: &syn::PatType) -> syn::Result<()> {
+	pub struct CheckRuntimeArg;
+	impl syn::parse::Parse for CheckRuntimeArg {
 		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+			input.parse::<syn::Token![&]>()?;
+			input.parse::<syn::Token![mut]>()?;
 			input.parse::<keyword::T>()?;
-			input.parse::<syn::Token![::]>()?;
-			input.parse::<keyword::AccountId>()?;
 			Ok(Self)
 		}
 	}
 
-	// This checks the arg name is `caller` or `_caller`.
+	// This checks the arg name is `runtime` or `_runtime`.
 	if let syn::Pat::Ident(ident) = &*arg.pat {
-		// We also support the name as `_caller` for when the variable is unused.
-		if &ident.ident != "caller" && &ident.ident != "_caller" {
-			let msg = "Invalid name for second parameter: expected `caller: T::AccountId`";
+		if &ident.ident != "runtime" && &ident.ident != "_runtime" {
+			let msg = "Invalid name for first parameter: expected a variant of self, or \
+				`runtime: &mut T`";
 			return Err(syn::Error::new(ident.span(), msg))
 		}
 	}
 
-	// This checks the type is `T::AccountId` with `CheckDispatchableFirstArg`
+	// This checks the type is `&mut T` with `CheckRuntimeArg`
 	let ty = &arg.ty;
-	syn::parse2::<CheckDispatchableFirstArg>(ty.to_token_stream()).map_err(|e| {
-		let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
-		let mut err = syn::Error::new(ty.span(), msg);
-		err.combine(e);
-		err
+	syn::parse2::<CheckRuntimeArg>(ty.to_token_stream()).map_err(|_| {
+		let msg = "Invalid type for first parameter: expected `runtime: &mut T`";
+		syn::Error::new(ty.span(), msg)
 	})?;
 
 	Ok(())
 }
+
+/// Check caller arg is exactly: `caller: T::AccountId` or `origin: OriginFor<T>`, and return
+/// whether it is the origin.
+///
+/// This is kept strict to keep the code simple.
+pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<bool> {
+	pub struct CheckCallerArg;
+	impl syn::parse::Parse for CheckCallerArg {
+		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+			input.parse::<keyword::T>()?;
+			input.parse::<syn::Token![::]>()?;
+			input.parse::<keyword::AccountId>()?;
+			Ok(Self)
+		}
+	}
+
+	pub struct CheckOriginArg;
+	impl syn::parse::Parse for CheckOriginArg {
+		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+			input.parse::<keyword::OriginFor>()?;
+			input.parse::<syn::Token![<]>()?;
+			input.parse::<keyword::T>()?;
+			input.parse::<syn::Token![>]>()?;
+			Ok(Self)
+		}
+	}
+
+	// This checks the arg name is `caller` or `origin`. We also support the names `_caller` and
+	// `_origin` for when the variable is unused.
+	let takes_origin = match &*arg.pat {
+		syn::Pat::Ident(ident) if ident.ident == "caller" || ident.ident == "_caller" => false,
+		syn::Pat::Ident(ident) if ident.ident == "origin" || ident.ident == "_origin" => true,
+		pat => {
+			let msg = "Invalid name for second parameter: expected `caller: T::AccountId` or \
+				`origin: OriginFor<T>`";
+			return Err(syn::Error::new(pat.span(), msg))
+		},
+	};
+
+	// This checks the type is `T::AccountId` with `CheckCallerArg`, or `OriginFor<T>` with
+	// `CheckOriginArg`.
+	let ty = &arg.ty;
+	if takes_origin {
+		syn::parse2::<CheckOriginArg>(ty.to_token_stream()).map_err(|_| {
+			let msg = "Invalid type for second parameter: expected `origin: OriginFor<T>`";
+			syn::Error::new_spanned(ty, msg)
+		})?;
+	} else {
+		syn::parse2::<CheckCallerArg>(ty.to_token_stream()).map_err(|_| {
+			let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
+			syn::Error::new_spanned(ty, msg)
+		})?;
+	}
+
+	Ok(takes_origin)
+}
+
+/// Check the type of an argument can be stored in the `Call` enum, which only has the generics of
+/// the impl block. References are only supported with a `'static` lifetime, and `impl Trait` is
+/// not supported.
+fn check_arg_type(ty: &syn::Type) -> syn::Result<()> {
+	match ty {
+		syn::Type::Reference(reference) => {
+			if reference.lifetime.as_ref().is_none_or(|lifetime| lifetime.ident != "static") {
+				let msg = "Invalid pallet::call, arguments are stored in the `Call` enum, so they must \
+					be owned, like `String` rather than `&str`, or have a `'static` lifetime";
+				return Err(syn::Error::new(ty.span(), msg))
+			}
+			check_arg_type(&reference.elem)
+		},
+		syn::Type::ImplTrait(_) => {
+			let msg = "Invalid pallet::call, `impl Trait` arguments are not supported, since they \
+				are stored in the `Call` enum; add a type parameter to the impl block instead";
+			Err(syn::Error::new(ty.span(), msg))
+		},
+		syn::Type::Paren(paren) => check_arg_type(&paren.elem),
+		syn::Type::Group(group) => check_arg_type(&group.elem),
+		syn::Type::Slice(slice) => check_arg_type(&slice.elem),
+		syn::Type::Array(array) => check_arg_type(&array.elem),
+		syn::Type::Tuple(tuple) => tuple.elems.iter().try_for_each(check_arg_type),
+		_ => Ok(()),
+	}
+}
+
+/// Find the string literals given to `Err(..)` and `ok_or(..)`, and given as the error of
+/// `ensure!(.., ..)`, in `tokens`. These are the errors a call returns directly, which we add to
+/// `errors`. Errors returned by the functions a call uses are not found this way.
+fn find_errors(tokens: proc_macro2::TokenStream, errors: &mut Vec<syn::LitStr>) {
+	let mut tokens = tokens.into_iter().peekable();
+	while let Some(token) = tokens.next() {
+		match token {
+			proc_macro2::TokenTree::Ident(ident) if ident == "Err" || ident == "ok_or" => {
+				let Some(proc_macro2::TokenTree::Group(group)) = tokens.peek() else { continue };
+				let literal = group.stream().into_iter().next().and_then(|token| match token {
+					proc_macro2::TokenTree::Literal(literal) => {
+						syn::parse2::<syn::LitStr>(literal.into_token_stream()).ok()
+					},
+					_ => None,
+				});
+				if let Some(error) = literal {
+					errors.push(error);
+				}
+			},
+			proc_macro2::TokenTree::Ident(ident) if ident == "ensure" => {
+				let Some(proc_macro2::TokenTree::Punct(punct)) = tokens.peek() else { continue };
+				if punct.as_char() != '!' {
+					continue
+				}
+				tokens.next();
+				let Some(proc_macro2::TokenTree::Group(group)) = tokens.peek() else { continue };
+				// The error is the last argument of the macro, which may be followed by a comma.
+				let args = group.stream().into_iter().collect::<Vec<_>>();
+				let mut args = args.split(|token| matches!(token, proc_macro2::TokenTree::Punct(p) if p.as_char() == ','));
+				let last = args.rfind(|arg| !arg.is_empty());
+				let literal = match last {
+					Some([proc_macro2::TokenTree::Literal(literal)]) => {
+						syn::parse2::<syn::LitStr>(literal.clone().into_token_stream()).ok()
+					},
+					_ => None,
+				};
+				if let Some(error) = literal {
+					errors.push(error);
+				}
+			},
+			proc_macro2::TokenTree::Group(group) => find_errors(group.stream(), errors),
+			_ => {},
+		}
+	}
+}
@@ -0,0 +1,55 @@
+use super::parse::EventDef;
+use quote::quote;
+
+/// See the `fn event` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_event(def: EventDef) -> proc_macro2::TokenStream {
+	let EventDef { generics, variants } = def;
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	let event_metadata = variants
+		.iter()
+		.map(|variant| {
+			let name = variant.name.to_string();
+			let field_names = variant.fields.iter().map(|(name, _)| name);
+			let field_types = variant.fields.iter().map(|(_, ty)| ty);
+			let docs = &variant.docs;
+			quote! {
+				crate::support::metadata::EventMetadata {
+					name: #name,
+					fields: vec![
+						#(
+							crate::support::metadata::FieldMetadata {
+								name: #field_names,
+								ty: core::any::type_name::<#field_types>(),
+							},
+						)*
+					],
+					docs: vec![ #( #docs ),* ],
+				}
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// We assume the pallet struct is named `Pallet`, and has the same generics as the events, or is
+	// generic over `T: Config` when the events are not generic. It stores the events in a field
+	// named `events`.
+	let pallet_impl = if generics.params.is_empty() {
+		quote!(impl<T: Config> Pallet<T>)
+	} else {
+		quote!(impl #impl_generics Pallet #ty_generics #where_clause)
+	};
+	quote! {
+		#pallet_impl {
+			/// Get the metadata of the events of this pallet.
+			pub fn event_metadata() -> Vec<crate::support::metadata::EventMetadata> {
+				vec![ #( #event_metadata ),* ]
+			}
+
+			/// Clear the events emitted by this pallet, which the runtime does at the beginning of
+			/// every block.
+			pub fn clear_events(&mut self) {
+				self.events.clear();
+			}
+		}
+	}
+}
@@ -0,0 +1,53 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the events.
+#[derive(Debug)]
+pub struct EventDef {
+	/// The generics of the event enum, which the pallet struct is expected to share if there are any.
+	pub generics: syn::Generics,
+	/// The events of the pallet. See `EventVariantDef`.
+	pub variants: Vec<EventVariantDef>,
+}
+
+/// This is the metadata we keep about each event.
+#[derive(Debug)]
+pub struct EventVariantDef {
+	/// The name of the variant.
+	pub name: syn::Ident,
+	/// The fields of the variant: `(name, type)`. Fields of tuple variants are named after their
+	/// position.
+	pub fields: Vec<(String, syn::Type)>,
+	/// The doc comments of the variant, one line per item.
+	pub docs: Vec<String>,
+}
+
+impl EventDef {
+	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::event, expected item enum"))
+		};
+
+		let variants = item_enum
+			.variants
+			.iter()
+			.map(|variant| {
+				let fields = variant
+					.fields
+					.iter()
+					.enumerate()
+					.map(|(index, field)| {
+						let name = field.ident.as_ref().map(ToString::to_string).unwrap_or(index.to_string());
+						(name, field.ty.clone())
+					})
+					.collect();
+				let docs = crate::utils::get_doc_literals(&variant.attrs);
+				EventVariantDef { name: variant.ident.clone(), fields, docs }
+			})
+			.collect();
+
+		Ok(Self { generics: item_enum.generics, variants })
+	}
+}
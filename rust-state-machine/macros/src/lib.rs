@@ -1,6 +1,57 @@
 mod call;
+mod event;
 mod runtime;
+mod storage;
+mod utils;
 
+/// Expand the callable functions of a pallet.
+///
+/// This is placed on an `impl<T: Config> Pallet<T>` block, and every function in it becomes a call
+/// of the pallet. The first argument of each function must either be a variant of `self`, or
+/// `runtime: &mut T` for calls which need access to the whole runtime, for example to dispatch
+/// other calls. The second argument must be `caller: T::AccountId` for calls which must be signed
+/// by an account, or `origin: OriginFor<T>` for calls which check the origin themselves, like with
+/// `ensure_root`.
+///
+/// Each call is given an index in the pallet with `#[call_index(n)]`, or its position in the block
+/// otherwise. Two calls cannot share an index. With `#[macros::call(strict)]`, every call must be
+/// given an explicit index, so that reordering the functions does not change the calls.
+///
+/// With `#[macros::call(helpers)]`, only the functions which are `pub` or marked with `#[call]`
+/// become calls. The other functions are left as they are, so the helpers of the calls can be
+/// written next to them.
+///
+/// The errors the calls can return are declared with `#[macros::call(errors("Not enough funds.", ..))]`,
+/// including the ones propagated with `?` from other functions. Since the macro cannot follow
+/// these, it only checks that the string literals given to `Err(..)`, `ok_or(..)` and
+/// `ensure!(.., ..)` in the body of each call are declared. The arguments can be combined, like
+/// `#[macros::call(strict, helpers, errors(..))]`.
+///
+/// The block can have other generics than `T`, which must come first, like the instance of pallets
+/// which can be included more than once in a runtime:
+/// `impl<T: Config<I>, I: support::Instance> Pallet<T, I>`. The generated code then has the same
+/// generics and where clause. Since the arguments are stored in the `Call` enum, they must be owned
+/// types or `'static` references, and functions cannot have generics of their own. An argument
+/// holding a call of the runtime, like the call of a scheduled task, must be a `Box` or a `Vec` of
+/// `RuntimeCall`: `RuntimeCall` holds the `Call` enum, which would otherwise contain itself. Such
+/// functions take the `Box` by value, so they allow `clippy::boxed_local`. These arguments are
+/// marked with `#[runtime_call]`, like `#[runtime_call] call: Box<T::RuntimeCall>`.
+///
+/// This generates:
+/// - `enum Call<T>` - with one variant per function, containing all of its other arguments, and
+///   whose discriminant is the call index. Each variant has the doc comments of its function. A
+///   hidden variant, which cannot be created, uses the
+///   generics which the arguments do not. It implements `Debug`, `Clone`, `PartialEq`, `Eq` and
+///   `Hash` when the types of all the arguments do, `Display` like `transfer(to=bob, amount=20)`
+///   when they implement `Debug`, with the arguments marked with `#[runtime_call]` written through
+///   `support::FormatCallArg`, `support::storage::Encode` as the call index followed by the
+///   arguments when they all implement it, `fn call_index()` and `fn call_name()`.
+/// - implements the trait `support::RuntimeDispatch` on the pallet, to dispatch a `Call` to the
+///   appropriate function.
+/// - implements the trait `support::Dispatch` on the pallet, when none of the functions need the
+///   whole runtime.
+/// - `fn call_metadata()` and `fn error_metadata()` - which describe the calls, along with their doc
+///   comments, and the declared errors.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -11,20 +62,61 @@ pub fn call(
 
 /// Expand the `Runtime` definition.
 ///
+/// The struct can have any name, and its first field must be the `system` pallet. The attribute
+/// takes optional arguments, for example `#[macros::runtime(block = types::Block, system = system)]`:
+/// - `block` - the type of the blocks executed by the runtime, `crate::types::Block` by default.
+/// - `system` - the path of the module of the system pallet, `crate::system` by default.
+/// - `migrations` - the `support::OnRuntimeUpgrade` migrations run by `fn upgrade()`, none by
+///   default.
+/// - `call` - the name of the generated enum of all the calls, `RuntimeCall` by default. Several
+///   runtimes in the same module must be given different names.
+/// - `call_filter` - the `support::CallFilter` consulted before dispatching any call not made by
+///   the `Root` origin, `support::Everything` by default. The extrinsics of a block are always
+///   dispatched with a signed origin, so only the runtime itself can make `Root` calls. Filtered
+///   calls fail with the error of `CallFilter::check`, `DispatchError::Filtered` unless the filter
+///   overrides it.
+/// - `fee` - the `support::ChargeFee` charging the caller of every extrinsic before it is
+///   dispatched, `()` by default, which charges nothing. Extrinsics whose caller cannot pay the fee
+///   are skipped.
+/// - `authorities` - the `support::ValidateAuthor` checking the author in the header of every block,
+///   `()` by default, which allows any author. Blocks with an invalid author are rejected.
+/// - `strict` - see below.
+///
+/// Each pallet is given an index in the runtime with `#[pallet_index(n)]`, or the position of its
+/// field otherwise. Two pallets cannot share an index. With `#[macros::runtime(strict)]`, every
+/// pallet must be given an explicit index, so that reordering the fields does not change the calls.
+///
 /// This generates function implementations on `Runtime`:
 /// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
 ///   included in the runtime.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
-///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   basic actions like checking the author of the block, incrementing the block number and
+///   checking the block to be executed has a valid block number. It then clears the events of every
+///   pallet, and calls `support::Hooks::on_initialize` on every pallet, before
+///   charging the fee of each extrinsic and dispatching it.
+/// - `fn upgrade()` - which runs the migrations of the runtime, in order. The whole upgrade is
+///   reverted if any of them fails.
+/// - `fn metadata()` - which gathers the metadata of every pallet, generated by the other macros,
+///   into a `support::metadata::RuntimeMetadata`.
+///
+/// It also implements `support::GetPallet` on `Runtime` for every pallet, including system, so that
+/// pallets can access each other through the runtime, and, in tests, `support::storage::RuntimeStorage`,
+/// which gathers the storage of every pallet. Every pallet must therefore declare its storage with
+/// `#[macros::storage]`. A pallet with instances can be included
+/// several times, like `rewards: balances::Pallet<Self, support::Instance1>`, and every field gets
+/// its own variant of `RuntimeCall`.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
 /// - Note: For simplicity, we assume that the system pallet is not callable.
-/// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+/// - `enum RuntimeCall`, or the name given with `call` - an "outer"-enum representing the accumulation of all possible calls to
+///   all pallets, whose discriminant is the pallet index. The system pallet is not included. It
+///   implements `Debug`, `Clone`, `PartialEq`, `Eq`, `Hash`, and `Display` like
+///   `balances.transfer(to=bob, amount=20)`, and `support::storage::Encode` as the pallet index
+///   followed by the encoding of the call in its pallet. It implements `support::GetCallName`, with the name
+///   of the field of the pallet and the name of the call, described by the metadata of the pallet.
+/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet, through
+///   the `support::RuntimeDispatch` implementation of that pallet. The system pallet is not
+///   included.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
@@ -32,3 +124,50 @@ pub fn runtime(
 ) -> proc_macro::TokenStream {
 	runtime::runtime(attr, item)
 }
+
+/// Describe the events of a pallet.
+///
+/// This is placed on the `Event<T>` enum of a pallet, and generates `fn event_metadata()` on the
+/// `Pallet<T>` struct of the same module, which describes every variant of the enum. The struct
+/// must store the events in a field named `events`, which the generated `fn clear_events()` clears
+/// at the beginning of every block.
+#[proc_macro_attribute]
+pub fn event(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	event::event(attr, item)
+}
+
+/// Declare the storage of a pallet.
+///
+/// This is placed on the `Pallet<T>` struct. Fields whose type is `StorageValue<Value>`,
+/// `StorageMap<Key, Value>` or `StorageDoubleMap<Key1, Key2, Value>` become storage items, backed by
+/// the types of `support::storage` and named after the field. These types do not need to be
+/// imported, since the macro rewrites them to their full path. By default, querying an item returns
+/// `None` when nothing is stored. An item can instead be given a default value with
+/// `#[default(expr)]`, which queries then return.
+///
+/// The version of the storage can be given with `#[macros::storage(version = 1)]`, and defaults to
+/// zero. It is recorded in a `storage_version` item, which migrations use to know whether they need
+/// to run.
+///
+/// If the struct has a second type parameter, like `Pallet<T: Config<I>, I: support::Instance = ()>`,
+/// it is the instance of the pallet, and the prefix of the instance is added to the keys of the
+/// storage items, so several instances can be included in the same runtime.
+///
+/// This generates:
+/// - `fn new()` - which creates the pallet with empty storage items, except for the current version
+///   of the storage. The other fields of the
+///   pallet are created with `Default::default()`.
+/// - `fn storage_entries()` - which returns the encoded keys and values of all the storage items,
+///   prefixed by the names of the pallet and of the item, when they can be encoded.
+/// - `fn storage_metadata()` - which describes the storage items.
+/// - implements the trait `support::storage::GetStorageVersion` on the pallet.
+#[proc_macro_attribute]
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	storage::storage(attr, item)
+}
@@ -1,14 +1,31 @@
-use super::parse::RuntimeDef;
+use super::parse::{RuntimeArgs, RuntimeDef};
 use quote::quote;
 
 /// See the `fn runtime` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
-	let RuntimeDef { runtime_struct, pallets } = def;
+	let RuntimeDef { runtime_struct, args, system, pallets } = def;
+	let RuntimeArgs { block, system: system_path, migrations, call: runtime_call, call_filter, fee, authorities, .. } = args;
+	let system_type = &system.ty;
+	let system_index = system.index;
 
 	// This is a vector of all the pallet names, not including system.
-	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+	let pallet_names = pallets.iter().map(|pallet| pallet.name.clone()).collect::<Vec<_>>();
 	// This is a vector of all the pallet types, not including system.
-	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	let pallet_types = pallets.iter().map(|pallet| pallet.ty.clone()).collect::<Vec<_>>();
+
+	// This is a vector of all the pallet indices, not including system.
+	let pallet_indices = pallets.iter().map(|pallet| pallet.index).collect::<Vec<_>>();
+	// This is a vector of the types of the calls of all the pallets, not including system. Pallet
+	// types usually refer to the runtime as `Self`, which is only valid in the implementations of
+	// the runtime, so we replace it with the name of the runtime.
+	let pallet_runtime_types = pallet_types
+		.iter()
+		.map(|ty| replace_self(quote!(#ty), &runtime_struct))
+		.collect::<Vec<_>>();
+	let pallet_calls = pallet_runtime_types
+		.iter()
+		.map(|ty| quote!(<#ty as crate::support::RuntimeDispatch<#runtime_struct>>::Call))
+		.collect::<Vec<_>>();
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
@@ -17,7 +34,7 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 			fn new() -> Self {
 				Self {
 					// Since system is not included in the list of pallets, we manually add it here.
-					system: <system::Pallet::<Self>>::new(),
+					system: <#system_type>::new(),
 					#(
 						#pallet_names: <#pallet_types>::new()
 					),*
@@ -25,14 +42,44 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 			}
 
 			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+			fn execute_block(&mut self, block: #block) -> crate::support::DispatchResult {
+				// The author is checked against the state before the block, so a block with an
+				// invalid author leaves the state untouched.
+				<#authorities as crate::support::ValidateAuthor<Self, _>>::validate_author(self, &block.header.author)?;
 				self.system.inc_block_number();
 				if block.header.block_number != self.system.block_number() {
-					return Err(&"block number does not match what is expected")
+					return Err("block number does not match what is expected".into())
+				}
+				// The events of every pallet only describe the current block, so the events of the
+				// previous block are cleared before anything can emit new ones.
+				{
+					use crate::support::DefaultEvents as _;
+					self.system.clear_events();
+					#( self.#pallet_names.clear_events(); )*
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
+				// Give every pallet the chance to execute logic before the extrinsics.
+				#(
+					<#pallet_types as crate::support::Hooks<Self, _>>::on_initialize(
+						self,
+						block.header.block_number,
+					);
+				)*
+				for (i, crate::support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
 					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
+					// The fee is charged even if the call then fails, but an extrinsic whose caller
+					// cannot pay it is not dispatched at all.
+					let fee = <#fee as crate::support::ChargeFee<Self, _, #runtime_call>>::charge_fee(self, &caller, &call);
+					if let Err(e) = fee {
+						eprintln!(
+							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
+							block.header.block_number, i, e
+						);
+						continue;
+					}
+					// Extrinsics are always signed by their caller, so they can never have the `Root`
+					// origin, whatever the account of the caller is.
+					let origin = crate::support::ensure::RawOrigin::Signed(caller);
+					let _res = <Self as crate::support::Dispatch>::dispatch(self, origin, call).map_err(|e| {
 						eprintln!(
 							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
 							block.header.block_number, i, e
@@ -41,39 +88,175 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				}
 				Ok(())
 			}
+
+			// Describe the pallets of the runtime, with their calls, storage, events and errors.
+			pub fn metadata() -> crate::support::metadata::RuntimeMetadata {
+				// Pallets which do not use some of the macros get empty metadata from this trait.
+				use crate::support::metadata::DefaultMetadata as _;
+
+				let mut pallets = vec![
+					crate::support::metadata::PalletMetadata {
+						name: "system",
+						index: #system_index,
+						calls: <#system_type>::call_metadata(),
+						storage: <#system_type>::storage_metadata(),
+						events: <#system_type>::event_metadata(),
+						errors: <#system_type>::error_metadata(),
+					},
+					#(
+						crate::support::metadata::PalletMetadata {
+							name: stringify!(#pallet_names),
+							index: #pallet_indices,
+							calls: <#pallet_types>::call_metadata(),
+							storage: <#pallet_types>::storage_metadata(),
+							events: <#pallet_types>::event_metadata(),
+							errors: <#pallet_types>::error_metadata(),
+						},
+					)*
+				];
+				pallets.sort_by_key(|pallet| pallet.index);
+				crate::support::metadata::RuntimeMetadata { pallets }
+			}
+
+			// Upgrade the state of the runtime by running its migrations, in order. If any of them
+			// fails its checks, the whole upgrade is reverted.
+			fn upgrade(&mut self) -> crate::support::DispatchResult {
+				crate::support::transactional(self, |runtime| {
+					<#migrations as crate::support::OnRuntimeUpgrade<Self>>::try_upgrade(runtime)
+				})
+			}
 		}
 	};
 
-	// This quote block implements the `RuntimeCall` enum and implements the `Dispatch` trait.
+	// This quote block gives access to each pallet through the `Runtime` struct, so that pallets can
+	// interact with each other.
+	let get_pallet_impl = quote! {
+		impl crate::support::GetPallet<#system_type> for #runtime_struct {
+			fn pallet(&self) -> &#system_type {
+				&self.system
+			}
+
+			fn pallet_mut(&mut self) -> &mut #system_type {
+				&mut self.system
+			}
+		}
+
+		#(
+			impl crate::support::GetPallet<#pallet_types> for #runtime_struct {
+				fn pallet(&self) -> &#pallet_types {
+					&self.#pallet_names
+				}
+
+				fn pallet_mut(&mut self) -> &mut #pallet_types {
+					&mut self.#pallet_names
+				}
+			}
+		)*
+	};
+
+	// This quote block implements the `RuntimeCall` enum, named after the `call` argument, and
+	// implements the `Dispatch` trait.
 	let dispatch_impl = quote! {
 		// These are all the calls which are exposed to the world.
 		// Note that it is just an accumulation of the calls exposed by each pallet.
 		//
-		// The parsed function names will be `snake_case`, and that will show up in the enum.
+		// The parsed function names will be `snake_case`, and that will show up in the enum. The
+		// discriminant of each variant is the index of its pallet, so it does not depend on the
+		// order of the pallets.
 		#[allow(non_camel_case_types)]
-		pub enum RuntimeCall {
-			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
+		#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+		#[repr(u8)]
+		pub enum #runtime_call {
+			#( #pallet_names(#pallet_calls) = #pallet_indices ),*
+		}
+
+		impl #runtime_call {
+			// Get the index of the pallet of the call.
+			pub fn pallet_index(&self) -> u8 {
+				match self {
+					#( #runtime_call::#pallet_names(_) => #pallet_indices, )*
+				}
+			}
+		}
+
+		// Calls are encoded as the index of their pallet followed by the encoding of the call in its
+		// pallet, which starts with its call index.
+		impl crate::support::storage::Encode for #runtime_call {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				crate::support::storage::Encode::encode_to(&self.pallet_index(), dest);
+				match self {
+					#( #runtime_call::#pallet_names(call) => crate::support::storage::Encode::encode_to(call, dest), )*
+				}
+			}
+		}
+
+		impl crate::support::GetCallName for #runtime_call {
+			fn pallet_name(&self) -> &'static str {
+				match self {
+					#( #runtime_call::#pallet_names(_) => stringify!(#pallet_names), )*
+				}
+			}
+
+			fn call_name(&self) -> &'static str {
+				match self {
+					#( #runtime_call::#pallet_names(call) => call.call_name(), )*
+				}
+			}
+
+			fn call_names() -> Vec<(&'static str, &'static str)> {
+				// Pallets which do not use `#[macros::call]` have no calls to describe.
+				use crate::support::metadata::DefaultMetadata as _;
+
+				let mut names = Vec::new();
+				#(
+					let calls = <#pallet_runtime_types>::call_metadata().into_iter();
+					names.extend(calls.map(|call| (stringify!(#pallet_names), call.name)));
+				)*
+				names
+			}
+		}
+
+		// Calls are written as the name of their pallet followed by their own `Display`, like
+		// `balances.transfer(to=bob, amount=20)`.
+		impl core::fmt::Display for #runtime_call {
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						#runtime_call::#pallet_names(call) => {
+							write!(f, "{}.", stringify!(#pallet_names))?;
+							core::fmt::Display::fmt(call, f)
+						},
+					)*
+				}
+			}
 		}
 
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
-			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			type Caller = crate::support::ensure::RawOrigin<<#runtime_struct as #system_path::Config>::AccountId>;
+			type Call = #runtime_call;
+			// Dispatch a call on behalf of a caller.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
-			// Note that we extract the `caller` from the extrinsic, and use that information
-			// to determine who we are executing the call on behalf of.
+			// Note that extrinsics are dispatched with the `Signed` origin of their `caller`, and
+			// only the runtime itself can dispatch calls with the `Root` origin.
 			fn dispatch(
 				&mut self,
 				caller: Self::Caller,
 				runtime_call: Self::Call,
 			) -> crate::support::DispatchResult {
+				// Calls made by `Root` bypass the filter, so governance can always fix the runtime.
+				if caller != crate::support::ensure::RawOrigin::Root {
+					<#call_filter as crate::support::CallFilter<Self, #runtime_call>>::check(self, &runtime_call)?;
+				}
+
 				// This match statement will allow us to correctly route `RuntimeCall`s
 				// to the appropriate pallet level call.
 				match runtime_call {
 					#(
-						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+						#runtime_call::#pallet_names(call) => {
+							<#pallet_types as crate::support::RuntimeDispatch<Self>>::dispatch(
+								self, caller, call,
+							)?;
 						}
 					),*
 				}
@@ -82,10 +265,45 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		}
 	};
 
+	// This quote block gathers the storage of every pallet, including system. Storage keys start
+	// with the name of their pallet, so the entries of different pallets never collide.
+	let storage_impl = quote! {
+		#[cfg(test)]
+		impl crate::support::storage::RuntimeStorage for #runtime_struct {
+			fn storage_entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+				let mut entries = self.system.storage_entries();
+				#(
+					entries.extend(self.#pallet_names.storage_entries());
+				)*
+				entries.sort();
+				entries
+			}
+		}
+	};
+
 	// We combine and return all the generated code.
 	quote! {
 		#dispatch_impl
 		#runtime_impl
+		#get_pallet_impl
+		#storage_impl
 	}
-	.into()
+}
+
+/// Replace every `Self` in `tokens` with `runtime`.
+fn replace_self(tokens: proc_macro2::TokenStream, runtime: &syn::Ident) -> proc_macro2::TokenStream {
+	tokens
+		.into_iter()
+		.map(|token| match token {
+			proc_macro2::TokenTree::Ident(ident) if ident == "Self" => {
+				proc_macro2::TokenTree::Ident(runtime.clone())
+			},
+			proc_macro2::TokenTree::Group(group) => {
+				let mut replaced = proc_macro2::Group::new(group.delimiter(), replace_self(group.stream(), runtime));
+				replaced.set_span(group.span());
+				proc_macro2::TokenTree::Group(replaced)
+			},
+			token => token,
+		})
+		.collect()
 }
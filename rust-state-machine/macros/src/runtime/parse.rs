@@ -3,15 +3,99 @@ use syn::spanned::Spanned;
 /// This object will collect all the information we need to keep while parsing the `Runtime` struct.
 #[derive(Debug)]
 pub struct RuntimeDef {
-	/// This is the name of the struct used by the user. We mostly assume it is `Runtime`.
+	/// This is the name of the struct used by the user.
 	pub runtime_struct: syn::Ident,
+	/// The arguments of the attribute. See `RuntimeArgs`.
+	pub args: RuntimeArgs,
+	/// The `system` pallet, which is the first field of the `Runtime` struct.
+	pub system: PalletDef,
 	/// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
 	/// list, but during parsing we check that system exists.
-	pub pallets: Vec<(syn::Ident, syn::Type)>,
+	pub pallets: Vec<PalletDef>,
+}
+
+/// The arguments of `#[macros::runtime(..)]`, which are all optional.
+#[derive(Debug)]
+pub struct RuntimeArgs {
+	/// The type of the blocks the runtime executes, `crate::types::Block` by default.
+	pub block: syn::Type,
+	/// The path of the module of the system pallet, `crate::system` by default.
+	pub system: syn::Path,
+	/// The migrations run by `upgrade`, none by default.
+	pub migrations: syn::Type,
+	/// The name of the generated enum of all the calls, `RuntimeCall` by default.
+	pub call: syn::Ident,
+	/// The `support::CallFilter` consulted before dispatching calls, `support::Everything` by
+	/// default.
+	pub call_filter: syn::Type,
+	/// The `support::ChargeFee` charging the caller of every extrinsic, `()` by default.
+	pub fee: syn::Type,
+	/// The `support::ValidateAuthor` checking the author of every block, `()` by default.
+	pub authorities: syn::Type,
+	/// Whether every pallet must be given an explicit index.
+	pub strict: bool,
+}
+
+impl RuntimeArgs {
+	/// Parse `block = <type>`, `system = <path>`, `migrations = <type>`, `call = <ident>`,
+	/// `call_filter = <type>`, `fee = <type>`, `authorities = <type>` and `strict`, in any order.
+	fn parse(attr: proc_macro2::TokenStream) -> syn::Result<Self> {
+		let mut args = Self {
+			block: syn::parse_quote!(crate::types::Block),
+			system: syn::parse_quote!(crate::system),
+			migrations: syn::parse_quote!(()),
+			call: syn::parse_quote!(RuntimeCall),
+			call_filter: syn::parse_quote!(crate::support::Everything),
+			fee: syn::parse_quote!(()),
+			authorities: syn::parse_quote!(()),
+			strict: false,
+		};
+		let parser = syn::meta::parser(|meta| {
+			if meta.path.is_ident("block") {
+				args.block = meta.value()?.parse()?;
+			} else if meta.path.is_ident("system") {
+				args.system = meta.value()?.parse()?;
+			} else if meta.path.is_ident("migrations") {
+				args.migrations = meta.value()?.parse()?;
+			} else if meta.path.is_ident("call") {
+				args.call = meta.value()?.parse()?;
+			} else if meta.path.is_ident("call_filter") {
+				args.call_filter = meta.value()?.parse()?;
+			} else if meta.path.is_ident("fee") {
+				args.fee = meta.value()?.parse()?;
+			} else if meta.path.is_ident("authorities") {
+				args.authorities = meta.value()?.parse()?;
+			} else if meta.path.is_ident("strict") {
+				args.strict = true;
+			} else {
+				let msg = "Invalid runtime, expected `block = ..`, `system = ..`, `migrations = ..`, \
+					`call = ..`, `call_filter = ..`, `fee = ..`, `authorities = ..` or `strict`";
+				return Err(meta.error(msg))
+			}
+			Ok(())
+		});
+		syn::parse::Parser::parse2(parser, attr)?;
+		Ok(args)
+	}
+}
+
+/// This is the metadata we keep about each pallet of the runtime.
+#[derive(Debug)]
+pub struct PalletDef {
+	/// The name of the field of the pallet.
+	pub name: syn::Ident,
+	/// The type of the pallet.
+	pub ty: syn::Type,
+	/// The index of the pallet in the runtime, given with `#[pallet_index(..)]`, or the position of
+	/// the field otherwise.
+	pub index: u8,
 }
 
 impl RuntimeDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	/// Parse the pallets of `item`, removing the `#[pallet_index(..)]` attributes from it.
+	pub fn try_from(attr: proc_macro2::TokenStream, item: &mut syn::Item) -> syn::Result<Self> {
+		let args = RuntimeArgs::parse(attr)?;
+
 		// First we check that we are parsing a `struct`.
 		let item_struct = if let syn::Item::Struct(item) = item {
 			item
@@ -19,24 +103,69 @@ impl RuntimeDef {
 			return Err(syn::Error::new(item.span(), "Invalid runtime, expected item struct"))
 		};
 
+		// We remove all the `#[pallet_index(..)]` attributes before anything else, so that the item
+		// we give back does not contain them even if the pallets are invalid.
+		let pallet_indices = item_struct.fields.iter_mut().map(take_pallet_index).collect::<Vec<_>>();
+
 		// We check that the `Runtime` includes the `system` pallet as the first item.
-		check_system(&item_struct)?;
+		check_system(item_struct)?;
 
-		let runtime_struct = item_struct.ident;
+		let runtime_struct = item_struct.ident.clone();
 
-		// Here is where we will store a list of all the pallets.
-		let mut pallets = vec![];
-		// We skip `system`, which we ensure is the first field in `check_system`.
-		for field in item_struct.fields.into_iter().skip(1) {
-			if let Some(ident) = field.ident {
-				pallets.push((ident, field.ty))
+		// Here is where we will store a list of all the pallets, including system for now.
+		let mut pallets: Vec<PalletDef> = vec![];
+		for ((position, field), index) in item_struct.fields.iter().enumerate().zip(pallet_indices) {
+			let name = field.ident.clone().expect("fields are named, which `check_system` ensures; qed");
+			let index = match index? {
+				Some(index) => index,
+				None if args.strict => {
+					let msg = "Invalid runtime, missing `#[pallet_index(..)]`, which is required in strict \
+						mode";
+					return Err(syn::Error::new(name.span(), msg))
+				},
+				None => u8::try_from(position)
+					.map_err(|_| syn::Error::new(name.span(), "Invalid runtime, too many pallets"))?,
+			};
+			if let Some(other) = pallets.iter().find(|other| other.index == index) {
+				let msg = format!("Invalid runtime, pallet index {} is already used by `{}`", index, other.name);
+				return Err(syn::Error::new(name.span(), msg))
 			}
+			pallets.push(PalletDef { name, ty: field.ty.clone(), index });
 		}
 
-		Ok(Self { runtime_struct, pallets })
+		// We remove `system`, which we ensure is the first field in `check_system`.
+		let system = pallets.remove(0);
+
+		Ok(Self { runtime_struct, args, system, pallets })
 	}
 }
 
+/// Remove the `#[pallet_index(..)]` attribute of a field, and return its index.
+fn take_pallet_index(field: &mut syn::Field) -> syn::Result<Option<u8>> {
+	let mut pallet_index = None;
+	let mut result = Ok(());
+	field.attrs.retain(|attr| {
+		if !attr.path().is_ident("pallet_index") {
+			return true
+		}
+		if pallet_index.is_some() {
+			let msg = "Invalid runtime, duplicate `#[pallet_index(..)]` attribute";
+			result = Err(syn::Error::new(attr.span(), msg));
+		} else {
+			match attr.parse_args::<syn::LitInt>().and_then(|index| index.base10_parse::<u8>()) {
+				Ok(index) => pallet_index = Some(index),
+				Err(e) => {
+					let msg = "Invalid runtime, expected a pallet index between 0 and 255, like \
+						`#[pallet_index(1)]`";
+					result = Err(syn::Error::new(e.span(), msg))
+				},
+			}
+		}
+		false
+	});
+	result.map(|_| pallet_index)
+}
+
 /// This function checks that the `system` pallet is the first pallet included in the `Runtime`
 /// struct. We make many assumptions about the `system` pallet in order to keep these macros simple.
 /// For example, we assume that the system pallet has no callable functions, and that it contains
@@ -44,23 +173,32 @@ impl RuntimeDef {
 ///
 /// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
 fn check_system(item_struct: &syn::ItemStruct) -> syn::Result<()> {
-	// Extract the name of the first field in the `Runtime` struct.
-	let first_field_name = if let Some(first_field) = item_struct.fields.iter().next() {
-		if let Some(field_name) = &first_field.ident {
-			field_name.to_string()
-		} else {
-			let msg = "first field is expected to have the name system";
-			return Err(syn::Error::new(item_struct.span(), msg))
-		}
-	} else {
-		let msg = "runtime struct is expected to have fields";
-		return Err(syn::Error::new(item_struct.span(), msg))
+	let expected = "expected the first field to be the system pallet, like `system: system::Pallet<Self>`";
+
+	// Extract the first field in the `Runtime` struct.
+	let first_field = match &item_struct.fields {
+		syn::Fields::Named(fields) => match fields.named.first() {
+			Some(field) => field,
+			None => {
+				let msg = format!("Invalid runtime, the struct has no fields; {}", expected);
+				return Err(syn::Error::new(fields.brace_token.span.join(), msg))
+			},
+		},
+		syn::Fields::Unnamed(fields) => {
+			let msg = format!("Invalid runtime, the fields must be named after their pallet; {}", expected);
+			return Err(syn::Error::new(fields.span(), msg))
+		},
+		syn::Fields::Unit => {
+			let msg = format!("Invalid runtime, the struct has no fields; {}", expected);
+			return Err(syn::Error::new(item_struct.ident.span(), msg))
+		},
 	};
 
 	// Check if the first field is named "system"
-	if first_field_name != "system" {
-		let msg = "first field is expected to be named system";
-		return Err(syn::Error::new(item_struct.span(), msg))
+	let name = first_field.ident.as_ref().expect("fields are named; qed");
+	if name != "system" {
+		let msg = format!("Invalid runtime, found `{}`; {}", name, expected);
+		return Err(syn::Error::new(name.span(), msg))
 	}
 
 	Ok(())
@@ -0,0 +1,176 @@
+use super::parse::{StorageDef, StorageItemDef, StorageKind};
+use quote::quote;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_storage(def: StorageDef) -> proc_macro2::TokenStream {
+	let StorageDef { mut item_struct, mut items, mut other_fields, version, instance } = def;
+
+	// Every pallet records the version of its storage in the storage itself.
+	if let syn::Fields::Named(fields) = &mut item_struct.fields {
+		fields.named.push(syn::parse_quote! {
+			/// The version of the storage as recorded in the state.
+			storage_version: StorageValue<crate::support::storage::StorageVersion>
+		});
+	}
+	// The instance of the pallet is only used in the bounds of the struct, so we add a field which
+	// uses it.
+	if let (Some(instance), syn::Fields::Named(fields)) = (&instance, &mut item_struct.fields) {
+		fields.named.push(syn::parse_quote! {
+			/// The instance of the pallet.
+			_instance: core::marker::PhantomData<#instance>
+		});
+		other_fields.push(syn::Ident::new("_instance", proc_macro2::Span::call_site()));
+	}
+	items.push(StorageItemDef {
+		name: syn::Ident::new("storage_version", proc_macro2::Span::call_site()),
+		kind: StorageKind::Value,
+		args: vec![syn::parse_quote!(crate::support::storage::StorageVersion)],
+		default: Some(syn::parse_quote!(crate::support::storage::StorageVersion::new(0))),
+		docs: vec!["The version of the storage as recorded in the state.".to_string()],
+	});
+
+	// Rewrite the type of every storage item to the full type from `support::storage`, which also
+	// says what queries return.
+	let storage_types = items
+		.iter()
+		.map(|item| {
+			let kind = item.kind.ident();
+			let args = &item.args;
+			let query = if item.default.is_some() {
+				quote!(crate::support::storage::ValueQuery)
+			} else {
+				quote!(crate::support::storage::OptionQuery)
+			};
+			quote!(crate::support::storage::#kind<#( #args, )* #query>)
+		})
+		.collect::<Vec<_>>();
+	for field in item_struct.fields.iter_mut() {
+		if let Some(index) = items.iter().position(|item| Some(&item.name) == field.ident.as_ref()) {
+			field.ty = syn::parse2(storage_types[index].clone()).expect("this is a valid type; qed");
+		}
+	}
+
+	let storage_names = items.iter().map(|item| item.name.clone()).collect::<Vec<_>>();
+	let storage_kinds = items.iter().map(|item| item.kind.ident()).collect::<Vec<_>>();
+	let storage_prefixes = storage_names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+	// Items without a default value use `OptionQuery`, which needs none.
+	let storage_defaults = items
+		.iter()
+		.map(|item| match &item.default {
+			Some(default) => quote!((|| #default) as fn() -> _),
+			None => quote!(()),
+		})
+		.collect::<Vec<_>>();
+
+	// Every instance of the pallet has its own prefix, so their storage does not collide.
+	let instance_prefix = match &instance {
+		Some(instance) => quote!(<#instance as crate::support::Instance>::PREFIX),
+		None => quote!(""),
+	};
+
+	let pallet_struct = &item_struct.ident;
+	let (impl_generics, ty_generics, where_clause) = item_struct.generics.split_for_impl();
+
+	// This quote block implements functions on the pallet struct.
+	let pallet_impl = quote! {
+		impl #impl_generics #pallet_struct #ty_generics #where_clause {
+			/// Create a new instance of this pallet, with nothing in its storage but the current
+			/// version of the storage.
+			pub fn new() -> Self {
+				let mut pallet = Self {
+					#(
+						#storage_names: crate::support::storage::#storage_kinds::new(
+							crate::support::storage::StoragePrefix {
+								pallet: module_path!(),
+								instance: #instance_prefix,
+								item: #storage_prefixes,
+							},
+							#storage_defaults,
+						),
+					)*
+					#(
+						#other_fields: Default::default(),
+					)*
+				};
+				pallet.storage_version.put(crate::support::storage::StorageVersion::new(#version));
+				pallet
+			}
+
+			/// Get the encoded `(key, value)` of every value in the storage of this pallet, ordered
+			/// by key. This is only available when all the keys and values can be encoded.
+			pub fn storage_entries(&self) -> Vec<(Vec<u8>, Vec<u8>)>
+			where
+				#( #storage_types: crate::support::storage::StorageItem, )*
+			{
+				let mut entries = Vec::new();
+				#(
+					entries.extend(crate::support::storage::StorageItem::entries(&self.#storage_names));
+				)*
+				entries.sort();
+				entries
+			}
+		}
+	};
+
+	// The metadata of each storage item. The last generic argument of each kind is the value, and
+	// the other ones are the keys.
+	let storage_metadata = items
+		.iter()
+		.map(|item| {
+			let name = item.name.to_string();
+			let kind = match item.kind {
+				StorageKind::Value => quote!(Value),
+				StorageKind::Map => quote!(Map),
+				StorageKind::DoubleMap => quote!(DoubleMap),
+			};
+			let (value, keys) = item.args.split_last().expect("storage items have a value; qed");
+			let has_default = item.default.is_some();
+			let docs = &item.docs;
+			quote! {
+				crate::support::metadata::StorageEntryMetadata {
+					name: #name,
+					kind: crate::support::metadata::StorageKind::#kind,
+					keys: vec![ #( core::any::type_name::<#keys>() ),* ],
+					value: core::any::type_name::<#value>(),
+					has_default: #has_default,
+					docs: vec![ #( #docs ),* ],
+				}
+			}
+		})
+		.collect::<Vec<_>>();
+	let metadata_impl = quote! {
+		impl #impl_generics #pallet_struct #ty_generics #where_clause {
+			/// Get the metadata of the storage items of this pallet.
+			pub fn storage_metadata() -> Vec<crate::support::metadata::StorageEntryMetadata> {
+				vec![ #( #storage_metadata ),* ]
+			}
+		}
+	};
+
+	// This quote block gives access to the version of the storage.
+	let storage_version_impl = quote! {
+		impl #impl_generics crate::support::storage::GetStorageVersion for #pallet_struct #ty_generics
+		#where_clause
+		{
+			fn current_storage_version() -> crate::support::storage::StorageVersion {
+				crate::support::storage::StorageVersion::new(#version)
+			}
+
+			fn on_chain_storage_version(&self) -> crate::support::storage::StorageVersion {
+				self.storage_version.get()
+			}
+
+			fn set_on_chain_storage_version(&mut self, version: crate::support::storage::StorageVersion) {
+				self.storage_version.put(version);
+			}
+		}
+	};
+
+	quote! {
+		#item_struct
+
+		#pallet_impl
+		#metadata_impl
+		#storage_version_impl
+	}
+}
@@ -0,0 +1,18 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let item_mod = syn::parse_macro_input!(item as syn::Item);
+
+	// Unlike the other macros, this one rewrites the pallet struct, so we do not keep the original
+	// item. We first parse the storage items of the struct...
+	match parse::StorageDef::try_from(attr.into(), item_mod) {
+		// ..then we generate the rewritten struct along with our new code.
+		Ok(def) => expand::expand_storage(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	}
+}
@@ -0,0 +1,194 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the pallet struct.
+#[derive(Debug)]
+pub struct StorageDef {
+	/// The pallet struct, with the `#[default(..)]` attributes of its fields removed. The types of
+	/// the storage items are rewritten during the expansion.
+	pub item_struct: syn::ItemStruct,
+	/// The storage items of the pallet. See `StorageItemDef`.
+	pub items: Vec<StorageItemDef>,
+	/// The names of the other fields of the pallet, which are created with `Default::default()`.
+	pub other_fields: Vec<syn::Ident>,
+	/// The current version of the storage, given with `#[macros::storage(version = ..)]`.
+	pub version: u16,
+	/// The instance of the pallet, which is its second type parameter, like `I` in
+	/// `Pallet<T: Config<I>, I: Instance = ()>`.
+	pub instance: Option<syn::Ident>,
+}
+
+/// The kinds of storage items a pallet can declare.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageKind {
+	/// `StorageValue<Value>`
+	Value,
+	/// `StorageMap<Key, Value>`
+	Map,
+	/// `StorageDoubleMap<Key1, Key2, Value>`
+	DoubleMap,
+}
+
+impl StorageKind {
+	/// The name of the storage type in `support::storage`.
+	pub fn ident(&self) -> syn::Ident {
+		let name = match self {
+			StorageKind::Value => "StorageValue",
+			StorageKind::Map => "StorageMap",
+			StorageKind::DoubleMap => "StorageDoubleMap",
+		};
+		syn::Ident::new(name, proc_macro2::Span::call_site())
+	}
+
+	/// The number of generic arguments the storage type takes: the keys, and the value.
+	fn arg_count(&self) -> usize {
+		match self {
+			StorageKind::Value => 1,
+			StorageKind::Map => 2,
+			StorageKind::DoubleMap => 3,
+		}
+	}
+}
+
+/// This is the metadata we keep about each storage item of the pallet.
+#[derive(Debug)]
+pub struct StorageItemDef {
+	/// The name of the field, which is also the name of the item in the storage.
+	pub name: syn::Ident,
+	/// The kind of storage item.
+	pub kind: StorageKind,
+	/// The types of the keys (if any) and of the value, in order.
+	pub args: Vec<syn::Type>,
+	/// The value returned when nothing is stored, given with `#[default(..)]`.
+	pub default: Option<syn::Expr>,
+	/// The doc comments of the field, one line per item.
+	pub docs: Vec<String>,
+}
+
+impl StorageDef {
+	pub fn try_from(attr: proc_macro2::TokenStream, item: syn::Item) -> syn::Result<Self> {
+		let version = parse_version(attr)?;
+
+		// First we check that we are parsing a `struct`.
+		let mut item_struct = if let syn::Item::Struct(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::storage, expected item struct"))
+		};
+
+		let fields = if let syn::Fields::Named(fields) = &mut item_struct.fields {
+			fields
+		} else {
+			let msg = "Invalid pallet::storage, expected a struct with named fields";
+			return Err(syn::Error::new(item_struct.span(), msg))
+		};
+
+		let mut items = vec![];
+		let mut other_fields = vec![];
+		for field in fields.named.iter_mut() {
+			let name = field.ident.clone().expect("fields are named; qed");
+			if name == "storage_version" || name == "_instance" {
+				let msg = format!(
+					"Invalid pallet::storage, `{}` is reserved for the fields added by the macro",
+					name,
+				);
+				return Err(syn::Error::new(name.span(), msg))
+			}
+			let default = take_default_attr(field)?;
+
+			match storage_kind(&field.ty)? {
+				Some((kind, args)) => {
+					let docs = crate::utils::get_doc_literals(&field.attrs);
+					items.push(StorageItemDef { name, kind, args, default, docs })
+				},
+				None => {
+					if let Some(default) = default {
+						let msg = "Invalid pallet::storage, `#[default(..)]` is only supported on \
+							storage items";
+						return Err(syn::Error::new(default.span(), msg))
+					}
+					other_fields.push(name);
+				},
+			}
+		}
+
+		let instance = item_struct.generics.type_params().nth(1).map(|param| param.ident.clone());
+
+		Ok(Self { item_struct, items, other_fields, version, instance })
+	}
+}
+
+/// Parse the arguments of the attribute, which can only be `version = <u16>`. The version defaults
+/// to zero.
+fn parse_version(attr: proc_macro2::TokenStream) -> syn::Result<u16> {
+	let mut version = 0;
+	let parser = syn::meta::parser(|meta| {
+		if meta.path.is_ident("version") {
+			version = meta.value()?.parse::<syn::LitInt>()?.base10_parse()?;
+			Ok(())
+		} else {
+			Err(meta.error("Invalid pallet::storage, expected `version = ..`"))
+		}
+	});
+	syn::parse::Parser::parse2(parser, attr)?;
+	Ok(version)
+}
+
+/// Remove the `#[default(..)]` attribute of a field, and return its expression.
+fn take_default_attr(field: &mut syn::Field) -> syn::Result<Option<syn::Expr>> {
+	let mut default = None;
+	let mut attrs = vec![];
+	for attr in field.attrs.drain(..) {
+		if !attr.path().is_ident("default") {
+			attrs.push(attr);
+			continue
+		}
+		if default.is_some() {
+			let msg = "Invalid pallet::storage, duplicate `#[default(..)]` attribute";
+			return Err(syn::Error::new(attr.span(), msg))
+		}
+		default = Some(attr.parse_args::<syn::Expr>()?);
+	}
+	field.attrs = attrs;
+	Ok(default)
+}
+
+/// Find out whether `ty` is one of the storage types, and if so, extract its generic arguments.
+fn storage_kind(ty: &syn::Type) -> syn::Result<Option<(StorageKind, Vec<syn::Type>)>> {
+	let segment = match ty {
+		syn::Type::Path(tp) if tp.qself.is_none() => tp.path.segments.last().expect("paths are not empty; qed"),
+		_ => return Ok(None),
+	};
+
+	let kind = if segment.ident == "StorageValue" {
+		StorageKind::Value
+	} else if segment.ident == "StorageMap" {
+		StorageKind::Map
+	} else if segment.ident == "StorageDoubleMap" {
+		StorageKind::DoubleMap
+	} else {
+		return Ok(None)
+	};
+
+	let args = match &segment.arguments {
+		syn::PathArguments::AngleBracketed(args) => args
+			.args
+			.iter()
+			.map(|arg| match arg {
+				syn::GenericArgument::Type(ty) => Ok(ty.clone()),
+				_ => Err(syn::Error::new(arg.span(), "Invalid pallet::storage, expected a type")),
+			})
+			.collect::<syn::Result<Vec<_>>>()?,
+		_ => vec![],
+	};
+
+	if args.len() != kind.arg_count() {
+		let msg = format!(
+			"Invalid pallet::storage, `{}` expects {} generic arguments: the keys, then the value",
+			segment.ident,
+			kind.arg_count(),
+		);
+		return Err(syn::Error::new(segment.span(), msg))
+	}
+
+	Ok(Some((kind, args)))
+}
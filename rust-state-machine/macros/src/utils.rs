@@ -0,0 +1,16 @@
+//! Helpers shared by the macros.
+
+/// Get the lines of the doc comments in `attrs`, without the leading space.
+pub fn get_doc_literals(attrs: &[syn::Attribute]) -> Vec<String> {
+	attrs
+		.iter()
+		.filter_map(|attr| match &attr.meta {
+			syn::Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+				syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) => Some(lit.value()),
+				_ => None,
+			},
+			_ => None,
+		})
+		.map(|line| line.strip_prefix(' ').map(str::to_string).unwrap_or(line))
+		.collect()
+}
@@ -0,0 +1,9 @@
+// The errors of the macros, checked against the expected output of the compiler in `tests/ui`.
+//
+// Run with `TRYBUILD=overwrite` to update the expected output after changing an error.
+#[test]
+fn ui() {
+	let t = trybuild::TestCases::new();
+	t.compile_fail("tests/ui/call/*.rs");
+	t.compile_fail("tests/ui/runtime/*.rs");
+}
@@ -0,0 +1,14 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	pub fn transfer(&mut self, _caller: T::AccountId, (_a, _b): (u64, u64)) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
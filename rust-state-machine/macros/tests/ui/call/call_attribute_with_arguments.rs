@@ -0,0 +1,15 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call(helpers)]
+impl<T: Config> Pallet<T> {
+	#[call(index = 0)]
+	fn transfer(&mut self, _caller: T::AccountId, _amount: u64) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
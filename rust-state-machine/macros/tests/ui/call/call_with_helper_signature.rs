@@ -0,0 +1,19 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call(helpers)]
+impl<T: Config> Pallet<T> {
+	pub fn transfer(&mut self, _caller: T::AccountId, _amount: u64) -> Result<(), &'static str> {
+		self.check()
+	}
+
+	#[call]
+	fn check(&self) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
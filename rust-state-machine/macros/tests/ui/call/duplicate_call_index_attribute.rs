@@ -0,0 +1,16 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	#[call_index(0)]
+	#[call_index(1)]
+	pub fn transfer(&mut self, _caller: T::AccountId, _amount: u64) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+impl<R: Config> Pallet<R> {
+	pub fn transfer(&mut self, _caller: R::AccountId, _amount: u64) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[macros::call]
+pub fn transfer(_amount: u64) -> Result<(), &'static str> {
+	Ok(())
+}
+
+fn main() {}
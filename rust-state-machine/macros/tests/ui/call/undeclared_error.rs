@@ -0,0 +1,17 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call(errors("Not enough funds."))]
+impl<T: Config> Pallet<T> {
+	pub fn transfer(&mut self, _caller: T::AccountId, amount: u64) -> Result<(), &'static str> {
+		if amount == 0 {
+			return Err("Amount must not be zero");
+		}
+		Ok(())
+	}
+}
+
+fn main() {}
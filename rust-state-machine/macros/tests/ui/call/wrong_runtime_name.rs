@@ -0,0 +1,15 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	pub fn transfer(rt: &mut T, _caller: T::AccountId) -> Result<(), &'static str> {
+		let _ = rt;
+		Ok(())
+	}
+}
+
+fn main() {}
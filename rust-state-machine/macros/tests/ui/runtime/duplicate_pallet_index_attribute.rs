@@ -0,0 +1,17 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod balances {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+#[macros::runtime]
+pub struct Runtime {
+	system: system::Pallet<Self>,
+	#[pallet_index(1)]
+	#[pallet_index(2)]
+	balances: balances::Pallet<Self>,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod balances {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+#[macros::runtime]
+pub struct Runtime {
+	balances: balances::Pallet<Self>,
+	system: system::Pallet<Self>,
+}
+
+fn main() {}
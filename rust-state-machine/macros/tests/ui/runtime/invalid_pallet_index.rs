@@ -0,0 +1,16 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod balances {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+#[macros::runtime]
+pub struct Runtime {
+	system: system::Pallet<Self>,
+	#[pallet_index(balances)]
+	balances: balances::Pallet<Self>,
+}
+
+fn main() {}
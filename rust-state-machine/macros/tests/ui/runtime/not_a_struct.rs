@@ -0,0 +1,15 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod balances {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+#[macros::runtime]
+pub enum Runtime {
+	System(system::Pallet<()>),
+	Balances(balances::Pallet<()>),
+}
+
+fn main() {}
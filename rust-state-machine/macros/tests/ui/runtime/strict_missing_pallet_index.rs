@@ -0,0 +1,16 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod balances {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+#[macros::runtime(strict)]
+pub struct Runtime {
+	#[pallet_index(0)]
+	system: system::Pallet<Self>,
+	balances: balances::Pallet<Self>,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod balances {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+#[macros::runtime]
+pub struct Runtime(system::Pallet<()>, balances::Pallet<()>);
+
+fn main() {}
@@ -0,0 +1,403 @@
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+use crate::support::storage::Encode;
+use crate::support::{DispatchError, DispatchResult};
+
+pub trait Config: crate::system::Config + Clone {
+	/// The type used to identify an asset.
+	type AssetId: Debug + Ord + Copy;
+	/// The type of the balance of an asset. This is independent of the native balance of the
+	/// balances pallet.
+	type AssetBalance: Debug + Zero + CheckedSub + CheckedAdd + Copy + Ord;
+}
+
+/// The key of an approval: the asset, the owner of the funds, and the delegate allowed to
+/// transfer them.
+type ApprovalKey<T> = (
+	<T as Config>::AssetId,
+	<T as crate::system::Config>::AccountId,
+	<T as crate::system::Config>::AccountId,
+);
+
+/// The details of an asset.
+#[derive(Debug, Clone)]
+pub struct AssetDetails<T: Config> {
+	/// The account allowed to mint, burn and set the metadata of the asset.
+	pub admin: T::AccountId,
+	/// The total amount of the asset in existence.
+	pub supply: T::AssetBalance,
+	/// The smallest balance of the asset an account is allowed to hold, other than zero.
+	pub min_balance: T::AssetBalance,
+}
+
+impl<T: Config> Encode for AssetDetails<T>
+where
+	T::AccountId: Encode,
+	T::AssetBalance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.admin.encode_to(dest);
+		self.supply.encode_to(dest);
+		self.min_balance.encode_to(dest);
+	}
+}
+
+/// The metadata of an asset, used by UIs to display it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetMetadata {
+	/// The name of the asset.
+	pub name: String,
+	/// The ticker symbol of the asset.
+	pub symbol: String,
+	/// The number of decimals the balances of the asset are displayed with.
+	pub decimals: u8,
+}
+
+impl Encode for AssetMetadata {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.name.encode_to(dest);
+		self.symbol.encode_to(dest);
+		self.decimals.encode_to(dest);
+	}
+}
+
+/// This is the Assets Module.
+/// It keeps track of how much of many different assets each account has, alongside the native
+/// currency of the balances pallet.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from an asset to its details.
+	assets: StorageMap<T::AssetId, AssetDetails<T>>,
+	/// A map from an asset to its metadata.
+	metadata: StorageMap<T::AssetId, AssetMetadata>,
+	/// A map from an asset and an account to the balance of that account. Empty balances are
+	/// not stored.
+	#[default(Zero::zero())]
+	accounts: StorageDoubleMap<T::AssetId, T::AccountId, T::AssetBalance>,
+	/// A map from an asset, an owner and a delegate to the amount of the asset the delegate is
+	/// allowed to transfer on behalf of the owner.
+	#[default(Zero::zero())]
+	approvals: StorageMap<ApprovalKey<T>, T::AssetBalance>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the details (if any) of the asset `id`.
+	pub fn asset(&self, id: &T::AssetId) -> Option<&AssetDetails<T>> {
+		self.assets.try_get(id)
+	}
+
+	/// Get the metadata (if any) of the asset `id`.
+	pub fn metadata(&self, id: &T::AssetId) -> Option<&AssetMetadata> {
+		self.metadata.try_get(id)
+	}
+
+	/// Get the balance of the asset `id` held by `who`.
+	/// If the account has no stored balance, we return zero.
+	pub fn balance(&self, id: T::AssetId, who: &T::AccountId) -> T::AssetBalance {
+		self.accounts.get(&id, who)
+	}
+
+	/// Get the amount of the asset `id` which `delegate` is allowed to transfer on behalf of
+	/// `owner`.
+	pub fn allowance(
+		&self,
+		id: T::AssetId,
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+	) -> T::AssetBalance {
+		self.approvals.get(&(id, owner.clone(), delegate.clone()))
+	}
+
+	/// Get the details of the asset `id`, checking that `who` is its admin.
+	fn ensure_admin(&self, id: &T::AssetId, who: &T::AccountId) -> Result<&AssetDetails<T>, DispatchError> {
+		let details = self.asset(id).ok_or("Asset does not exist")?;
+		if &details.admin != who {
+			return Err("Caller is not the admin of the asset".into());
+		}
+		Ok(details)
+	}
+
+	/// Set the balance of the asset `id` held by `who`, removing the account once it is empty.
+	fn set_account_balance(&mut self, id: T::AssetId, who: &T::AccountId, amount: T::AssetBalance) {
+		if amount.is_zero() {
+			self.accounts.remove(&id, who);
+		} else {
+			self.accounts.insert(id, who.clone(), amount);
+		}
+	}
+
+	/// Transfer `amount` of the asset `id` from `from` to `to`.
+	/// This function verifies that `from` has at least `amount` balance to transfer, that neither
+	/// account is left with less than the minimum balance, and that no mathematical overflows occur.
+	fn do_transfer(
+		&mut self,
+		id: T::AssetId,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let details = self.asset(&id).ok_or("Asset does not exist")?;
+		let min_balance = details.min_balance;
+
+		let new_from_balance = self.balance(id, from).checked_sub(&amount).ok_or("Not enough funds.")?;
+		// A transfer to oneself changes no balance, and writing both balances would mint `amount`.
+		if from == to {
+			return Ok(());
+		}
+		let new_to_balance = self.balance(id, to).checked_add(&amount).ok_or("Overflow")?;
+
+		if !new_from_balance.is_zero() && new_from_balance < min_balance {
+			return Err("Balance below minimum".into());
+		}
+		if new_to_balance < min_balance {
+			return Err("Balance below minimum".into());
+		}
+
+		self.set_account_balance(id, from, new_from_balance);
+		self.set_account_balance(id, to, new_to_balance);
+
+		Ok(())
+	}
+}
+
+#[macros::call(errors(
+	"Asset already exists",
+	"Minimum balance must not be zero",
+	"Overflow",
+	"Balance below minimum",
+	"Not enough funds.",
+	"Asset does not exist",
+	"Not enough allowance",
+	"Caller is not the admin of the asset",
+))]
+impl<T: Config> Pallet<T> {
+	/// Create a new asset `id`, administrated by `admin`.
+	/// This function will return an error if the asset already exists, or if `min_balance` is
+	/// zero.
+	pub fn create(
+		&mut self,
+		_caller: T::AccountId,
+		id: T::AssetId,
+		admin: T::AccountId,
+		min_balance: T::AssetBalance,
+	) -> DispatchResult {
+		if self.assets.contains_key(&id) {
+			return Err("Asset already exists".into());
+		}
+		if min_balance.is_zero() {
+			return Err("Minimum balance must not be zero".into());
+		}
+
+		self.assets.insert(id, AssetDetails { admin, supply: Zero::zero(), min_balance });
+		Ok(())
+	}
+
+	/// Set the metadata of the asset `id`.
+	/// This function should only succeed if the caller is the admin of the asset.
+	pub fn set_metadata(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		name: String,
+		symbol: String,
+		decimals: u8,
+	) -> DispatchResult {
+		self.ensure_admin(&id, &caller)?;
+		self.metadata.insert(id, AssetMetadata { name, symbol, decimals });
+		Ok(())
+	}
+
+	/// Create `amount` of the asset `id` in the account of `beneficiary`.
+	/// This function should only succeed if the caller is the admin of the asset. It verifies that
+	/// `beneficiary` holds at least the minimum balance afterwards, and that no mathematical
+	/// overflows occur.
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		beneficiary: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let details = self.ensure_admin(&id, &caller)?;
+		let new_supply = details.supply.checked_add(&amount).ok_or("Overflow")?;
+		let min_balance = details.min_balance;
+
+		let new_balance = self.balance(id, &beneficiary).checked_add(&amount).ok_or("Overflow")?;
+		if new_balance < min_balance {
+			return Err("Balance below minimum".into());
+		}
+
+		self.set_account_balance(id, &beneficiary, new_balance);
+		self.assets.mutate(id, |details| {
+			if let Some(details) = details {
+				details.supply = new_supply;
+			}
+		});
+
+		Ok(())
+	}
+
+	/// Destroy `amount` of the asset `id` from the account of `who`.
+	/// This function should only succeed if the caller is the admin of the asset. If `who` is left
+	/// with less than the minimum balance, the rest of their balance is destroyed as well.
+	pub fn burn(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let min_balance = self.ensure_admin(&id, &caller)?.min_balance;
+		let balance = self.balance(id, &who);
+
+		let mut new_balance = balance.checked_sub(&amount).ok_or("Not enough funds.")?;
+		if new_balance < min_balance {
+			new_balance = Zero::zero();
+		}
+		let burned = balance.checked_sub(&new_balance).ok_or("Overflow")?;
+
+		self.set_account_balance(id, &who, new_balance);
+		self.assets.try_mutate(id, |details| -> DispatchResult {
+			if let Some(details) = details {
+				details.supply = details.supply.checked_sub(&burned).ok_or("Overflow")?;
+			}
+			Ok(())
+		})
+	}
+
+	/// Transfer `amount` of the asset `id` from the `caller` to `target`.
+	/// This function verifies that the caller has at least `amount` balance to transfer, that
+	/// neither account is left with less than the minimum balance, and that no mathematical
+	/// overflows occur.
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		target: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		self.do_transfer(id, &caller, &target, amount)
+	}
+
+	/// Allow `delegate` to transfer `amount` more of the asset `id` on behalf of the `caller`.
+	pub fn approve_transfer(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		delegate: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		if !self.assets.contains_key(&id) {
+			return Err("Asset does not exist".into());
+		}
+
+		let new_allowance = self.allowance(id, &caller, &delegate).checked_add(&amount).ok_or("Overflow")?;
+		self.approvals.insert((id, caller, delegate), new_allowance);
+		Ok(())
+	}
+
+	/// Transfer `amount` of the asset `id` from `owner` to `destination`, using the allowance
+	/// `owner` gave to the `caller` with `approve_transfer`.
+	/// This function will return an error if the allowance is smaller than `amount`.
+	pub fn transfer_approved(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		owner: T::AccountId,
+		destination: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let new_allowance = self
+			.allowance(id, &owner, &caller)
+			.checked_sub(&amount)
+			.ok_or("Not enough allowance")?;
+
+		self.do_transfer(id, &owner, &destination, amount)?;
+
+		if new_allowance.is_zero() {
+			self.approvals.remove(&(id, owner, caller));
+		} else {
+			self.approvals.insert((id, owner, caller), new_allowance);
+		}
+
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	#[derive(Clone)]
+	struct TestConfig;
+
+	impl crate::system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl super::Config for TestConfig {
+		type AssetId = u32;
+		type AssetBalance = u128;
+	}
+
+	fn setup() -> super::Pallet<TestConfig> {
+		let mut assets = super::Pallet::<TestConfig>::new();
+		assets.create("alice", 1, "alice", 10).unwrap();
+		assets.mint("alice", 1, "alice", 100).unwrap();
+		assets
+	}
+
+	#[test]
+	fn create_mint_and_burn() {
+		let mut assets = setup();
+
+		assert_eq!(assets.create("bob", 1, "bob", 10), Err("Asset already exists".into()));
+		assert_eq!(assets.mint("bob", 1, "bob", 100), Err("Caller is not the admin of the asset".into()));
+		assert_eq!(assets.mint("alice", 1, "bob", 5), Err("Balance below minimum".into()));
+
+		assets.set_metadata("alice", 1, "Token".to_string(), "TKN".to_string(), 12).unwrap();
+		assert_eq!(assets.metadata(&1).unwrap().symbol, "TKN");
+
+		// Burning below the minimum balance destroys the rest of the balance too.
+		assets.burn("alice", 1, "alice", 95).unwrap();
+		assert_eq!(assets.balance(1, &"alice"), 0);
+		assert_eq!(assets.asset(&1).unwrap().supply, 0);
+	}
+
+	#[test]
+	fn transfer_asset() {
+		let mut assets = setup();
+
+		assets.transfer("alice", 1, "bob", 40).unwrap();
+		assert_eq!(assets.balance(1, &"alice"), 60);
+		assert_eq!(assets.balance(1, &"bob"), 40);
+
+		assert_eq!(assets.transfer("alice", 1, "bob", 100), Err("Not enough funds.".into()));
+		assert_eq!(assets.transfer("alice", 1, "charlie", 5), Err("Balance below minimum".into()));
+		assert_eq!(assets.transfer("alice", 2, "bob", 5), Err("Asset does not exist".into()));
+
+		// Transferring to oneself changes neither the balance nor the supply.
+		assets.transfer("alice", 1, "alice", 50).unwrap();
+		assert_eq!(assets.balance(1, &"alice"), 60);
+		assert_eq!(assets.asset(&1).unwrap().supply, 100);
+		assert_eq!(assets.transfer("alice", 1, "alice", 100), Err("Not enough funds.".into()));
+	}
+
+	#[test]
+	fn transfer_approved_asset() {
+		let mut assets = setup();
+
+		assets.approve_transfer("alice", 1, "bob", 30).unwrap();
+		assert_eq!(
+			assets.transfer_approved("bob", 1, "alice", "charlie", 40),
+			Err("Not enough allowance".into())
+		);
+
+		assets.transfer_approved("bob", 1, "alice", "charlie", 20).unwrap();
+		assert_eq!(assets.balance(1, &"charlie"), 20);
+		assert_eq!(assets.allowance(1, &"alice", &"bob"), 10);
+	}
+}
@@ -1,47 +1,198 @@
-use std::collections::BTreeMap;
-use num::traits::{CheckedAdd, CheckedSub, Zero};
+use core::fmt::Debug;
+use crate::support::ensure::{ensure_root, OriginFor};
+use crate::support::Instance;
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, Zero};
+
+/// The identifier of a lock, so that different pallets can lock the funds of the same account
+/// independently of each other.
+pub type LockIdentifier = [u8; 8];
 
 // Combine all generic types and their trait bounds into a single `pub trait Config`.
 //When you are done, your `Pallet` can simply be defined with `Pallet<T: Config>`.
-pub trait Config: crate::system::Config {
+//
+// The pallet can be included more than once in a runtime, with a separate configuration for every
+// instance `I`. The default instance is `()`, so `Config` alone is the configuration of the
+// default instance.
+pub trait Config<I: Instance = ()>: crate::system::Config {
     /// The type of balance.
-    type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+    type Balance: Debug + Zero + CheckedSub + CheckedAdd + CheckedMul + Copy + Ord;
 }
 
 /// This is the Balances Module.
 /// It is a simple module which keeps track of how much balance each account has in this state
 /// machine.
-#[derive(Debug)]
-pub struct Pallet<T: Config> {
+#[macros::storage(version = 1)]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config<I>, I: Instance = ()> {
     // A simple storage mapping from accounts (`String`) to their balances (`u128`).
-    balances: BTreeMap<T::AccountId, T::Balance>,
+    // Since version 1 of the storage, empty balances are removed rather than stored.
+    #[default(Zero::zero())]
+    balances: StorageMap<T::AccountId, T::Balance>,
+    // A storage mapping from accounts to the part of their funds which is reserved, for example as
+    // a deposit. Reserved funds are not included in `balances`, and cannot be transferred. Like
+    // `balances`, empty reserved balances are not stored.
+    #[default(Zero::zero())]
+    reserved: StorageMap<T::AccountId, T::Balance>,
+    // A storage mapping from accounts and lock identifiers to the locks on their balance. Locks
+    // overlap, so the largest lock of an account is the part of its balance which cannot be
+    // transferred or reserved.
+    locks: StorageDoubleMap<T::AccountId, LockIdentifier, T::Balance>,
 }
 
-impl<T: Config> Pallet<T>{
-    /// Create a new instance of the balances module.
-    pub fn new() -> Self {
-        Self {
-            balances: BTreeMap::new(),
+impl<T: Config<I>, I: Instance> Pallet<T, I> {
+	/// Store `amount` as the balance of `who`, removing the entry if it is empty.
+    fn write_balance(&mut self, who: T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.balances.remove(&who);
+        } else {
+            self.balances.insert(who, amount);
+        }
+    }
+
+	/// Store `amount` as the reserved balance of `who`, removing the entry if it is empty.
+    fn write_reserved(&mut self, who: T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.reserved.remove(&who);
+        } else {
+            self.reserved.insert(who, amount);
         }
     }
 
 	/// Set the balance of an account `who` to some `amount`.
     pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-        self.balances.insert(who.clone(), amount);
+        self.write_balance(who.clone(), amount);
     }
 
 	/// Get the balance of an account `who`.
 	/// If the account has no stored balance, we return zero.
     pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-        *self.balances.get(who).unwrap_or(&Zero::zero())
+        self.balances.get(who)
+    }
+
+	/// Get the reserved balance of an account `who`.
+	/// If the account has no reserved balance, we return zero.
+    pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.reserved.get(who)
+    }
+
+	/// Get the locked balance of an account `who`, which is the largest of their locks.
+	/// If the account has no locks, we return zero.
+    pub fn locked_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.locks
+            .iter_prefix(who)
+            .map(|(_, amount)| *amount)
+            .max()
+            .unwrap_or(Zero::zero())
+    }
+
+	/// Lock `amount` of the balance of `who` under the identifier `id`, replacing any previous lock
+	/// with the same identifier.
+    pub fn set_lock(&mut self, id: LockIdentifier, who: &T::AccountId, amount: T::Balance) {
+        self.locks.insert(who.clone(), id, amount);
+    }
+
+	/// Remove the lock with the identifier `id` from the balance of `who`, if any.
+    pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+        self.locks.remove(who, &id);
+    }
+
+	/// Move `amount` from the balance of `who` to their reserved balance.
+	/// This function verifies that `who` has at least `amount` unlocked balance to reserve,
+	/// and that no mathematical overflows occur.
+    pub fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> crate::support::DispatchResult {
+        let new_balance = self.balance(who).checked_sub(&amount).ok_or("Not enough funds.")?;
+        if new_balance < self.locked_balance(who) {
+            return Err("Funds are locked.".into());
+        }
+        let new_reserved = self.reserved_balance(who).checked_add(&amount).ok_or("Overflow")?;
+
+        self.write_balance(who.clone(), new_balance);
+        self.write_reserved(who.clone(), new_reserved);
+
+        Ok(())
+    }
+
+	/// Move `amount` from the reserved balance of `who` back to their balance.
+	/// This function verifies that `who` has at least `amount` reserved balance,
+	/// and that no mathematical overflows occur.
+    pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> crate::support::DispatchResult {
+        let new_reserved = self.reserved_balance(who).checked_sub(&amount).ok_or("Not enough reserved funds.")?;
+        let new_balance = self.balance(who).checked_add(&amount).ok_or("Overflow")?;
+
+        self.write_reserved(who.clone(), new_reserved);
+        self.write_balance(who.clone(), new_balance);
+
+        Ok(())
+    }
+
+	/// Move `amount` from the reserved balance of `from` to the balance of `to`.
+	/// This function verifies that `from` has at least `amount` reserved balance,
+	/// and that no mathematical overflows occur.
+    pub fn repatriate_reserved(
+        &mut self,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: T::Balance,
+    ) -> crate::support::DispatchResult {
+        let new_reserved = self.reserved_balance(from).checked_sub(&amount).ok_or("Not enough reserved funds.")?;
+        let new_balance = self.balance(to).checked_add(&amount).ok_or("Overflow")?;
+
+        self.write_reserved(from.clone(), new_reserved);
+        self.write_balance(to.clone(), new_balance);
+
+        Ok(())
+    }
+
+	/// Add `amount` to the balance of `who`, creating the funds.
+	/// This function verifies that no mathematical overflows occur.
+    pub fn deposit(&mut self, who: &T::AccountId, amount: T::Balance) -> crate::support::DispatchResult {
+        let new_balance = self.balance(who).checked_add(&amount).ok_or("Overflow")?;
+        self.write_balance(who.clone(), new_balance);
+
+        Ok(())
+    }
+
+	/// Remove up to `amount` from the balance of `who`, even if it is locked.
+	/// Returns the amount which was actually removed, which is then up to the caller to deposit
+	/// somewhere else, or to burn.
+    pub fn slash(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let balance = self.balance(who);
+        let slashed = amount.min(balance);
+        self.write_balance(who.clone(), balance.checked_sub(&slashed).unwrap_or(Zero::zero()));
+
+        slashed
+    }
+
+	/// Remove `amount` from the unlocked balance of `who`, for example to pay a fee.
+	/// Like `slash`, it is then up to the caller to deposit the amount somewhere else, or to burn it.
+    pub fn withdraw(&mut self, who: &T::AccountId, amount: T::Balance) -> crate::support::DispatchResult {
+        let balance = self.balance(who);
+        let new_balance = balance.checked_sub(&amount).ok_or("Not enough funds.")?;
+        if new_balance < self.locked_balance(who) {
+            return Err("Funds are locked.".into());
+        }
+        self.write_balance(who.clone(), new_balance);
+
+        Ok(())
+    }
+
+	/// Remove up to `amount` from the reserved balance of `who`.
+	/// Returns the amount which was actually removed, like `slash`.
+    pub fn slash_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let reserved = self.reserved_balance(who);
+        let slashed = amount.min(reserved);
+        self.write_reserved(who.clone(), reserved.checked_sub(&slashed).unwrap_or(Zero::zero()));
+
+        slashed
     }
 }
 
-#[macros::call]
-impl<T: Config> Pallet<T> {
+#[macros::call(strict, errors("Not enough funds.", "Overflow", "Funds are locked."))]
+impl<T: Config<I>, I: Instance> Pallet<T, I> {
 	/// Transfer `amount` from one account to another.
-	/// This function verifies that `from` has at least `amount` balance to transfer,
+	/// This function verifies that `from` has at least `amount` unlocked balance to transfer,
 	/// and that no mathematical overflows occur.
+	#[call_index(0)]
 	pub fn transfer(
 		&mut self,
 		caller: T::AccountId,
@@ -49,16 +200,104 @@ impl<T: Config> Pallet<T> {
 		amount: T::Balance,
 	) -> crate::support::DispatchResult {
 		let caller_balance = self.balance(&caller);
-		let to_balance = self.balance(&to);
-
 		let new_caller_balance = caller_balance.checked_sub(&amount).ok_or("Not enough funds.")?;
+		// A transfer to oneself changes no balance, and writing both balances would mint `amount`.
+		if caller == to {
+			return Ok(());
+		}
+
+		let to_balance = self.balance(&to);
 		let new_to_balance = to_balance.checked_add(&amount).ok_or("Overflow")?;
 
-		self.balances.insert(caller, new_caller_balance);
-		self.balances.insert(to, new_to_balance);
+		if new_caller_balance < self.locked_balance(&caller) {
+			return Err("Funds are locked.".into());
+		}
+
+		self.write_balance(caller, new_caller_balance);
+		self.write_balance(to, new_to_balance);
 
 		Ok(())
 	}
+
+	/// Set the free balance of `who` to `amount`.
+	/// This function should only succeed if the caller is the `Root` origin.
+	#[call_index(1)]
+	pub fn force_set_balance(
+		&mut self,
+		origin: OriginFor<T>,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		ensure_root(origin)?;
+		self.set_balance(&who, amount);
+		Ok(())
+	}
+}
+
+impl<T: Config<I>, I: Instance> crate::support::Hooks<T, T::BlockNumber> for Pallet<T, I> {}
+
+/// The migrations of the storage of the Balances Module.
+pub mod migrations {
+    use super::{Config, Pallet};
+    use crate::support::storage::{Encode, VersionedMigration};
+    use crate::support::{DispatchError, DispatchResult, GetPallet, Instance, OnRuntimeUpgrade};
+    use num::traits::Zero;
+
+    /// Migrate the storage of the instance `I` from version 0 to version 1, removing the empty
+    /// balances. Each instance of the pallet must be migrated.
+    pub type MigrateToV1<T, I = ()> = VersionedMigration<0, 1, RemoveEmptyBalances<T, I>, Pallet<T, I>>;
+
+    /// Remove the empty balances and reserved balances, which are no longer stored since version 1.
+    pub struct RemoveEmptyBalances<T, I = ()>(core::marker::PhantomData<(T, I)>);
+
+    impl<T: Config<I> + GetPallet<Pallet<T, I>>, I: Instance> RemoveEmptyBalances<T, I> {
+        /// The number of accounts with some balance, and with some reserved balance.
+        fn funded_accounts(pallet: &Pallet<T, I>) -> (u64, u64) {
+            let funded = pallet.balances.iter().filter(|(_, amount)| !amount.is_zero()).count();
+            let reserved = pallet.reserved.iter().filter(|(_, amount)| !amount.is_zero()).count();
+            (funded as u64, reserved as u64)
+        }
+    }
+
+    impl<T: Config<I> + GetPallet<Pallet<T, I>>, I: Instance> OnRuntimeUpgrade<T> for RemoveEmptyBalances<T, I> {
+        fn pre_upgrade(runtime: &T) -> Result<Vec<u8>, DispatchError> {
+            Ok(Self::funded_accounts(runtime.pallet()).encode())
+        }
+
+        fn migrate(runtime: &mut T) {
+            let pallet: &mut Pallet<T, I> = runtime.pallet_mut();
+            let empty_balances = pallet
+                .balances
+                .iter()
+                .filter(|(_, amount)| amount.is_zero())
+                .map(|(who, _)| who.clone())
+                .collect::<Vec<_>>();
+            let empty_reserved = pallet
+                .reserved
+                .iter()
+                .filter(|(_, amount)| amount.is_zero())
+                .map(|(who, _)| who.clone())
+                .collect::<Vec<_>>();
+
+            for who in empty_balances {
+                pallet.balances.remove(&who);
+            }
+            for who in empty_reserved {
+                pallet.reserved.remove(&who);
+            }
+        }
+
+        fn post_upgrade(runtime: &T, state: Vec<u8>) -> DispatchResult {
+            let pallet: &Pallet<T, I> = runtime.pallet();
+            if pallet.balances.iter().chain(pallet.reserved.iter()).any(|(_, amount)| amount.is_zero()) {
+                return Err("Empty balances are still stored".into());
+            }
+            if Self::funded_accounts(pallet).encode() != state {
+                return Err("Funded accounts changed".into());
+            }
+            Ok(())
+        }
+    }
 }
 
 #[cfg(test)]
@@ -75,6 +314,29 @@ mod tests {
         type Balance = u128;
     }
 
+    // A runtime with only the balances pallet, next to the main runtime of the crate, to check
+    // that balances does not depend on the other pallets.
+    type MinimalBlock = crate::support::Block<
+        crate::support::Header<u32, String>,
+        crate::support::Extrinsic<String, MinimalCall>,
+    >;
+
+    #[macros::runtime(block = MinimalBlock, system = crate::system, call = MinimalCall)]
+    #[derive(Debug, Clone)]
+    pub struct MinimalRuntime {
+        system: crate::system::Pallet<Self>,
+        balances: super::Pallet<Self>,
+    }
+
+    impl crate::system::Config for MinimalRuntime {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+    }
+
+    impl super::Config for MinimalRuntime {
+        type Balance = u128;
+    }
 
     #[test]
     fn init_balances() {
@@ -105,6 +367,20 @@ mod tests {
 
     }
 
+    #[test]
+    fn transfer_to_oneself_does_not_mint() {
+        let mut balances = super::Pallet::<TestConfig>::new();
+
+        balances.set_balance(&"alice".to_string(), 100);
+
+        assert_eq!(balances.transfer("alice".to_string(), "alice".to_string(), 50), Ok(()));
+        assert_eq!(balances.balance(&"alice".to_string()), 100);
+
+        // `alice` still cannot move more than their balance.
+        assert_eq!(balances.transfer("alice".to_string(), "alice".to_string(), 200), Err("Not enough funds.".into()));
+        assert_eq!(balances.balance(&"alice".to_string()), 100);
+    }
+
     #[test]
     fn transfer_balance_insufficient_funds() {
         // test that the balances are not updated if the transfer would cause an underflow
@@ -115,9 +391,139 @@ mod tests {
 
         let res = balances.transfer("alice".to_string(), "bob".to_string(), 200);
 
-        assert_eq!(res, Err("Not enough funds."));
+        assert_eq!(res, Err("Not enough funds.".into()));
 
         assert_eq!(balances.balance(&"alice".to_string()), 50);
         assert_eq!(balances.balance(&"bob".to_string()), 50);
     }
+
+    #[test]
+    fn reserve_and_unreserve() {
+        // test that reserved funds are moved out of the balance, and cannot be over-unreserved
+        let mut balances = super::Pallet::<TestConfig>::new();
+        let alice = "alice".to_string();
+
+        balances.set_balance(&alice, 100);
+
+        assert_eq!(balances.reserve(&alice, 30), Ok(()));
+        assert_eq!(balances.balance(&alice), 70);
+        assert_eq!(balances.reserved_balance(&alice), 30);
+
+        assert_eq!(balances.reserve(&alice, 100), Err("Not enough funds.".into()));
+        assert_eq!(balances.unreserve(&alice, 40), Err("Not enough reserved funds.".into()));
+
+        assert_eq!(balances.unreserve(&alice, 30), Ok(()));
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.reserved_balance(&alice), 0);
+    }
+
+    #[test]
+    fn locked_funds_cannot_be_moved() {
+        // test that overlapping locks keep the largest amount in the account
+        let mut balances = super::Pallet::<TestConfig>::new();
+        let alice = "alice".to_string();
+
+        balances.set_balance(&alice, 100);
+        balances.set_lock(*b"first   ", &alice, 60);
+        balances.set_lock(*b"second  ", &alice, 30);
+        assert_eq!(balances.locked_balance(&alice), 60);
+
+        assert_eq!(balances.transfer(alice.clone(), "bob".to_string(), 50), Err("Funds are locked.".into()));
+        assert_eq!(balances.reserve(&alice, 50), Err("Funds are locked.".into()));
+        assert_eq!(balances.transfer(alice.clone(), "bob".to_string(), 40), Ok(()));
+
+        balances.remove_lock(*b"first   ", &alice);
+        assert_eq!(balances.locked_balance(&alice), 30);
+        assert_eq!(balances.reserve(&alice, 30), Ok(()));
+        assert_eq!(balances.balance(&alice), 30);
+    }
+
+    #[test]
+    fn minimal_runtime_executes_transfers() {
+        let mut runtime = MinimalRuntime::new();
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        runtime.balances.set_balance(&alice, 100);
+
+        let call = MinimalCall::balances(super::Call::transfer { to: bob.clone(), amount: 30 });
+        let extrinsic = crate::support::Extrinsic { caller: alice.clone(), call };
+        let block = MinimalBlock { header: crate::support::Header { block_number: 1, author: "alice".to_string() }, extrinsics: vec![extrinsic] };
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.balances.balance(&alice), 70);
+        assert_eq!(runtime.balances.balance(&bob), 30);
+        assert_eq!(MinimalRuntime::metadata().pallets.len(), 2);
+    }
+
+    #[test]
+    fn instances_are_independent() {
+        let mut runtime = crate::Runtime::new();
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        runtime.rewards.set_balance(&alice, 100);
+        // The fee of the extrinsic is paid in the native token.
+        runtime.balances.set_balance(&alice, 10);
+
+        let call = crate::RuntimeCall::rewards(super::Call::transfer { to: bob.clone(), amount: 30 });
+        assert_eq!(call.pallet_index(), 16);
+        let extrinsic = crate::support::Extrinsic { caller: alice.clone(), call };
+        let block = crate::support::Block { header: crate::support::Header { block_number: 1, author: "alice".to_string() }, extrinsics: vec![extrinsic] };
+        runtime.execute_block(block).unwrap();
+
+        assert_eq!(runtime.rewards.balance(&alice), 70);
+        assert_eq!(runtime.rewards.balance(&bob), 30);
+        assert_eq!(runtime.balances.balance(&alice), 8);
+        assert_eq!(runtime.balances.balance(&bob), 0);
+
+        // Both instances store their version under their own keys.
+        let keys = |entries: Vec<(Vec<u8>, Vec<u8>)>| entries.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+        let native_keys = keys(runtime.balances.storage_entries());
+        assert!(keys(runtime.rewards.storage_entries()).iter().all(|key| !native_keys.contains(key)));
+    }
+
+    #[test]
+    fn migration_to_v1_removes_empty_balances() {
+        use crate::support::storage::{GetStorageVersion, StorageVersion};
+        use crate::support::{self, GetPallet};
+
+        // A snapshot of the state before version 1, which still stores empty balances.
+        let mut snapshot = crate::Runtime::new();
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        let balances: &mut super::Pallet<crate::Runtime> = snapshot.pallet_mut();
+        balances.set_on_chain_storage_version(StorageVersion::new(0));
+        balances.balances.insert(alice.clone(), 0);
+        balances.balances.insert(bob.clone(), 50);
+        balances.reserved.insert(bob.clone(), 0);
+        let rewards: &mut super::Pallet<crate::Runtime, support::Instance1> = snapshot.pallet_mut();
+        rewards.set_on_chain_storage_version(StorageVersion::new(0));
+        rewards.balances.insert(alice.clone(), 0);
+
+        // The migrations of the runtime migrate both instances of the pallet.
+        let runtime = support::try_runtime_upgrade::<_, crate::Migrations>(&snapshot).unwrap();
+        assert_eq!(runtime.balances.on_chain_storage_version(), StorageVersion::new(1));
+        assert!(!runtime.balances.balances.contains_key(&alice));
+        assert!(!runtime.balances.reserved.contains_key(&bob));
+        assert_eq!(runtime.balances.balance(&bob), 50);
+        assert_eq!(runtime.rewards.on_chain_storage_version(), StorageVersion::new(1));
+        assert!(!runtime.rewards.balances.contains_key(&alice));
+    }
+
+    #[test]
+    fn migrations_cannot_change_the_state_twice() {
+        use crate::support::{self, OnRuntimeUpgrade};
+
+        // A migration which does not check the storage version mints funds every time it runs.
+        struct MintOnEveryRun;
+        impl OnRuntimeUpgrade<crate::Runtime> for MintOnEveryRun {
+            fn migrate(runtime: &mut crate::Runtime) {
+                let alice = "alice".to_string();
+                let balance = runtime.balances.balance(&alice);
+                runtime.balances.set_balance(&alice, balance + 1);
+            }
+        }
+
+        let snapshot = crate::Runtime::new();
+        assert_eq!(
+            support::try_runtime_upgrade::<_, MintOnEveryRun>(&snapshot).err(),
+            Some("Migration changed the state again".into())
+        );
+    }
 }
@@ -0,0 +1,542 @@
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, Zero};
+use crate::balances::LockIdentifier;
+use crate::support::ensure::RawOrigin;
+use crate::support::storage::Encode;
+use crate::support::{DispatchError, DispatchResult, GetPallet, Hooks};
+
+/// The identifier of the lock this pallet places on the funds used to vote.
+const DEMOCRACY_ID: LockIdentifier = *b"democrac";
+
+/// The identifier given to every referendum.
+pub type ReferendumIndex = u32;
+
+/// The configuration of the Democracy Module.
+///
+/// Deposits are reserved and votes are locked in the balances pallet, and passed proposals are
+/// enacted through the scheduler pallet, so the runtime must give access to them.
+pub trait Config:
+	crate::balances::Config<Balance: From<u8>>
+	+ crate::scheduler::Config
+	+ Sized
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+	+ GetPallet<crate::scheduler::Pallet<Self>>
+	+ GetPallet<crate::system::Pallet<Self>>
+{
+	/// The minimum amount which must be deposited to make a proposal.
+	const MINIMUM_DEPOSIT: Self::Balance;
+	/// The minimum amount of funds which must vote on a referendum, whatever their conviction, for
+	/// it to pass.
+	const MINIMUM_TURNOUT: Self::Balance;
+	/// The number of blocks a referendum is open for voting.
+	const VOTING_PERIOD: Self::BlockNumber;
+	/// The number of blocks between the end of a referendum and the enactment of its proposal.
+	const ENACTMENT_PERIOD: Self::BlockNumber;
+	/// The number of blocks the funds of a vote stay locked for each level of conviction.
+	const VOTE_LOCKING_PERIOD: Self::BlockNumber;
+}
+
+/// How strongly a voter believes in their vote: the longer they agree to lock their funds after the
+/// end of the referendum, the more their vote weighs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Conviction {
+	/// A tenth of a vote, without any lock after the end of the referendum.
+	None,
+	/// One vote, locked for one locking period.
+	Locked1x,
+	/// Two votes, locked for two locking periods.
+	Locked2x,
+	/// Three votes, locked for four locking periods.
+	Locked3x,
+}
+
+/// A conviction is encoded as the index of its variant.
+impl Encode for Conviction {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(*self as u8);
+	}
+}
+
+impl Conviction {
+	/// The weight of a vote, in tenths of a vote.
+	fn multiplier(self) -> u8 {
+		match self {
+			Conviction::None => 1,
+			Conviction::Locked1x => 10,
+			Conviction::Locked2x => 20,
+			Conviction::Locked3x => 30,
+		}
+	}
+
+	/// The number of locking periods the funds of a vote stay locked for.
+	fn lock_periods(self) -> u8 {
+		match self {
+			Conviction::None => 0,
+			Conviction::Locked1x => 1,
+			Conviction::Locked2x => 2,
+			Conviction::Locked3x => 4,
+		}
+	}
+}
+
+/// A vote on a referendum.
+#[derive(Debug, Clone)]
+pub struct Vote<T: Config> {
+	/// Whether the vote is in favor of the proposal.
+	pub aye: bool,
+	/// The amount of funds voting.
+	pub balance: T::Balance,
+	/// The conviction of the vote.
+	pub conviction: Conviction,
+}
+
+impl<T: Config> Encode for Vote<T>
+where
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.aye.encode_to(dest);
+		self.balance.encode_to(dest);
+		self.conviction.encode_to(dest);
+	}
+}
+
+impl<T: Config> Vote<T> {
+	/// The weight of this vote in the tally, in tenths of a vote.
+	fn weight(&self) -> Result<T::Balance, DispatchError> {
+		self.balance.checked_mul(&self.conviction.multiplier().into()).ok_or("Overflow".into())
+	}
+}
+
+/// The ongoing tally of a referendum, in tenths of a vote.
+#[derive(Debug, Clone)]
+pub struct Tally<T: Config> {
+	/// The weight of the votes in favor.
+	pub ayes: T::Balance,
+	/// The weight of the votes against.
+	pub nays: T::Balance,
+	/// The amount of funds which voted, without conviction.
+	pub turnout: T::Balance,
+}
+
+impl<T: Config> Encode for Tally<T>
+where
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.ayes.encode_to(dest);
+		self.nays.encode_to(dest);
+		self.turnout.encode_to(dest);
+	}
+}
+
+/// A referendum on a proposal.
+#[derive(Debug, Clone)]
+pub struct ReferendumInfo<T: Config> {
+	/// The account which made the proposal.
+	pub proposer: T::AccountId,
+	/// The amount reserved from the proposer until the end of the referendum.
+	pub deposit: T::Balance,
+	/// The call to enact if the referendum passes.
+	pub call: <T as crate::scheduler::Config>::RuntimeCall,
+	/// The block at which voting ends.
+	pub end: T::BlockNumber,
+	/// The current tally of the votes.
+	pub tally: Tally<T>,
+}
+
+impl<T: Config> Encode for ReferendumInfo<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+	<T as crate::scheduler::Config>::RuntimeCall: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.proposer.encode_to(dest);
+		self.deposit.encode_to(dest);
+		self.call.encode_to(dest);
+		self.end.encode_to(dest);
+		self.tally.encode_to(dest);
+	}
+}
+
+/// The funds of an account locked by its votes.
+#[derive(Debug, Clone)]
+pub struct VotingLock<T: Config> {
+	/// The amount locked, which is the largest amount the account voted with.
+	pub amount: T::Balance,
+	/// The block from which the funds can be unlocked.
+	pub until: T::BlockNumber,
+}
+
+impl<T: Config> Encode for VotingLock<T>
+where
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.amount.encode_to(dest);
+		self.until.encode_to(dest);
+	}
+}
+
+/// The events emitted by the Democracy Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// A referendum `index` was started on a proposal of `proposer`, and ends at block `end`.
+	Started { index: ReferendumIndex, proposer: T::AccountId, end: T::BlockNumber },
+	/// `who` voted on the referendum `index`.
+	Voted { index: ReferendumIndex, who: T::AccountId, vote: Vote<T> },
+	/// The referendum `index` passed, and its proposal will be enacted at block `enactment`.
+	Passed { index: ReferendumIndex, enactment: T::BlockNumber },
+	/// The referendum `index` did not pass.
+	NotPassed { index: ReferendumIndex },
+}
+
+/// This is the Democracy Module.
+/// It allows accounts to propose calls, and token holders to vote on them. A proposal passes if at
+/// least `T::MINIMUM_TURNOUT` funds voted on it, and the ayes outweigh the nays. Proposals which
+/// pass are dispatched on behalf of the `Root` origin once the enactment period is over.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from a referendum to its information, while it is open for voting.
+	referenda: StorageMap<ReferendumIndex, ReferendumInfo<T>>,
+	/// A map from a referendum and a voter to their vote.
+	votes: StorageDoubleMap<ReferendumIndex, T::AccountId, Vote<T>>,
+	/// A map from an account to the funds locked by its votes.
+	locks: StorageMap<T::AccountId, VotingLock<T>>,
+	/// The index given to the next referendum.
+	#[default(0)]
+	next_index: StorageValue<ReferendumIndex>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the information (if any) of the ongoing referendum `index`.
+	pub fn referendum(&self, index: ReferendumIndex) -> Option<&ReferendumInfo<T>> {
+		self.referenda.try_get(&index)
+	}
+
+	/// Get the vote (if any) of `who` on the ongoing referendum `index`.
+	pub fn vote_of(&self, index: ReferendumIndex, who: &T::AccountId) -> Option<&Vote<T>> {
+		self.votes.try_get(&index, who)
+	}
+
+	/// Get the funds (if any) of `who` locked by their votes.
+	pub fn voting_lock(&self, who: &T::AccountId) -> Option<&VotingLock<T>> {
+		self.locks.try_get(who)
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+
+	/// Close the referendum `index`, returning the deposit of the proposer, and schedule its
+	/// proposal for enactment if it passed.
+	fn close(runtime: &mut T, index: ReferendumIndex, now: T::BlockNumber) {
+		let pallet: &mut Self = runtime.pallet_mut();
+		let Some(referendum) = pallet.referenda.remove(&index) else { return };
+		let voters: Vec<T::AccountId> = pallet.votes.iter_prefix(&index).map(|(voter, _)| voter.clone()).collect();
+		for voter in voters {
+			pallet.votes.remove(&index, &voter);
+		}
+
+		// The deposit was reserved when the proposal was made, so this cannot fail.
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		let _ = balances.unreserve(&referendum.proposer, referendum.deposit);
+
+		let Tally { ayes, nays, turnout } = referendum.tally;
+		let enactment = now.checked_add(&T::ENACTMENT_PERIOD);
+		let event = match enactment {
+			Some(enactment) if turnout >= T::MINIMUM_TURNOUT && ayes > nays => {
+				let scheduler: &mut crate::scheduler::Pallet<T> = runtime.pallet_mut();
+				match scheduler.schedule_as(RawOrigin::Root, enactment, None, referendum.call) {
+					Ok(_) => Event::Passed { index, enactment },
+					Err(_) => Event::NotPassed { index },
+				}
+			},
+			_ => Event::NotPassed { index },
+		};
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.events.push(event);
+	}
+}
+
+#[macros::call(errors(
+	"Deposit is too low",
+	"Overflow",
+	"Not enough funds.",
+	"Referendum is not ongoing",
+	"Underflow",
+	"No voting lock",
+	"Vote is still locked",
+	"Funds are locked.",
+))]
+impl<T: Config> Pallet<T> {
+	/// Propose `call`, reserving `deposit` from the `caller` until the end of the referendum.
+	/// A referendum is started right away, and is open for voting for `T::VOTING_PERIOD` blocks.
+	/// This function will return an error if the deposit is below `T::MINIMUM_DEPOSIT`.
+	#[allow(clippy::boxed_local)]
+	pub fn propose(
+		runtime: &mut T,
+		caller: T::AccountId,
+		#[runtime_call] call: Box<<T as crate::scheduler::Config>::RuntimeCall>,
+		deposit: T::Balance,
+	) -> DispatchResult {
+		if deposit < T::MINIMUM_DEPOSIT {
+			return Err("Deposit is too low".into());
+		}
+
+		let now = GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number();
+		let end = now.checked_add(&T::VOTING_PERIOD).ok_or("Overflow")?;
+		let pallet: &Self = runtime.pallet();
+		let index = pallet.next_index.get();
+		let next_index = index.checked_add(1).ok_or("Overflow")?;
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.reserve(&caller, deposit)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.next_index.put(next_index);
+
+		let tally = Tally { ayes: Zero::zero(), nays: Zero::zero(), turnout: Zero::zero() };
+		let referendum = ReferendumInfo { proposer: caller.clone(), deposit, call: *call, end, tally };
+		pallet.referenda.insert(index, referendum);
+		pallet.events.push(Event::Started { index, proposer: caller, end });
+
+		Ok(())
+	}
+
+	/// Vote on the referendum `index` with `balance` of the funds of the `caller`, replacing any
+	/// previous vote. The funds are locked until the end of the referendum, and for longer with a
+	/// higher `conviction`.
+	/// This function will return an error if the referendum is not ongoing, or if the caller does
+	/// not have `balance`.
+	pub fn vote(
+		runtime: &mut T,
+		caller: T::AccountId,
+		index: ReferendumIndex,
+		aye: bool,
+		balance: T::Balance,
+		conviction: Conviction,
+	) -> DispatchResult {
+		let balances: &crate::balances::Pallet<T> = runtime.pallet();
+		if balance > balances.balance(&caller) {
+			return Err("Not enough funds.".into());
+		}
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		let mut referendum = pallet.referenda.get(&index).ok_or("Referendum is not ongoing")?;
+		let vote = Vote { aye, balance, conviction };
+
+		// The funds stay locked after the end of the referendum, depending on the conviction.
+		let mut until = referendum.end;
+		for _ in 0..conviction.lock_periods() {
+			until = until.checked_add(&T::VOTE_LOCKING_PERIOD).ok_or("Overflow")?;
+		}
+
+		// Replace the previous vote in the tally, if any.
+		let Tally { mut ayes, mut nays, mut turnout } = referendum.tally;
+		if let Some(previous) = pallet.votes.try_get(&index, &caller) {
+			let side = if previous.aye { &mut ayes } else { &mut nays };
+			*side = side.checked_sub(&previous.weight()?).ok_or("Underflow")?;
+			turnout = turnout.checked_sub(&previous.balance).ok_or("Underflow")?;
+		}
+		let side = if aye { &mut ayes } else { &mut nays };
+		*side = side.checked_add(&vote.weight()?).ok_or("Overflow")?;
+		turnout = turnout.checked_add(&balance).ok_or("Overflow")?;
+		referendum.tally = Tally { ayes, nays, turnout };
+
+		let lock = match pallet.locks.try_get(&caller) {
+			Some(lock) => VotingLock { amount: lock.amount.max(balance), until: lock.until.max(until) },
+			None => VotingLock { amount: balance, until },
+		};
+		let amount = lock.amount;
+		pallet.referenda.insert(index, referendum);
+		pallet.locks.insert(caller.clone(), lock);
+		pallet.votes.insert(index, caller.clone(), vote);
+		pallet.events.push(Event::Voted { index, who: caller.clone(), vote: Vote { aye, balance, conviction } });
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.set_lock(DEMOCRACY_ID, &caller, amount);
+		Ok(())
+	}
+
+	/// Remove the lock on the funds the `caller` voted with.
+	/// This function will return an error if the funds are still locked.
+	pub fn unlock(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+		let now = GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number();
+		let pallet: &mut Self = runtime.pallet_mut();
+		let lock = pallet.locks.try_get(&caller).ok_or("No voting lock")?;
+		if lock.until > now {
+			return Err("Vote is still locked".into());
+		}
+		pallet.locks.remove(&caller);
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.remove_lock(DEMOCRACY_ID, &caller);
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+	/// Close the referenda whose voting period is over.
+	fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) {
+		let pallet: &Self = runtime.pallet();
+		let ended = pallet
+			.referenda
+			.iter()
+			.filter(|(_, referendum)| referendum.end <= block_number)
+			.map(|(index, _)| *index)
+			.collect::<Vec<_>>();
+
+		for index in ended {
+			Self::close(runtime, index, block_number);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Conviction, Event, Pallet};
+	use crate::support::ensure::RawOrigin;
+	use crate::{balances, support, types, Runtime, RuntimeCall};
+
+	fn run_to_block(runtime: &mut Runtime, block_number: u32) {
+		while runtime.system.block_number() < block_number {
+			let header = support::Header { block_number: runtime.system.block_number() + 1, author: "alice".to_string() };
+			let block: types::Block = support::Block { header, extrinsics: vec![] };
+			runtime.execute_block(block).unwrap();
+		}
+	}
+
+	fn set_balance(who: &str, amount: u128) -> Box<RuntimeCall> {
+		let call = balances::Call::force_set_balance { who: who.to_string(), amount };
+		Box::new(RuntimeCall::balances(call))
+	}
+
+	#[test]
+	fn passed_proposal_is_enacted_as_root() {
+		let mut runtime = Runtime::new();
+		let (alice, bob, charlie) = ("alice".to_string(), "bob".to_string(), "charlie".to_string());
+		runtime.balances.set_balance(&alice, 100);
+		runtime.balances.set_balance(&bob, 100);
+		runtime.balances.set_balance(&charlie, 50);
+
+		// Only root can set balances directly.
+		assert_eq!(runtime.balances.force_set_balance(RawOrigin::Signed(alice.clone()), alice.clone(), 1), Err(support::DispatchError::BadOrigin));
+
+		assert_eq!(Pallet::propose(&mut runtime, alice.clone(), set_balance("dave", 1000), 1), Err("Deposit is too low".into()));
+		Pallet::propose(&mut runtime, alice.clone(), set_balance("dave", 1000), 10).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+
+		// `bob` votes against with more funds, but `charlie` has more conviction.
+		Pallet::vote(&mut runtime, bob.clone(), 0, false, 100, Conviction::None).unwrap();
+		Pallet::vote(&mut runtime, charlie.clone(), 0, true, 50, Conviction::Locked1x).unwrap();
+		assert_eq!(runtime.balances.transfer(charlie.clone(), alice.clone(), 1), Err("Funds are locked.".into()));
+
+		// Voting ends at block 3, and the proposal is enacted 2 blocks later.
+		run_to_block(&mut runtime, 3);
+		assert!(matches!(runtime.democracy.events().last(), Some(Event::Passed { index: 0, enactment: 5 })));
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+		run_to_block(&mut runtime, 5);
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 1000);
+		// The events of the block which closed the referendum are cleared by the following blocks.
+		assert!(runtime.democracy.events().is_empty());
+
+		// `bob` voted without conviction, so their funds are unlocked with the end of the referendum.
+		Pallet::unlock(&mut runtime, bob.clone()).unwrap();
+		assert_eq!(Pallet::unlock(&mut runtime, charlie.clone()), Err("Vote is still locked".into()));
+		run_to_block(&mut runtime, 8);
+		Pallet::unlock(&mut runtime, charlie.clone()).unwrap();
+		assert_eq!(runtime.balances.locked_balance(&charlie), 0);
+	}
+
+	#[test]
+	fn rejected_proposal_is_not_enacted() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		runtime.balances.set_balance(&alice, 100);
+		runtime.balances.set_balance(&bob, 100);
+
+		Pallet::propose(&mut runtime, alice.clone(), set_balance("dave", 1000), 10).unwrap();
+		Pallet::vote(&mut runtime, alice.clone(), 0, true, 50, Conviction::Locked1x).unwrap();
+		// Changing a vote replaces it in the tally.
+		Pallet::vote(&mut runtime, bob.clone(), 0, true, 100, Conviction::Locked1x).unwrap();
+		Pallet::vote(&mut runtime, bob.clone(), 0, false, 100, Conviction::Locked1x).unwrap();
+		assert_eq!(runtime.democracy.referendum(0).unwrap().tally.ayes, 500);
+
+		run_to_block(&mut runtime, 3);
+		assert!(matches!(runtime.democracy.events().last(), Some(Event::NotPassed { index: 0 })));
+		assert!(runtime.democracy.referendum(0).is_none());
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+	}
+
+	#[test]
+	fn proposal_without_turnout_is_not_enacted() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		// Nobody opposes the proposal, but a single unit of funds is far from the minimum turnout.
+		Pallet::propose(&mut runtime, alice.clone(), set_balance("dave", 1000), 10).unwrap();
+		Pallet::vote(&mut runtime, alice.clone(), 0, true, 1, Conviction::Locked3x).unwrap();
+		assert_eq!(runtime.democracy.referendum(0).unwrap().tally.turnout, 1);
+
+		run_to_block(&mut runtime, 3);
+		assert!(matches!(runtime.democracy.events().last(), Some(Event::NotPassed { index: 0 })));
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+	}
+
+	#[test]
+	fn root_account_cannot_skip_governance() {
+		let mut runtime = Runtime::new();
+		let (alice, root) = ("alice".to_string(), "root".to_string());
+		runtime.balances.set_balance(&alice, 100);
+		runtime.balances.set_balance(&root, 100);
+
+		// An extrinsic signed by an account named `root` is not made by the `Root` origin, so only
+		// the proposal of `root` goes through.
+		let extrinsics = vec![
+			support::Extrinsic { caller: root.clone(), call: *set_balance("dave", 1000) },
+			support::Extrinsic {
+				caller: root.clone(),
+				call: RuntimeCall::democracy(super::Call::propose { call: set_balance("dave", 1000), deposit: 10 }),
+			},
+		];
+		let block: types::Block = support::Block { header: support::Header { block_number: 1, author: "alice".to_string() }, extrinsics };
+		runtime.execute_block(block).unwrap();
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+		assert_eq!(runtime.balances.reserved_balance(&root), 10);
+
+		Pallet::vote(&mut runtime, alice.clone(), 0, true, 100, Conviction::None).unwrap();
+		// Voting ends at block 4, and the proposal is enacted as `Root` 2 blocks later.
+		run_to_block(&mut runtime, 6);
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 1000);
+	}
+
+	#[test]
+	fn overflows_leave_no_reserved_deposit_or_lock() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		runtime.democracy.next_index.put(u32::MAX);
+		assert_eq!(Pallet::propose(&mut runtime, alice.clone(), set_balance("dave", 1000), 10), Err("Overflow".into()));
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+
+		runtime.democracy.next_index.put(0);
+		Pallet::propose(&mut runtime, alice.clone(), set_balance("dave", 1000), 10).unwrap();
+		runtime.democracy.referenda.mutate(0, |referendum| referendum.as_mut().unwrap().end = u32::MAX - 1);
+		assert_eq!(Pallet::vote(&mut runtime, alice.clone(), 0, true, 50, Conviction::Locked1x), Err("Overflow".into()));
+		assert_eq!(runtime.democracy.referendum(0).unwrap().tally.turnout, 0);
+		assert_eq!(runtime.balances.locked_balance(&alice), 0);
+	}
+}
@@ -0,0 +1,343 @@
+use crate::support::storage::Encode;
+use crate::support::{DispatchResult, GetPallet, Hooks};
+
+/// The identifier given to every escrow.
+pub type EscrowId = u32;
+
+/// The configuration of the Escrow Module.
+///
+/// Escrowed funds are reserved in the balances pallet, and conditions can depend on the claims of
+/// the proof of existence pallet and on the block number, so the runtime must give access to them.
+pub trait Config:
+	crate::balances::Config
+	+ crate::proof_of_existence::Config<Content: Clone + PartialEq>
+	+ Clone
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+	+ GetPallet<crate::proof_of_existence::Pallet<Self>>
+	+ GetPallet<crate::system::Pallet<Self>>
+{
+}
+
+/// The condition to release escrowed funds to the payee.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Condition<AccountId, BlockNumber, Content> {
+	/// The block number reached the given block.
+	AtBlock(BlockNumber),
+	/// The payer owns the given claim in the proof of existence pallet.
+	ClaimOwned(Content),
+	/// The given arbiter approved the payment.
+	ArbiterApproval(AccountId),
+}
+
+/// A condition is encoded as the index of its variant, followed by its value.
+impl<AccountId: Encode, BlockNumber: Encode, Content: Encode> Encode for Condition<AccountId, BlockNumber, Content> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Condition::AtBlock(block_number) => (0u8, block_number).encode_to(dest),
+			Condition::ClaimOwned(claim) => (1u8, claim).encode_to(dest),
+			Condition::ArbiterApproval(arbiter) => (2u8, arbiter).encode_to(dest),
+		}
+	}
+}
+
+/// The condition of an escrow, as configured for the runtime.
+pub type ConditionOf<T> = Condition<
+	<T as crate::system::Config>::AccountId,
+	<T as crate::system::Config>::BlockNumber,
+	<T as crate::proof_of_existence::Config>::Content,
+>;
+
+/// Funds held until a condition is met.
+#[derive(Debug, Clone)]
+pub struct Escrow<T: Config> {
+	/// The account the funds are reserved from.
+	pub payer: T::AccountId,
+	/// The account the funds are released to.
+	pub payee: T::AccountId,
+	/// The amount held.
+	pub amount: T::Balance,
+	/// The condition to release the funds.
+	pub condition: ConditionOf<T>,
+	/// The block from which the funds are refunded to the payer if the condition is not met.
+	pub timeout: T::BlockNumber,
+	/// Whether the arbiter (if any) approved the payment.
+	pub approved: bool,
+}
+
+impl<T: Config> Encode for Escrow<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+	T::Content: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.payer.encode_to(dest);
+		self.payee.encode_to(dest);
+		self.amount.encode_to(dest);
+		self.condition.encode_to(dest);
+		self.timeout.encode_to(dest);
+		self.approved.encode_to(dest);
+	}
+}
+
+/// The events emitted by the Escrow Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// The escrow `id` was created.
+	Created { id: EscrowId, payer: T::AccountId, payee: T::AccountId, amount: T::Balance },
+	/// The funds of the escrow `id` were released to the payee.
+	Released { id: EscrowId },
+	/// The funds of the escrow `id` were refunded to the payer.
+	Refunded { id: EscrowId },
+}
+
+/// This is the Escrow Module.
+/// It allows a payer to hold funds for a payee until a condition is met, for example until the
+/// payee transfers a claim to the payer. If the condition is not met before a timeout, the funds
+/// are refunded to the payer.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from an escrow to its details, until it is released or refunded.
+	escrows: StorageMap<EscrowId, Escrow<T>>,
+	/// The id given to the next escrow.
+	#[default(0)]
+	next_id: StorageValue<EscrowId>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the details (if any) of the escrow `id`.
+	pub fn escrow(&self, id: EscrowId) -> Option<&Escrow<T>> {
+		self.escrows.try_get(&id)
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+}
+
+// The private functions of this block are helpers of the calls, rather than calls.
+#[macros::call(helpers, errors(
+	"Timeout must be in the future",
+	"Overflow",
+	"Escrow does not exist",
+	"Caller is not the arbiter of the escrow",
+	"Condition is not met",
+	"Not enough funds.",
+	"Funds are locked.",
+	"Not enough reserved funds.",
+))]
+impl<T: Config> Pallet<T> {
+	/// Reserve `amount` from the `caller`, to be released to `payee` once `condition` is met, or
+	/// refunded at block `timeout`.
+	/// This function will return an error if the timeout is not in the future.
+	pub fn create(
+		runtime: &mut T,
+		caller: T::AccountId,
+		payee: T::AccountId,
+		amount: T::Balance,
+		condition: ConditionOf<T>,
+		timeout: T::BlockNumber,
+	) -> DispatchResult {
+		if timeout <= GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number() {
+			return Err("Timeout must be in the future".into());
+		}
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.reserve(&caller, amount)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		let id = pallet.next_id.get();
+		pallet.next_id.put(id.checked_add(1).ok_or("Overflow")?);
+		let escrow = Escrow {
+			payer: caller.clone(),
+			payee: payee.clone(),
+			amount,
+			condition,
+			timeout,
+			approved: false,
+		};
+		pallet.escrows.insert(id, escrow);
+		pallet.events.push(Event::Created { id, payer: caller, payee, amount });
+
+		Ok(())
+	}
+
+	/// Approve the payment of the escrow `id` as its arbiter, which releases its funds.
+	/// This function should only succeed if the caller is the arbiter of the escrow.
+	pub fn approve(runtime: &mut T, caller: T::AccountId, id: EscrowId) -> DispatchResult {
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.escrows.try_mutate(id, |escrow| -> DispatchResult {
+			let escrow = escrow.as_mut().ok_or("Escrow does not exist")?;
+			if escrow.condition != Condition::ArbiterApproval(caller) {
+				return Err("Caller is not the arbiter of the escrow".into());
+			}
+
+			escrow.approved = true;
+			Ok(())
+		})?;
+		Self::settle(runtime, id, true)
+	}
+
+	/// Release the funds of the escrow `id` to its payee right away, rather than at the beginning
+	/// of the next block. Anyone can call this, since the funds can only go to the payee.
+	/// This function will return an error if the condition of the escrow is not met.
+	pub fn release(runtime: &mut T, _caller: T::AccountId, id: EscrowId) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let escrow = pallet.escrow(id).ok_or("Escrow does not exist")?;
+		if !Self::condition_met(runtime, escrow) {
+			return Err("Condition is not met".into());
+		}
+
+		Self::settle(runtime, id, true)
+	}
+
+	/// Whether the condition of `escrow` is met.
+	fn condition_met(runtime: &T, escrow: &Escrow<T>) -> bool {
+		match &escrow.condition {
+			Condition::AtBlock(block_number) => {
+				GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number() >= *block_number
+			},
+			Condition::ClaimOwned(claim) => {
+				let poe: &crate::proof_of_existence::Pallet<T> = runtime.pallet();
+				poe.get_claim(claim) == Some(&escrow.payer)
+			},
+			Condition::ArbiterApproval(_) => escrow.approved,
+		}
+	}
+
+	/// Pay the funds of the escrow `id` to the payee if `release`, or back to the payer otherwise,
+	/// and remove the escrow. If the funds cannot be paid, the escrow is kept.
+	fn settle(runtime: &mut T, id: EscrowId, release: bool) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let escrow = pallet.escrow(id).ok_or("Escrow does not exist")?;
+		let (payer, payee, amount) = (escrow.payer.clone(), escrow.payee.clone(), escrow.amount);
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		if release {
+			balances.repatriate_reserved(&payer, &payee, amount)?;
+		} else {
+			balances.unreserve(&payer, amount)?;
+		}
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.escrows.remove(&id);
+		pallet.events.push(if release { Event::Released { id } } else { Event::Refunded { id } });
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+	/// Release the escrows whose condition is met, and refund the ones which timed out.
+	fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) {
+		let pallet: &Self = runtime.pallet();
+		let due = pallet
+			.escrows
+			.iter()
+			.filter_map(|(id, escrow)| {
+				if Self::condition_met(runtime, escrow) {
+					Some((*id, true))
+				} else if escrow.timeout <= block_number {
+					Some((*id, false))
+				} else {
+					None
+				}
+			})
+			.collect::<Vec<_>>();
+
+		for (id, release) in due {
+			// If the funds cannot be paid, for example because the balance of the payee would
+			// overflow, the escrow is kept and settling it is tried again at the next block.
+			let _ = Self::settle(runtime, id, release);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Condition, Event, Pallet};
+	use crate::{support, types, Runtime};
+
+	fn run_to_block(runtime: &mut Runtime, block_number: u32) {
+		while runtime.system.block_number() < block_number {
+			let header = support::Header { block_number: runtime.system.block_number() + 1, author: "alice".to_string() };
+			let block: types::Block = support::Block { header, extrinsics: vec![] };
+			runtime.execute_block(block).unwrap();
+		}
+	}
+
+	#[test]
+	fn payment_is_released_once_the_claim_is_transferred() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		let claim = "document".to_string();
+		runtime.balances.set_balance(&alice, 100);
+		runtime.proof_of_existence.create_claim(bob.clone(), claim.clone()).unwrap();
+
+		let condition = Condition::ClaimOwned(claim.clone());
+		Pallet::create(&mut runtime, alice.clone(), bob.clone(), 60, condition, 10).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&alice), 60);
+		assert_eq!(Pallet::release(&mut runtime, bob.clone(), 0), Err("Condition is not met".into()));
+
+		runtime.proof_of_existence.transfer_claim(bob.clone(), claim, alice.clone()).unwrap();
+		Pallet::release(&mut runtime, bob.clone(), 0).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), 60);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert!(runtime.escrow.escrow(0).is_none());
+
+		// The helpers of the calls are not calls themselves.
+		let calls = Pallet::<Runtime>::call_metadata().into_iter().map(|call| call.name).collect::<Vec<_>>();
+		assert_eq!(calls, vec!["create", "approve", "release"]);
+	}
+
+	#[test]
+	fn escrow_is_settled_by_block_hooks_and_arbiters() {
+		let mut runtime = Runtime::new();
+		let (alice, bob, arbiter) = ("alice".to_string(), "bob".to_string(), "arbiter".to_string());
+		runtime.balances.set_balance(&alice, 100);
+
+		// Released at block 3.
+		Pallet::create(&mut runtime, alice.clone(), bob.clone(), 10, Condition::AtBlock(3), 5).unwrap();
+		// Refunded at block 4, unless the arbiter approves.
+		let condition = Condition::ArbiterApproval(arbiter.clone());
+		Pallet::create(&mut runtime, alice.clone(), bob.clone(), 20, condition.clone(), 4).unwrap();
+		Pallet::create(&mut runtime, alice.clone(), bob.clone(), 30, condition, 4).unwrap();
+
+		assert_eq!(Pallet::approve(&mut runtime, bob.clone(), 2), Err("Caller is not the arbiter of the escrow".into()));
+		Pallet::approve(&mut runtime, arbiter, 2).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), 30);
+
+		run_to_block(&mut runtime, 3);
+		assert_eq!(runtime.balances.balance(&bob), 40);
+		run_to_block(&mut runtime, 4);
+		assert!(matches!(runtime.escrow.events().last(), Some(Event::Refunded { id: 1 })));
+		assert_eq!(runtime.balances.balance(&alice), 60);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+	}
+
+	#[test]
+	fn escrow_is_kept_until_its_funds_can_be_paid() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		runtime.balances.set_balance(&alice, 100);
+		runtime.balances.set_balance(&bob, u128::MAX);
+
+		// The balance of `bob` would overflow, so the escrow and the reserved funds are kept.
+		Pallet::create(&mut runtime, alice.clone(), bob.clone(), 10, Condition::AtBlock(2), 5).unwrap();
+		run_to_block(&mut runtime, 2);
+		assert!(runtime.escrow.escrow(0).is_some());
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+
+		// Once `bob` can receive the funds, they are released at the next block.
+		runtime.balances.set_balance(&bob, 0);
+		run_to_block(&mut runtime, 3);
+		assert!(runtime.escrow.escrow(0).is_none());
+		assert_eq!(runtime.balances.balance(&bob), 10);
+	}
+}
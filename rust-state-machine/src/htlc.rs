@@ -0,0 +1,281 @@
+use core::fmt::Debug;
+use num::traits::Zero;
+use crate::support::storage::Encode;
+use crate::support::{DispatchResult, GetPallet};
+
+/// The configuration of the Hashed Time-Lock Contract Module.
+///
+/// Locked funds are reserved in the balances pallet, and deadlines are checked against the block
+/// number of the system pallet, so the runtime must give access to both.
+pub trait Config:
+	crate::balances::Config
+	+ Clone
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+	+ GetPallet<crate::system::Pallet<Self>>
+{
+	/// The type of the hash which locks a swap.
+	type Hash: Debug + Copy + Ord;
+
+	/// Hash `preimage`. To swap with another chain, both chains must use the same hash function.
+	fn hash(preimage: &[u8]) -> Self::Hash;
+}
+
+/// Funds locked by a swap, until the target claims them or the deadline passes.
+#[derive(Debug, Clone)]
+pub struct PendingSwap<T: Config> {
+	/// The account the funds are locked from.
+	pub source: T::AccountId,
+	/// The account which can claim the funds.
+	pub target: T::AccountId,
+	/// The amount locked.
+	pub amount: T::Balance,
+	/// The block from which the funds can no longer be claimed, and can be refunded to the source.
+	pub deadline: T::BlockNumber,
+}
+
+impl<T: Config> Encode for PendingSwap<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.source.encode_to(dest);
+		self.target.encode_to(dest);
+		self.amount.encode_to(dest);
+		self.deadline.encode_to(dest);
+	}
+}
+
+/// The events emitted by the Hashed Time-Lock Contract Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// `source` locked `amount` against `hash`, to be claimed by `target` before block `deadline`.
+	Created {
+		hash: T::Hash,
+		source: T::AccountId,
+		target: T::AccountId,
+		amount: T::Balance,
+		deadline: T::BlockNumber,
+	},
+	/// The swap of `source` locked by `hash` was claimed by revealing `proof`.
+	Claimed { source: T::AccountId, hash: T::Hash, proof: Vec<u8> },
+	/// The swap of `source` locked by `hash` expired, and was refunded to `source`.
+	Cancelled { source: T::AccountId, hash: T::Hash },
+}
+
+/// This is the Hashed Time-Lock Contract Module.
+/// It allows an account to lock funds against a hash, which the target of the swap can claim by
+/// revealing the preimage of the hash before a deadline. Since the preimage is then public, the
+/// same hash can lock funds in the other direction on another chain, making the swap atomic.
+///
+/// Swaps are identified by their source and their hash, so that once a hash is known, nobody else
+/// can prevent its source from locking funds against it.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from the source of a swap and the hash locking it to the swap.
+	swaps: StorageDoubleMap<T::AccountId, T::Hash, PendingSwap<T>>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the swap (if any) of `source` locked by `hash`.
+	pub fn swap(&self, source: &T::AccountId, hash: &T::Hash) -> Option<&PendingSwap<T>> {
+		self.swaps.try_get(source, hash)
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+}
+
+#[macros::call(errors(
+	"Swap already exists",
+	"Amount must not be zero",
+	"Deadline must be in the future",
+	"Swap does not exist",
+	"Caller is not the target of the swap",
+	"Swap has expired",
+	"Swap has not expired",
+	"Not enough funds.",
+	"Funds are locked.",
+	"Overflow",
+	"Not enough reserved funds.",
+))]
+impl<T: Config> Pallet<T> {
+	/// Lock `amount` from the `caller` against `hash`, to be claimed by `target` before block
+	/// `deadline`.
+	/// This function will return an error if the caller already locked a swap with `hash`, if the
+	/// amount is zero, or if the deadline is not in the future.
+	pub fn create_swap(
+		runtime: &mut T,
+		caller: T::AccountId,
+		target: T::AccountId,
+		hash: T::Hash,
+		amount: T::Balance,
+		deadline: T::BlockNumber,
+	) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		if pallet.swaps.contains_key(&caller, &hash) {
+			return Err("Swap already exists".into());
+		}
+		if amount.is_zero() {
+			return Err("Amount must not be zero".into());
+		}
+		if deadline <= GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number() {
+			return Err("Deadline must be in the future".into());
+		}
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.reserve(&caller, amount)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		let swap = PendingSwap { source: caller.clone(), target: target.clone(), amount, deadline };
+		pallet.swaps.insert(caller.clone(), hash, swap);
+		pallet.events.push(Event::Created { hash, source: caller, target, amount, deadline });
+		Ok(())
+	}
+
+	/// Claim the funds of the swap of `source` locked by the hash of `proof`.
+	/// This function should only succeed if the caller is the target of the swap, and if the
+	/// deadline has not passed.
+	pub fn claim_swap(runtime: &mut T, caller: T::AccountId, source: T::AccountId, proof: Vec<u8>) -> DispatchResult {
+		let hash = T::hash(&proof);
+		let now = GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number();
+		let pallet: &mut Self = runtime.pallet_mut();
+		let swap = pallet.swap(&source, &hash).ok_or("Swap does not exist")?;
+		if swap.target != caller {
+			return Err("Caller is not the target of the swap".into());
+		}
+		if swap.deadline <= now {
+			return Err("Swap has expired".into());
+		}
+		let amount = swap.amount;
+
+		// The funds are paid first, so that the swap is kept if it fails.
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.repatriate_reserved(&source, &caller, amount)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.swaps.remove(&source, &hash);
+		pallet.events.push(Event::Claimed { source, hash, proof });
+		Ok(())
+	}
+
+	/// Refund the funds of the swap of the `caller` locked by `hash`.
+	/// This function should only succeed if the deadline of the swap has passed.
+	pub fn cancel_swap(runtime: &mut T, caller: T::AccountId, hash: T::Hash) -> DispatchResult {
+		let now = GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number();
+		let pallet: &mut Self = runtime.pallet_mut();
+		let swap = pallet.swap(&caller, &hash).ok_or("Swap does not exist")?;
+		if swap.deadline > now {
+			return Err("Swap has not expired".into());
+		}
+		let amount = swap.amount;
+
+		// The funds are refunded first, so that the swap is kept if it fails.
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.unreserve(&caller, amount)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.swaps.remove(&caller, &hash);
+		pallet.events.push(Event::Cancelled { source: caller, hash });
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Config, Pallet};
+	use crate::{support, types, Runtime};
+
+	fn run_to_block(runtime: &mut Runtime, block_number: u32) {
+		while runtime.system.block_number() < block_number {
+			let header = support::Header { block_number: runtime.system.block_number() + 1, author: "alice".to_string() };
+			let block: types::Block = support::Block { header, extrinsics: vec![] };
+			runtime.execute_block(block).unwrap();
+		}
+	}
+
+	#[test]
+	fn target_claims_with_the_preimage() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		let hash = Runtime::hash(b"secret");
+		runtime.balances.set_balance(&alice, 100);
+
+		Pallet::create_swap(&mut runtime, alice.clone(), bob.clone(), hash, 40, 5).unwrap();
+		assert_eq!(
+			Pallet::create_swap(&mut runtime, alice.clone(), bob.clone(), hash, 40, 5),
+			Err("Swap already exists".into())
+		);
+		assert_eq!(
+			Pallet::claim_swap(&mut runtime, bob.clone(), alice.clone(), b"guess".to_vec()),
+			Err("Swap does not exist".into())
+		);
+		assert_eq!(
+			Pallet::claim_swap(&mut runtime, alice.clone(), alice.clone(), b"secret".to_vec()),
+			Err("Caller is not the target of the swap".into())
+		);
+		assert_eq!(Pallet::cancel_swap(&mut runtime, alice.clone(), hash), Err("Swap has not expired".into()));
+
+		// The swap is kept if its funds cannot be paid to `bob`.
+		runtime.balances.set_balance(&bob, u128::MAX);
+		assert_eq!(Pallet::claim_swap(&mut runtime, bob.clone(), alice.clone(), b"secret".to_vec()), Err("Overflow".into()));
+		assert!(runtime.htlc.swap(&alice, &hash).is_some());
+		runtime.balances.set_balance(&bob, 0);
+
+		Pallet::claim_swap(&mut runtime, bob.clone(), alice.clone(), b"secret".to_vec()).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), 40);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+	}
+
+	#[test]
+	fn source_refunds_after_the_deadline() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		let hash = Runtime::hash(b"secret");
+		runtime.balances.set_balance(&alice, 100);
+
+		Pallet::create_swap(&mut runtime, alice.clone(), bob.clone(), hash, 40, 5).unwrap();
+		run_to_block(&mut runtime, 5);
+		assert_eq!(
+			Pallet::claim_swap(&mut runtime, bob.clone(), alice.clone(), b"secret".to_vec()),
+			Err("Swap has expired".into())
+		);
+
+		assert_eq!(Pallet::cancel_swap(&mut runtime, bob.clone(), hash), Err("Swap does not exist".into()));
+		Pallet::cancel_swap(&mut runtime, alice.clone(), hash).unwrap();
+		assert_eq!(runtime.balances.balance(&alice), 100);
+		assert!(runtime.htlc.swap(&alice, &hash).is_none());
+	}
+
+	#[test]
+	fn others_cannot_block_a_swap_with_the_same_hash() {
+		let mut runtime = Runtime::new();
+		let (alice, bob, mallory) = ("alice".to_string(), "bob".to_string(), "mallory".to_string());
+		let hash = Runtime::hash(b"secret");
+		runtime.balances.set_balance(&alice, 100);
+		runtime.balances.set_balance(&mallory, 100);
+
+		// Once the hash is public, `mallory` locks funds against it first, but cannot lock nothing.
+		assert_eq!(
+			Pallet::create_swap(&mut runtime, mallory.clone(), bob.clone(), hash, 0, 1000),
+			Err("Amount must not be zero".into())
+		);
+		Pallet::create_swap(&mut runtime, mallory.clone(), bob.clone(), hash, 1, 1000).unwrap();
+
+		// The swap of `alice` is kept apart, and `bob` claims each of them from its source.
+		Pallet::create_swap(&mut runtime, alice.clone(), bob.clone(), hash, 40, 5).unwrap();
+		Pallet::claim_swap(&mut runtime, bob.clone(), alice.clone(), b"secret".to_vec()).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), 40);
+		assert_eq!(runtime.htlc.swap(&mallory, &hash).unwrap().amount, 1);
+	}
+}
@@ -0,0 +1,330 @@
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+use crate::support::ensure::{ensure_root, OriginFor};
+use crate::support::storage::Encode;
+use crate::support::{DispatchError, DispatchResult, GetPallet, OnUnbalanced};
+
+/// The index of a registrar, in the order they were added.
+pub type RegistrarIndex = u32;
+
+/// The configuration of the Identity Module.
+///
+/// Deposits are reserved in the balances pallet, so the runtime must give access to it.
+pub trait Config:
+	crate::balances::Config
+	+ Clone
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+{
+	/// The amount reserved to register an identity.
+	const BASIC_DEPOSIT: Self::Balance;
+	/// The amount reserved for every optional field set in an identity.
+	const FIELD_DEPOSIT: Self::Balance;
+
+	/// The handler of the deposits slashed when an identity is killed.
+	type Slashed: OnUnbalanced<Self, Self::Balance>;
+}
+
+/// The information an account gives about itself.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdentityInfo {
+	/// The name to display instead of the account id.
+	pub display: String,
+	/// An email address.
+	pub email: Option<String>,
+	/// A website.
+	pub web: Option<String>,
+}
+
+impl Encode for IdentityInfo {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.display.encode_to(dest);
+		self.email.encode_to(dest);
+		self.web.encode_to(dest);
+	}
+}
+
+impl IdentityInfo {
+	/// The number of optional fields which are set.
+	fn additional_fields(&self) -> usize {
+		[&self.email, &self.web].iter().filter(|field| field.is_some()).count()
+	}
+}
+
+/// The opinion of a registrar about the identity of an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Judgement {
+	/// The account asked for a judgement, which the registrar has not given yet.
+	Requested,
+	/// The registrar cannot tell whether the identity is correct.
+	Unknown,
+	/// The identity looks correct.
+	Reasonable,
+	/// The registrar made sure the identity is correct.
+	KnownGood,
+	/// The identity used to be correct, but is not anymore.
+	OutOfDate,
+	/// The identity is not trustworthy.
+	LowQuality,
+	/// The identity is wrong, possibly on purpose.
+	Erroneous,
+}
+
+/// A judgement is encoded as the index of its variant.
+impl Encode for Judgement {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(*self as u8);
+	}
+}
+
+/// The identity of an account.
+#[derive(Debug, Clone)]
+pub struct Registration<T: Config> {
+	/// The information given by the account.
+	pub info: IdentityInfo,
+	/// The amount reserved from the account.
+	pub deposit: T::Balance,
+	/// The judgements of the registrars on this identity.
+	pub judgements: Vec<(RegistrarIndex, Judgement)>,
+}
+
+impl<T: Config> Encode for Registration<T>
+where
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.info.encode_to(dest);
+		self.deposit.encode_to(dest);
+		self.judgements.encode_to(dest);
+	}
+}
+
+/// This is the Identity Module.
+/// It allows accounts to register information about themselves, like a name to display instead of
+/// their account id, backed by a deposit. Registrars can then judge whether this information is
+/// correct.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from an account to its identity.
+	identities: StorageMap<T::AccountId, Registration<T>>,
+	/// The accounts of the registrars, indexed by their `RegistrarIndex`.
+	#[default(Vec::new())]
+	registrars: StorageValue<Vec<T::AccountId>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the identity (if any) of `who`.
+	pub fn identity(&self, who: &T::AccountId) -> Option<&Registration<T>> {
+		self.identities.try_get(who)
+	}
+
+	/// Get the name (if any) to display for `who`.
+	pub fn display_name(&self, who: &T::AccountId) -> Option<&str> {
+		self.identity(who).map(|registration| registration.info.display.as_str())
+	}
+
+	/// Get the accounts of the registrars, indexed by their `RegistrarIndex`.
+	pub fn registrars(&self) -> &[T::AccountId] {
+		self.registrars.try_get().map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	/// The deposit required for `info`.
+	fn deposit_for(info: &IdentityInfo) -> Result<T::Balance, DispatchError> {
+		(0..info.additional_fields()).try_fold(T::BASIC_DEPOSIT, |deposit, _| {
+			deposit.checked_add(&T::FIELD_DEPOSIT).ok_or("Overflow".into())
+		})
+	}
+}
+
+#[macros::call(errors(
+	"Underflow",
+	"No identity",
+	"Registrar does not exist",
+	"Judgement already requested",
+	"Judgement already given",
+	"Caller is not the registrar",
+	"Invalid judgement",
+	"Judgement was not requested",
+	"Overflow",
+	"Not enough funds.",
+	"Funds are locked.",
+	"Not enough reserved funds.",
+))]
+impl<T: Config> Pallet<T> {
+	/// Set the identity of the `caller`, replacing any previous one along with its judgements.
+	/// The deposit reserved from the caller is adjusted to the number of fields set.
+	pub fn set_identity(
+		runtime: &mut T,
+		caller: T::AccountId,
+		display: String,
+		email: Option<String>,
+		web: Option<String>,
+	) -> DispatchResult {
+		let info = IdentityInfo { display, email, web };
+		let deposit = Self::deposit_for(&info)?;
+
+		let pallet: &Self = runtime.pallet();
+		let old_deposit = pallet.identity(&caller).map(|registration| registration.deposit).unwrap_or(Zero::zero());
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		if deposit > old_deposit {
+			balances.reserve(&caller, deposit.checked_sub(&old_deposit).ok_or("Underflow")?)?;
+		} else {
+			balances.unreserve(&caller, old_deposit.checked_sub(&deposit).ok_or("Underflow")?)?;
+		}
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.identities.insert(caller, Registration { info, deposit, judgements: Vec::new() });
+		Ok(())
+	}
+
+	/// Clear the identity of the `caller`, and return its deposit.
+	pub fn clear_identity(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let deposit = pallet.identity(&caller).ok_or("No identity")?.deposit;
+
+		// The deposit is returned first, so that the identity is kept if it fails.
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.unreserve(&caller, deposit)?;
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.identities.remove(&caller);
+		Ok(())
+	}
+
+	/// Ask the registrar `registrar` to judge the identity of the `caller`.
+	/// This function will return an error if the registrar already gave a judgement other than
+	/// `Unknown`, since that judgement can only change with the identity.
+	pub fn request_judgement(&mut self, caller: T::AccountId, registrar: RegistrarIndex) -> DispatchResult {
+		if self.registrars().get(registrar as usize).is_none() {
+			return Err("Registrar does not exist".into());
+		}
+		self.identities.try_mutate(caller, |registration| -> DispatchResult {
+			let registration = registration.as_mut().ok_or("No identity")?;
+
+			match registration.judgements.iter_mut().find(|(index, _)| *index == registrar) {
+				Some((_, Judgement::Requested)) => return Err("Judgement already requested".into()),
+				Some((_, judgement)) if *judgement != Judgement::Unknown => return Err("Judgement already given".into()),
+				Some((_, judgement)) => *judgement = Judgement::Requested,
+				None => registration.judgements.push((registrar, Judgement::Requested)),
+			}
+			Ok(())
+		})
+	}
+
+	/// Give the `judgement` of the registrar `registrar` on the identity of `target`.
+	/// This function should only succeed if the caller is the account of the registrar, and if
+	/// `target` requested a judgement from it.
+	pub fn provide_judgement(
+		&mut self,
+		caller: T::AccountId,
+		registrar: RegistrarIndex,
+		target: T::AccountId,
+		judgement: Judgement,
+	) -> DispatchResult {
+		if self.registrars().get(registrar as usize) != Some(&caller) {
+			return Err("Caller is not the registrar".into());
+		}
+		if judgement == Judgement::Requested {
+			return Err("Invalid judgement".into());
+		}
+		self.identities.try_mutate(target, |registration| -> DispatchResult {
+			let registration = registration.as_mut().ok_or("No identity")?;
+			let (_, current) = registration
+				.judgements
+				.iter_mut()
+				.find(|(index, judgement)| *index == registrar && *judgement == Judgement::Requested)
+				.ok_or("Judgement was not requested")?;
+
+			*current = judgement;
+			Ok(())
+		})
+	}
+
+	/// Add `account` as a registrar.
+	/// This function should only succeed if the caller is the `Root` origin.
+	pub fn add_registrar(&mut self, origin: OriginFor<T>, account: T::AccountId) -> DispatchResult {
+		ensure_root(origin)?;
+		self.registrars.mutate(|registrars| registrars.push(account));
+		Ok(())
+	}
+
+	/// Remove the identity of `target`, and slash its deposit.
+	/// This function should only succeed if the caller is the `Root` origin.
+	pub fn kill_identity(runtime: &mut T, origin: OriginFor<T>, target: T::AccountId) -> DispatchResult {
+		ensure_root(origin)?;
+		let pallet: &mut Self = runtime.pallet_mut();
+		let registration = pallet.identities.remove(&target).ok_or("No identity")?;
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		let slashed = balances.slash_reserved(&target, registration.deposit);
+		T::Slashed::on_unbalanced(runtime, slashed);
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Judgement, Pallet};
+	use crate::support::ensure::RawOrigin;
+	use crate::support::DispatchError;
+	use crate::Runtime;
+
+	#[test]
+	fn identity_deposit_follows_its_fields() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		Pallet::set_identity(&mut runtime, alice.clone(), "Alice".to_string(), None, None).unwrap();
+		assert_eq!(runtime.identity.display_name(&alice), Some("Alice"));
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+
+		let email = Some("alice@example.com".to_string());
+		let web = Some("alice.example.com".to_string());
+		Pallet::set_identity(&mut runtime, alice.clone(), "Alice".to_string(), email.clone(), web).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&alice), 14);
+		Pallet::set_identity(&mut runtime, alice.clone(), "Alice".to_string(), email, None).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&alice), 12);
+
+		// The identity is kept if its deposit cannot be returned.
+		runtime.balances.set_balance(&alice, u128::MAX);
+		assert_eq!(Pallet::clear_identity(&mut runtime, alice.clone()), Err("Overflow".into()));
+		assert_eq!(runtime.identity.display_name(&alice), Some("Alice"));
+		runtime.balances.set_balance(&alice, 88);
+
+		Pallet::clear_identity(&mut runtime, alice.clone()).unwrap();
+		assert_eq!(runtime.identity.display_name(&alice), None);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert_eq!(runtime.balances.balance(&alice), 100);
+	}
+
+	#[test]
+	fn registrars_judge_requested_identities() {
+		let mut runtime = Runtime::new();
+		let (alice, registrar) = ("alice".to_string(), "registrar".to_string());
+		runtime.balances.set_balance(&alice, 100);
+		Pallet::set_identity(&mut runtime, alice.clone(), "Alice".to_string(), None, None).unwrap();
+
+		assert_eq!(runtime.identity.add_registrar(RawOrigin::Signed(alice.clone()), alice.clone()), Err(DispatchError::BadOrigin));
+		runtime.identity.add_registrar(RawOrigin::Root, registrar.clone()).unwrap();
+		assert_eq!(
+			runtime.identity.provide_judgement(registrar.clone(), 0, alice.clone(), Judgement::KnownGood),
+			Err("Judgement was not requested".into())
+		);
+
+		runtime.identity.request_judgement(alice.clone(), 0).unwrap();
+		assert_eq!(
+			runtime.identity.provide_judgement(alice.clone(), 0, alice.clone(), Judgement::KnownGood),
+			Err("Caller is not the registrar".into())
+		);
+		runtime.identity.provide_judgement(registrar.clone(), 0, alice.clone(), Judgement::KnownGood).unwrap();
+		assert_eq!(runtime.identity.identity(&alice).unwrap().judgements, vec![(0, Judgement::KnownGood)]);
+		assert_eq!(runtime.identity.request_judgement(alice.clone(), 0), Err("Judgement already given".into()));
+
+		// Killing an identity slashes its deposit into the treasury.
+		Pallet::kill_identity(&mut runtime, RawOrigin::Root, alice.clone()).unwrap();
+		assert!(runtime.identity.identity(&alice).is_none());
+		assert_eq!(runtime.balances.balance(&crate::treasury::Pallet::<Runtime>::account_id()), 10);
+	}
+}
@@ -0,0 +1,39 @@
+//! Tests of the code generated by the macros, on pallets which only exist to exercise them.
+
+use crate::system::Config;
+use crate::Runtime;
+
+// A pallet with a type parameter besides `T` and a where clause, which the generated `Call` enum
+// and dispatch logic must keep.
+struct Tags<T: Config, Tag> {
+	tags: Vec<(T::AccountId, Tag)>,
+}
+
+#[macros::call(errors("Already tagged"))]
+impl<T: Config, Tag> Tags<T, Tag>
+where
+	Tag: Clone + PartialEq,
+{
+	/// Tag the `caller` with `tag`, unless they already have it.
+	pub fn tag(&mut self, caller: T::AccountId, tag: Tag) -> crate::support::DispatchResult {
+		if self.tags.iter().any(|(who, other)| *who == caller && *other == tag) {
+			return Err("Already tagged".into());
+		}
+		self.tags.push((caller, tag));
+		Ok(())
+	}
+}
+
+#[test]
+fn calls_keep_the_generics_of_the_pallet() {
+	use crate::support::Dispatch;
+
+	let mut tags = Tags::<Runtime, u8> { tags: Vec::new() };
+	let call: Call<Runtime, u8> = Call::tag { tag: 7 };
+	assert_eq!(format!("{:?}", call), "tag { tag: 7 }");
+	let alice = crate::support::ensure::RawOrigin::Signed("alice".to_string());
+	tags.dispatch(alice.clone(), call.clone()).unwrap();
+	assert_eq!(tags.dispatch(alice, call), Err("Already tagged".into()));
+	let docs = Tags::<Runtime, u8>::call_metadata()[0].docs.clone();
+	assert_eq!(docs, vec!["Tag the `caller` with `tag`, unless they already have it."]);
+}
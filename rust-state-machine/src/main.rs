@@ -1,9 +1,23 @@
+mod assets;
 mod balances;
+mod democracy;
+mod escrow;
+mod htlc;
+mod identity;
+mod multisig;
+mod nfts;
 mod proof_of_existence;
+mod proxy;
+mod safe_mode;
+mod scheduler;
+mod staking;
+mod treasury;
+mod utility;
+mod vesting;
 mod system;
 mod support;
-
-use crate::support::Dispatch;
+#[cfg(test)]
+mod macro_tests;
 
 // These are the concrete types we will use in our simple state machine.
 // Modules are configured for these types directly, and they satisfy all of our
@@ -16,19 +30,90 @@ mod types {
     pub type BlockNumber = u32;
     pub type Nonce = u32;
     pub type Extrinsic = support::Extrinsic<AccountId, crate::RuntimeCall>;
-    pub type Header = support::Header<BlockNumber>;
+    pub type Header = support::Header<BlockNumber, AccountId>;
     pub type Block = support::Block<Header, Extrinsic>;
     pub type Content = String;
+    pub type AssetId = u32;
+    pub type CollectionId = u32;
+    pub type ItemId = u32;
+    pub type Hash = [u8; 32];
 }
 
 // This is our main Runtime.
 // It accumulates all of the different pallets we want to use.
-#[macros::runtime]
-#[derive(Debug)]
+// Pallets are given explicit indices, so reordering them does not change the encoding of calls.
+// The safe mode pallet filters the calls, so they can be paused when something goes wrong.
+// Every extrinsic pays a fee, which is shared between the treasury and a burn.
+// Blocks can only be authored by the validators elected by the staking pallet.
+#[macros::runtime(
+    block = types::Block,
+    system = system,
+    migrations = Migrations,
+    call_filter = safe_mode::Pallet<Runtime>,
+    fee = TransactionFee,
+    authorities = staking::Pallet<Runtime>,
+    strict
+)]
+#[derive(Debug, Clone)]
 pub struct Runtime {
+    #[pallet_index(0)]
     system: system::Pallet<Self>,
+    #[pallet_index(1)]
     balances: balances::Pallet<Self>,
+    #[pallet_index(2)]
     proof_of_existence: proof_of_existence::Pallet<Self>,
+    #[pallet_index(3)]
+    scheduler: scheduler::Pallet<Self>,
+    #[pallet_index(4)]
+    utility: utility::Pallet<Self>,
+    #[pallet_index(5)]
+    multisig: multisig::Pallet<Self>,
+    #[pallet_index(6)]
+    proxy: proxy::Pallet<Self>,
+    #[pallet_index(7)]
+    vesting: vesting::Pallet<Self>,
+    #[pallet_index(8)]
+    assets: assets::Pallet<Self>,
+    #[pallet_index(9)]
+    nfts: nfts::Pallet<Self>,
+    #[pallet_index(10)]
+    democracy: democracy::Pallet<Self>,
+    #[pallet_index(11)]
+    treasury: treasury::Pallet<Self>,
+    #[pallet_index(12)]
+    staking: staking::Pallet<Self>,
+    #[pallet_index(13)]
+    identity: identity::Pallet<Self>,
+    #[pallet_index(14)]
+    escrow: escrow::Pallet<Self>,
+    #[pallet_index(15)]
+    htlc: htlc::Pallet<Self>,
+    // A second instance of the balances pallet, for a reward token kept apart from the native one.
+    #[pallet_index(16)]
+    rewards: balances::Pallet<Self, support::Instance1>,
+    #[pallet_index(17)]
+    safe_mode: safe_mode::Pallet<Self>,
+}
+
+// The migrations to run when upgrading the runtime, in order. Once a migration ran on every
+// chain running this runtime, it can be removed from this list.
+type Migrations = (
+    balances::migrations::MigrateToV1<Runtime>,
+    balances::migrations::MigrateToV1<Runtime, support::Instance1>,
+);
+
+// The flat fee paid in the native token by the caller of every extrinsic.
+const TRANSACTION_FEE: types::Balance = 2;
+
+// Charges the transaction fee, and hands it to the treasury which keeps its share of it.
+pub struct TransactionFee;
+
+impl support::ChargeFee<Runtime, types::AccountId, RuntimeCall> for TransactionFee {
+    fn charge_fee(runtime: &mut Runtime, who: &types::AccountId, _call: &RuntimeCall) -> support::DispatchResult {
+        runtime.balances.withdraw(who, TRANSACTION_FEE)?;
+        <treasury::FeeShare<Runtime> as support::OnUnbalanced<_, _>>::on_unbalanced(runtime, TRANSACTION_FEE);
+        Ok(())
+    }
 }
 
 impl system::Config for Runtime {
@@ -43,12 +128,153 @@ impl balances::Config for Runtime {
     type Balance = types::Balance;
 }
 
+// The reward token is configured separately from the native token.
+impl balances::Config<support::Instance1> for Runtime {
+    type Balance = types::Balance;
+}
+
 // Implement the `proof_of_existence::Config` trait you created on your `Runtime`.
 // Use `Self` to satisfy the generic parameter required for `proof_of_existence::Pallet`.
 impl proof_of_existence::Config for Runtime {
     type Content = types::Content;
 }
 
+impl scheduler::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+
+    const MAX_SCHEDULED_PER_BLOCK: u32 = 50;
+}
+
+impl utility::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+}
+
+impl multisig::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+
+    const DEPOSIT: types::Balance = 5;
+
+    // The multisig account is simply named after its threshold and signatories. Each signatory is
+    // prefixed with its length, so that signatories containing the separator cannot collide.
+    fn multi_account_id(signatories: &[types::AccountId], threshold: u16) -> types::AccountId {
+        let signatories: Vec<String> =
+            signatories.iter().map(|who| format!("{}:{}", who.len(), who)).collect();
+        format!("multisig/{}/{}", threshold, signatories.join(","))
+    }
+}
+
+// The kinds of proxy an account can register, and the calls each of them is allowed to make.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProxyType {
+    /// Allows any call.
+    Any,
+    /// Only allows balance transfers.
+    Transfer,
+    /// Only allows calls to the proof of existence pallet.
+    ProofOfExistence,
+}
+
+impl support::storage::Encode for ProxyType {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        let index: u8 = match self {
+            ProxyType::Any => 0,
+            ProxyType::Transfer => 1,
+            ProxyType::ProofOfExistence => 2,
+        };
+        index.encode_to(dest);
+    }
+}
+
+impl proxy::InstanceFilter<RuntimeCall> for ProxyType {
+    fn filter(&self, call: &RuntimeCall) -> bool {
+        match self {
+            ProxyType::Any => true,
+            ProxyType::Transfer => {
+                matches!(call, RuntimeCall::balances(balances::Call::transfer { .. }))
+            },
+            ProxyType::ProofOfExistence => matches!(call, RuntimeCall::proof_of_existence(_)),
+        }
+    }
+}
+
+impl proxy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type ProxyType = ProxyType;
+
+    const DEPOSIT: types::Balance = 5;
+    const MAX_PROXIES: u32 = 3;
+}
+
+impl vesting::Config for Runtime {
+    const MAX_VESTING_SCHEDULES: u32 = 3;
+    const MIN_VESTED_TRANSFER: types::Balance = 10;
+
+    fn block_number_to_balance(block_number: types::BlockNumber) -> types::Balance {
+        block_number.into()
+    }
+}
+
+impl assets::Config for Runtime {
+    type AssetId = types::AssetId;
+    type AssetBalance = types::Balance;
+}
+
+impl nfts::Config for Runtime {
+    type CollectionId = types::CollectionId;
+    type ItemId = types::ItemId;
+}
+
+impl democracy::Config for Runtime {
+    const MINIMUM_DEPOSIT: types::Balance = 10;
+    const MINIMUM_TURNOUT: types::Balance = 100;
+    const VOTING_PERIOD: types::BlockNumber = 3;
+    const ENACTMENT_PERIOD: types::BlockNumber = 2;
+    const VOTE_LOCKING_PERIOD: types::BlockNumber = 5;
+}
+
+impl treasury::Config for Runtime {
+    const PALLET_ID: support::PalletId = *b"py/trsry";
+    const PROPOSAL_BOND: types::Balance = 10;
+    const SPEND_PERIOD: types::BlockNumber = 5;
+    const FEE_SHARE: u8 = 50;
+
+    // The account of a pallet is simply named after its identifier.
+    fn pallet_account(id: support::PalletId) -> types::AccountId {
+        format!("modl/{}", String::from_utf8_lossy(&id))
+    }
+}
+
+impl staking::Config for Runtime {
+    const ERA_LENGTH: types::BlockNumber = 5;
+    const VALIDATOR_COUNT: u32 = 2;
+    const BONDING_DURATION: staking::EraIndex = 1;
+    const ERA_REWARD: types::Balance = 100;
+
+    type Slash = treasury::Pallet<Self>;
+}
+
+impl identity::Config for Runtime {
+    const BASIC_DEPOSIT: types::Balance = 10;
+    const FIELD_DEPOSIT: types::Balance = 2;
+
+    type Slashed = treasury::Pallet<Self>;
+}
+
+impl escrow::Config for Runtime {}
+
+impl htlc::Config for Runtime {
+    type Hash = types::Hash;
+
+    fn hash(preimage: &[u8]) -> types::Hash {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(preimage).into()
+    }
+}
+
+impl safe_mode::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+}
+
 fn main() {
 	// Create a new instance of the Runtime.
 	// It will instantiate with it all the modules it uses.
@@ -59,9 +285,12 @@ fn main() {
 	// Initialize the system with some initial balance.
 	runtime.balances.set_balance(&alice, 100);
 
+	// Run the migrations of the runtime, as would happen when upgrading an existing chain.
+	runtime.upgrade().expect("invalid migrations");
+
     // Create a new block with the extrinsics.
     let block_1 = types::Block {
-        header: support::Header { block_number: 1 },
+        header: support::Header { block_number: 1, author: alice.clone() },
         extrinsics: vec![
             support::Extrinsic {
                 caller: alice.clone(),
@@ -72,7 +301,7 @@ fn main() {
 
     // create a new block with the extrinsics for the proof of existence module.
     let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
+        header: support::Header { block_number: 2, author: alice.clone() },
         extrinsics: vec![
             support::Extrinsic {
                 caller: alice.clone(),
@@ -81,10 +310,35 @@ fn main() {
         ],
     };
 
+    // create a new block which schedules a transfer to be dispatched in the next block.
+    let block_3 = types::Block {
+        header: support::Header { block_number: 3, author: alice.clone() },
+        extrinsics: vec![
+            support::Extrinsic {
+                caller: alice.clone(),
+                call: RuntimeCall::scheduler(scheduler::Call::schedule {
+                    when: 4,
+                    period: None,
+                    call: Box::new(RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 10 })),
+                }),
+            },
+        ],
+    };
+
+    // the scheduled transfer is dispatched at the beginning of this empty block.
+    let block_4 = types::Block {
+        header: support::Header { block_number: 4, author: alice.clone() },
+        extrinsics: vec![],
+    };
+
     runtime.execute_block(block_1).expect("invalid block");
 
     runtime.execute_block(block_2).expect("invalid block");
 
+    runtime.execute_block(block_3).expect("invalid block");
+
+    runtime.execute_block(block_4).expect("invalid block");
+
 	// Simply print the debug format of our runtime state.
 	println!("{:#?}", runtime);
 }
\ No newline at end of file
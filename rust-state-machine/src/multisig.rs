@@ -0,0 +1,419 @@
+use core::fmt::Debug;
+use sha2::{Digest, Sha256};
+use crate::support::ensure::{OriginFor, RawOrigin};
+use crate::support::storage::Encode;
+use crate::support::{Dispatch, DispatchError, DispatchResult, GetPallet};
+
+/// The SHA-256 hash of a call, which signatories approve.
+pub type CallHash = [u8; 32];
+
+/// The configuration of the Multisig Module.
+///
+/// Approved calls are dispatched through the runtime, and deposits are held in the balances
+/// pallet, so the runtime must give access to both.
+pub trait Config:
+	crate::balances::Config
+	+ Clone
+	+ Dispatch<Caller = OriginFor<Self>, Call = <Self as Config>::RuntimeCall>
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+{
+	/// The aggregated call type of the runtime, which is what signatories approve.
+	type RuntimeCall: Debug + Clone + Encode;
+
+	/// The deposit reserved from the first signatory approving a call, until the call is
+	/// dispatched or canceled.
+	const DEPOSIT: Self::Balance;
+
+	/// Derive the account of the multisig made of the sorted `signatories` and `threshold`.
+	/// The same signatories and threshold must always give the same account.
+	fn multi_account_id(signatories: &[Self::AccountId], threshold: u16) -> Self::AccountId;
+}
+
+/// A call which is being approved by the signatories of a multisig.
+#[derive(Debug, Clone)]
+pub struct Multisig<T: Config> {
+	/// The signatory which made the first approval, and who holds the deposit.
+	pub depositor: T::AccountId,
+	/// The signatories which approved the call so far, sorted.
+	pub approvals: Vec<T::AccountId>,
+	/// The call being approved, once one of the signatories provided it.
+	pub call: Option<T::RuntimeCall>,
+}
+
+impl<T: Config> Encode for Multisig<T>
+where
+	T::AccountId: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.depositor.encode_to(dest);
+		self.approvals.encode_to(dest);
+		self.call.encode_to(dest);
+	}
+}
+
+/// The events emitted by the Multisig Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// The first approval of the call `call_hash` by `multisig` was made by `approving`.
+	NewMultisig { approving: T::AccountId, multisig: T::AccountId, call_hash: CallHash },
+	/// The call `call_hash` by `multisig` was approved by `approving`.
+	MultisigApproval { approving: T::AccountId, multisig: T::AccountId, call_hash: CallHash },
+	/// The call `call_hash` by `multisig` reached its threshold, and was dispatched with `result`.
+	MultisigExecuted { multisig: T::AccountId, call_hash: CallHash, result: DispatchResult },
+	/// The call `call_hash` by `multisig` was canceled by `cancelling`.
+	MultisigCancelled { cancelling: T::AccountId, multisig: T::AccountId, call_hash: CallHash },
+}
+
+/// This is the Multisig Module.
+/// It allows a set of signatories to share an account, which can only dispatch a call once a
+/// threshold of the signatories approved it.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from a multisig account and the hash of a call to the approvals of that call.
+	multisigs: StorageDoubleMap<T::AccountId, CallHash, Multisig<T>>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the approvals (if any) of the call `call_hash` by `multisig`.
+	pub fn multisig(&self, multisig: &T::AccountId, call_hash: CallHash) -> Option<&Multisig<T>> {
+		self.multisigs.try_get(multisig, &call_hash)
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+
+	/// Get the hash of a `call`, as approved by the signatories.
+	/// The call is encoded with the index of its pallet and its call index, followed by its
+	/// arguments, so different calls are never encoded the same way.
+	pub fn hash_call(call: &T::RuntimeCall) -> CallHash {
+		Sha256::digest(call.encode()).into()
+	}
+
+	/// Get the account of the multisig made of `who`, `other_signatories` and `threshold`.
+	/// This function will return an error if the signatories are not unique, or if the threshold
+	/// cannot be reached by the signatories.
+	pub fn multi_account_id(
+		who: &T::AccountId,
+		other_signatories: &[T::AccountId],
+		threshold: u16,
+	) -> Result<T::AccountId, DispatchError> {
+		let mut signatories = other_signatories.to_vec();
+		signatories.push(who.clone());
+		signatories.sort();
+
+		if signatories.windows(2).any(|pair| pair[0] == pair[1]) {
+			return Err("Signatories must be unique".into());
+		}
+		if threshold == 0 || usize::from(threshold) > signatories.len() {
+			return Err("Threshold must be between one and the number of signatories".into());
+		}
+
+		Ok(T::multi_account_id(&signatories, threshold))
+	}
+
+	/// Record an `event` emitted by this pallet.
+	fn deposit_event(runtime: &mut T, event: Event<T>) {
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.events.push(event);
+	}
+
+	/// Add the approval of `caller` to the call `call_hash` by their multisig, storing the `call`
+	/// if it is provided. Once the threshold is reached and the call is known, it is dispatched
+	/// on behalf of the multisig account.
+	fn approve_as(
+		runtime: &mut T,
+		caller: T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u16,
+		call_hash: CallHash,
+		maybe_call: Option<T::RuntimeCall>,
+	) -> DispatchResult {
+		let multisig = Self::multi_account_id(&caller, &other_signatories, threshold)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		match pallet.multisigs.get(&multisig, &call_hash) {
+			Some(mut approvals) => {
+				let already_approved = approvals.approvals.binary_search(&caller);
+				match already_approved {
+					// The caller already approved, and is not providing a missing call.
+					Ok(_) if approvals.call.is_some() || maybe_call.is_none() => {
+						return Err("Caller already approved the call".into());
+					},
+					Ok(_) => {},
+					Err(index) => approvals.approvals.insert(index, caller.clone()),
+				}
+				if approvals.call.is_none() {
+					approvals.call = maybe_call;
+				}
+				pallet.multisigs.insert(multisig.clone(), call_hash, approvals);
+
+				let event = Event::MultisigApproval { approving: caller, multisig: multisig.clone(), call_hash };
+				Self::deposit_event(runtime, event);
+			},
+			None => {
+				let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+				balances.reserve(&caller, T::DEPOSIT)?;
+
+				let approvals = Multisig {
+					depositor: caller.clone(),
+					approvals: vec![caller.clone()],
+					call: maybe_call,
+				};
+				let pallet: &mut Self = runtime.pallet_mut();
+				pallet.multisigs.insert(multisig.clone(), call_hash, approvals);
+
+				let event = Event::NewMultisig { approving: caller, multisig: multisig.clone(), call_hash };
+				Self::deposit_event(runtime, event);
+			},
+		}
+
+		Self::maybe_dispatch(runtime, multisig, call_hash, threshold)
+	}
+
+	/// Dispatch the call `call_hash` by `multisig` if it reached the `threshold` and is known,
+	/// returning the deposit.
+	fn maybe_dispatch(
+		runtime: &mut T,
+		multisig: T::AccountId,
+		call_hash: CallHash,
+		threshold: u16,
+	) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let Some(approvals) = pallet.multisigs.try_get(&multisig, &call_hash) else { return Ok(()) };
+		if approvals.call.is_none() || approvals.approvals.len() < usize::from(threshold) {
+			return Ok(());
+		}
+
+		// The deposit is returned first, so that the approvals are kept if it fails.
+		let depositor = approvals.depositor.clone();
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.unreserve(&depositor, T::DEPOSIT)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		let approvals = pallet.multisigs.remove(&multisig, &call_hash).ok_or("Multisig does not exist")?;
+		let call = approvals.call.ok_or("Call is not known")?;
+		let result = runtime.dispatch(RawOrigin::Signed(multisig.clone()), call);
+
+		Self::deposit_event(runtime, Event::MultisigExecuted { multisig, call_hash, result });
+		Ok(())
+	}
+}
+
+#[macros::call(errors(
+	"Multisig does not exist",
+	"Caller is not the depositor of the multisig",
+	"Signatories must be unique",
+	"Threshold must be between one and the number of signatories",
+	"Caller already approved the call",
+	"Call is not known",
+	"Not enough funds.",
+	"Funds are locked.",
+	"Overflow",
+	"Not enough reserved funds.",
+))]
+impl<T: Config> Pallet<T> {
+	/// Approve `call` on behalf of the multisig made of the `caller`, `other_signatories` and
+	/// `threshold`. The call is dispatched on behalf of the multisig account as soon as
+	/// `threshold` signatories approved it.
+	/// The first approval of a call reserves a deposit from the `caller`.
+	#[allow(clippy::boxed_local)]
+	pub fn as_multi(
+		runtime: &mut T,
+		caller: T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u16,
+		#[runtime_call] call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let call_hash = Self::hash_call(&call);
+		Self::approve_as(runtime, caller, other_signatories, threshold, call_hash, Some(*call))
+	}
+
+	/// Approve the call `call_hash` on behalf of the multisig made of the `caller`,
+	/// `other_signatories` and `threshold`, without providing the call itself. The call is
+	/// dispatched once `threshold` signatories approved it and one of them provided it with
+	/// `as_multi`.
+	/// The first approval of a call reserves a deposit from the `caller`.
+	pub fn approve(
+		runtime: &mut T,
+		caller: T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u16,
+		call_hash: CallHash,
+	) -> DispatchResult {
+		Self::approve_as(runtime, caller, other_signatories, threshold, call_hash, None)
+	}
+
+	/// Cancel the call `call_hash` on behalf of the multisig made of the `caller`,
+	/// `other_signatories` and `threshold`, returning the deposit.
+	/// This function will return an error if the call is not being approved, or if the caller
+	/// is not the signatory which made the first approval.
+	pub fn cancel(
+		runtime: &mut T,
+		caller: T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u16,
+		call_hash: CallHash,
+	) -> DispatchResult {
+		let multisig = Self::multi_account_id(&caller, &other_signatories, threshold)?;
+
+		let pallet: &Self = runtime.pallet();
+		let approvals = pallet.multisigs.try_get(&multisig, &call_hash).ok_or("Multisig does not exist")?;
+		if approvals.depositor != caller {
+			return Err("Caller is not the depositor of the multisig".into());
+		}
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.unreserve(&caller, T::DEPOSIT)?;
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.multisigs.remove(&multisig, &call_hash);
+
+		let event = Event::MultisigCancelled { cancelling: caller, multisig, call_hash };
+		Self::deposit_event(runtime, event);
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, Pallet};
+	use crate::support::ensure::RawOrigin;
+	use crate::support::storage::Encode;
+	use crate::{balances, multisig, Runtime, RuntimeCall};
+
+	fn transfer(to: &str, amount: u128) -> Box<RuntimeCall> {
+		Box::new(RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount }))
+	}
+
+	fn others(who: &[&str]) -> Vec<String> {
+		who.iter().map(|who| who.to_string()).collect()
+	}
+
+	fn setup() -> (Runtime, String) {
+		let mut runtime = Runtime::new();
+		for who in ["alice", "bob", "charlie"] {
+			runtime.balances.set_balance(&who.to_string(), 10);
+		}
+
+		// The multisig account does not depend on which signatory derives it.
+		let multisig =
+			Pallet::<Runtime>::multi_account_id(&"alice".to_string(), &others(&["bob", "charlie"]), 2)
+				.unwrap();
+		assert_eq!(
+			Pallet::<Runtime>::multi_account_id(&"charlie".to_string(), &others(&["alice", "bob"]), 2),
+			Ok(multisig.clone())
+		);
+		runtime.balances.set_balance(&multisig, 100);
+
+		(runtime, multisig)
+	}
+
+	#[test]
+	fn dispatches_once_threshold_is_reached() {
+		let (mut runtime, multisig) = setup();
+		let call = transfer("dave", 50);
+		let call_hash = Pallet::<Runtime>::hash_call(&call);
+		// The hash is over the pallet index, the call index and the encoded arguments.
+		let encoded = [vec![1, 0], "dave".encode(), 50u128.encode()].concat();
+		assert_eq!(call.encode(), encoded);
+		assert_ne!(Pallet::<Runtime>::hash_call(&transfer("dave", 51)), call_hash);
+
+		// `alice` approves the hash, and reserves the deposit.
+		Pallet::approve(&mut runtime, "alice".to_string(), others(&["bob", "charlie"]), 2, call_hash)
+			.unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 5);
+		assert_eq!(
+			Pallet::approve(&mut runtime, "alice".to_string(), others(&["bob", "charlie"]), 2, call_hash),
+			Err("Caller already approved the call".into())
+		);
+
+		// `charlie` provides the call, which reaches the threshold.
+		Pallet::as_multi(&mut runtime, "charlie".to_string(), others(&["alice", "bob"]), 2, call)
+			.unwrap();
+
+		assert_eq!(runtime.balances.balance(&multisig), 50);
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 50);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+		assert!(runtime.multisig.multisig(&multisig, call_hash).is_none());
+		assert!(matches!(
+			runtime.multisig.events().last(),
+			Some(Event::MultisigExecuted { result: Ok(()), .. })
+		));
+	}
+
+	#[test]
+	fn cancel_returns_deposit() {
+		let (mut runtime, multisig) = setup();
+		let call = transfer("dave", 50);
+		let call_hash = Pallet::<Runtime>::hash_call(&call);
+
+		let call = RuntimeCall::multisig(multisig::Call::as_multi {
+			other_signatories: others(&["bob", "charlie"]),
+			threshold: 2,
+			call,
+		});
+		crate::support::Dispatch::dispatch(&mut runtime, RawOrigin::Signed("alice".to_string()), call).unwrap();
+		assert_eq!(runtime.multisig.multisig(&multisig, call_hash).unwrap().approvals, ["alice"]);
+
+		// Only the depositor can cancel.
+		assert_eq!(
+			Pallet::cancel(&mut runtime, "bob".to_string(), others(&["alice", "charlie"]), 2, call_hash),
+			Err("Caller is not the depositor of the multisig".into())
+		);
+		Pallet::cancel(&mut runtime, "alice".to_string(), others(&["bob", "charlie"]), 2, call_hash)
+			.unwrap();
+
+		assert!(runtime.multisig.multisig(&multisig, call_hash).is_none());
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 10);
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+	}
+
+	#[test]
+	fn approvals_are_kept_if_the_deposit_cannot_be_returned() {
+		let (mut runtime, multisig) = setup();
+		let call = transfer("dave", 50);
+		let call_hash = Pallet::<Runtime>::hash_call(&call);
+		Pallet::approve(&mut runtime, "alice".to_string(), others(&["bob", "charlie"]), 2, call_hash)
+			.unwrap();
+
+		// Returning the deposit to `alice` would overflow the balance of `alice`.
+		runtime.balances.set_balance(&"alice".to_string(), u128::MAX);
+		assert_eq!(
+			Pallet::cancel(&mut runtime, "alice".to_string(), others(&["bob", "charlie"]), 2, call_hash),
+			Err("Overflow".into())
+		);
+		assert_eq!(
+			Pallet::as_multi(&mut runtime, "charlie".to_string(), others(&["alice", "bob"]), 2, call),
+			Err("Overflow".into())
+		);
+		assert_eq!(runtime.multisig.multisig(&multisig, call_hash).unwrap().approvals.len(), 2);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 5);
+	}
+
+	#[test]
+	fn invalid_signatories() {
+		let alice = "alice".to_string();
+		assert_eq!(
+			Pallet::<Runtime>::multi_account_id(&alice, &others(&["bob", "alice"]), 2),
+			Err("Signatories must be unique".into())
+		);
+		assert_eq!(
+			Pallet::<Runtime>::multi_account_id(&alice, &others(&["bob"]), 3),
+			Err("Threshold must be between one and the number of signatories".into())
+		);
+
+		// Signatories containing the separator do not collide with other signatories.
+		assert_ne!(
+			<Runtime as multisig::Config>::multi_account_id(&others(&["a,b", "c"]), 1),
+			<Runtime as multisig::Config>::multi_account_id(&others(&["a", "b,c"]), 1)
+		);
+	}
+}
@@ -0,0 +1,436 @@
+use core::fmt::Debug;
+use crate::support::storage::Encode;
+use crate::support::{DispatchResult, GetPallet};
+
+/// The configuration of the Non-Fungible Tokens Module.
+///
+/// Items can be minted as certificates of the claims of the proof of existence pallet, so the
+/// runtime must give access to it.
+pub trait Config:
+	crate::proof_of_existence::Config<Content: Clone>
+	+ Clone
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::proof_of_existence::Pallet<Self>>
+{
+	/// The type used to identify a collection.
+	type CollectionId: Debug + Ord + Copy;
+	/// The type used to identify an item within a collection.
+	type ItemId: Debug + Ord + Copy;
+}
+
+/// The details of a collection.
+#[derive(Debug, Clone)]
+pub struct CollectionDetails<T: Config> {
+	/// The account allowed to mint items in the collection, and to set their attributes.
+	pub owner: T::AccountId,
+	/// The number of items in the collection.
+	pub items: u32,
+}
+
+impl<T: Config> Encode for CollectionDetails<T>
+where
+	T::AccountId: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.owner.encode_to(dest);
+		self.items.encode_to(dest);
+	}
+}
+
+/// The details of an item.
+#[derive(Debug, Clone)]
+pub struct ItemDetails<T: Config> {
+	/// The owner of the item.
+	pub owner: T::AccountId,
+	/// The account (if any) allowed to transfer the item on behalf of its owner.
+	pub approved: Option<T::AccountId>,
+}
+
+impl<T: Config> Encode for ItemDetails<T>
+where
+	T::AccountId: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.owner.encode_to(dest);
+		self.approved.encode_to(dest);
+	}
+}
+
+/// A certificate of a claim of the proof of existence pallet.
+#[derive(Debug, Clone)]
+pub struct Certificate<T: Config> {
+	/// The claim the item certifies.
+	pub claim: T::Content,
+	/// The owner of the claim when the certificate was minted. The certificate is only valid while
+	/// they still own the claim.
+	pub claim_owner: T::AccountId,
+}
+
+impl<T: Config> Encode for Certificate<T>
+where
+	T::Content: Encode,
+	T::AccountId: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.claim.encode_to(dest);
+		self.claim_owner.encode_to(dest);
+	}
+}
+
+/// This is the Non-Fungible Tokens Module.
+/// Like the proof of existence pallet, every item can only have one owner. Items are grouped in
+/// collections, can be transferred, and can be described with attributes.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from a collection to its details.
+	collections: StorageMap<T::CollectionId, CollectionDetails<T>>,
+	/// A map from a collection and an item to the details of that item.
+	items: StorageDoubleMap<T::CollectionId, T::ItemId, ItemDetails<T>>,
+	/// A map from an item and the name of an attribute to its value.
+	attributes: StorageDoubleMap<(T::CollectionId, T::ItemId), String, String>,
+	/// A map from a collection and an item minted as a certificate to the claim it certifies.
+	certificates: StorageDoubleMap<T::CollectionId, T::ItemId, Certificate<T>>,
+	/// A map from a claim and its owner to the item certifying it, so each owner of a claim can mint
+	/// at most one certificate of it.
+	certified: StorageDoubleMap<T::Content, T::AccountId, (T::CollectionId, T::ItemId)>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the details (if any) of the collection `collection`.
+	pub fn collection(&self, collection: &T::CollectionId) -> Option<&CollectionDetails<T>> {
+		self.collections.try_get(collection)
+	}
+
+	/// Get the owner (if any) of an item.
+	pub fn owner(&self, collection: T::CollectionId, item: T::ItemId) -> Option<&T::AccountId> {
+		self.items.try_get(&collection, &item).map(|details| &details.owner)
+	}
+
+	/// Get the value (if any) of the attribute `key` of an item.
+	pub fn attribute(&self, collection: T::CollectionId, item: T::ItemId, key: &str) -> Option<&String> {
+		self.attributes.try_get(&(collection, item), &key.to_string())
+	}
+
+	/// Get the certificate (if any) minted as an item, whether it is still valid or not.
+	pub fn certificate(&self, collection: T::CollectionId, item: T::ItemId) -> Option<&Certificate<T>> {
+		self.certificates.try_get(&collection, &item)
+	}
+
+	/// Get the item (if any) certifying `claim` on behalf of `claim_owner`.
+	pub fn certified_by(&self, claim: &T::Content, claim_owner: &T::AccountId) -> Option<(T::CollectionId, T::ItemId)> {
+		self.certified.get(claim, claim_owner)
+	}
+
+	/// Check that an item is a certificate of a claim which is still owned by the account which
+	/// minted the certificate. Revoking or transferring the claim invalidates its certificate.
+	pub fn is_valid_certificate(runtime: &T, collection: T::CollectionId, item: T::ItemId) -> bool {
+		let pallet: &Self = runtime.pallet();
+		let Some(certificate) = pallet.certificate(collection, item) else { return false };
+		let poe: &crate::proof_of_existence::Pallet<T> = runtime.pallet();
+		poe.get_claim(&certificate.claim) == Some(&certificate.claim_owner)
+	}
+
+	/// Check that `who` is the owner of `collection`.
+	fn ensure_collection_owner(&self, collection: &T::CollectionId, who: &T::AccountId) -> DispatchResult {
+		let details = self.collection(collection).ok_or("Collection does not exist")?;
+		if &details.owner != who {
+			return Err("Caller is not the owner of the collection".into());
+		}
+		Ok(())
+	}
+}
+
+#[macros::call(errors(
+	"Collection already exists",
+	"Item already exists",
+	"Collection does not exist",
+	"Overflow",
+	"Claim does not exist",
+	"Caller is not the owner of the claim",
+	"Claim already has a certificate",
+	"Item does not exist",
+	"Caller is not allowed to transfer the item",
+	"Caller is not the owner of the item",
+	"Attribute does not exist",
+	"Caller is not the owner of the collection",
+))]
+impl<T: Config> Pallet<T> {
+	/// Create a new collection owned by the `caller`.
+	/// This function will return an error if the collection already exists.
+	pub fn create_collection(&mut self, caller: T::AccountId, collection: T::CollectionId) -> DispatchResult {
+		if self.collections.contains_key(&collection) {
+			return Err("Collection already exists".into());
+		}
+
+		self.collections.insert(collection, CollectionDetails { owner: caller, items: 0 });
+		Ok(())
+	}
+
+	/// Mint a new `item` in `collection`, owned by `owner`.
+	/// This function should only succeed if the caller is the owner of the collection, and will
+	/// return an error if the item already exists.
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		owner: T::AccountId,
+	) -> DispatchResult {
+		self.ensure_collection_owner(&collection, &caller)?;
+		if self.items.contains_key(&collection, &item) {
+			return Err("Item already exists".into());
+		}
+		self.collections.try_mutate(collection, |details| -> DispatchResult {
+			let details = details.as_mut().ok_or("Collection does not exist")?;
+			details.items = details.items.checked_add(1).ok_or("Overflow")?;
+			Ok(())
+		})?;
+
+		self.items.insert(collection, item, ItemDetails { owner, approved: None });
+		Ok(())
+	}
+
+	/// Mint a new `item` in `collection`, owned by the `caller`, as a certificate of `claim` in the
+	/// proof of existence pallet.
+	/// This function should only succeed if the caller is the owner of both the collection and the
+	/// claim, and has not certified the claim yet. Burning the certificate allows minting another, and
+	/// a new owner of the claim can mint their own.
+	pub fn mint_certificate(
+		runtime: &mut T,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		claim: T::Content,
+	) -> DispatchResult {
+		let poe: &crate::proof_of_existence::Pallet<T> = runtime.pallet();
+		let claim_owner = poe.get_claim(&claim).ok_or("Claim does not exist")?;
+		if claim_owner != &caller {
+			return Err("Caller is not the owner of the claim".into());
+		}
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		if pallet.certified_by(&claim, &caller).is_some() {
+			return Err("Claim already has a certificate".into());
+		}
+		pallet.mint(caller.clone(), collection, item, caller.clone())?;
+		pallet.certified.insert(claim.clone(), caller.clone(), (collection, item));
+		pallet.certificates.insert(collection, item, Certificate { claim, claim_owner: caller });
+		Ok(())
+	}
+
+	/// Transfer `item` of `collection` to `dest`.
+	/// This function should only succeed if the caller is the owner of the item, or is approved
+	/// to transfer it. Any approval is cleared by the transfer.
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		dest: T::AccountId,
+	) -> DispatchResult {
+		self.items.try_mutate(collection, item, |details| -> DispatchResult {
+			let details = details.as_mut().ok_or("Item does not exist")?;
+			if details.owner != caller && details.approved.as_ref() != Some(&caller) {
+				return Err("Caller is not allowed to transfer the item".into());
+			}
+
+			details.owner = dest;
+			details.approved = None;
+			Ok(())
+		})
+	}
+
+	/// Allow `delegate` to transfer `item` of `collection` on behalf of the `caller`, replacing any
+	/// previous approval.
+	/// This function should only succeed if the caller is the owner of the item.
+	pub fn approve_transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		delegate: T::AccountId,
+	) -> DispatchResult {
+		self.items.try_mutate(collection, item, |details| -> DispatchResult {
+			let details = details.as_mut().ok_or("Item does not exist")?;
+			if details.owner != caller {
+				return Err("Caller is not the owner of the item".into());
+			}
+
+			details.approved = Some(delegate);
+			Ok(())
+		})
+	}
+
+	/// Remove the approval to transfer `item` of `collection`.
+	/// This function should only succeed if the caller is the owner of the item.
+	pub fn cancel_approval(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> DispatchResult {
+		self.items.try_mutate(collection, item, |details| -> DispatchResult {
+			let details = details.as_mut().ok_or("Item does not exist")?;
+			if details.owner != caller {
+				return Err("Caller is not the owner of the item".into());
+			}
+
+			details.approved = None;
+			Ok(())
+		})
+	}
+
+	/// Set the attribute `key` of `item` in `collection` to `value`.
+	/// This function should only succeed if the caller is the owner of the collection.
+	pub fn set_attribute(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: String,
+		value: String,
+	) -> DispatchResult {
+		self.ensure_collection_owner(&collection, &caller)?;
+		if !self.items.contains_key(&collection, &item) {
+			return Err("Item does not exist".into());
+		}
+
+		self.attributes.insert((collection, item), key, value);
+		Ok(())
+	}
+
+	/// Remove the attribute `key` of `item` in `collection`.
+	/// This function should only succeed if the caller is the owner of the collection.
+	pub fn clear_attribute(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: String,
+	) -> DispatchResult {
+		self.ensure_collection_owner(&collection, &caller)?;
+		self.attributes.remove(&(collection, item), &key).ok_or("Attribute does not exist")?;
+		Ok(())
+	}
+
+	/// Destroy `item` of `collection`, along with its attributes.
+	/// This function should only succeed if the caller is the owner of the item.
+	pub fn burn(&mut self, caller: T::AccountId, collection: T::CollectionId, item: T::ItemId) -> DispatchResult {
+		let owner = self.owner(collection, item).ok_or("Item does not exist")?;
+		if owner != &caller {
+			return Err("Caller is not the owner of the item".into());
+		}
+
+		self.items.remove(&collection, &item);
+		if let Some(Certificate { claim, claim_owner }) = self.certificates.remove(&collection, &item) {
+			self.certified.remove(&claim, &claim_owner);
+		}
+		let keys: Vec<String> = self.attributes.iter_prefix(&(collection, item)).map(|(key, _)| key.clone()).collect();
+		for key in keys {
+			self.attributes.remove(&(collection, item), &key);
+		}
+		self.collections.mutate(collection, |details| {
+			if let Some(details) = details {
+				details.items = details.items.saturating_sub(1);
+			}
+		});
+
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::Pallet;
+	use crate::Runtime;
+
+	fn setup() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.nfts.create_collection("alice".to_string(), 0).unwrap();
+		runtime.nfts.mint("alice".to_string(), 0, 1, "bob".to_string()).unwrap();
+		runtime
+	}
+
+	#[test]
+	fn mint_transfer_and_burn() {
+		let mut runtime = setup();
+		let (alice, bob, charlie) = ("alice".to_string(), "bob".to_string(), "charlie".to_string());
+
+		assert_eq!(runtime.nfts.mint(bob.clone(), 0, 2, bob.clone()), Err("Caller is not the owner of the collection".into()));
+		assert_eq!(runtime.nfts.mint(alice.clone(), 0, 1, bob.clone()), Err("Item already exists".into()));
+
+		// `charlie` can only transfer the item once `bob` approved it.
+		assert_eq!(
+			runtime.nfts.transfer(charlie.clone(), 0, 1, charlie.clone()),
+			Err("Caller is not allowed to transfer the item".into())
+		);
+		runtime.nfts.approve_transfer(bob.clone(), 0, 1, charlie.clone()).unwrap();
+		runtime.nfts.transfer(charlie.clone(), 0, 1, alice.clone()).unwrap();
+		assert_eq!(runtime.nfts.owner(0, 1), Some(&alice));
+		assert_eq!(
+			runtime.nfts.transfer(charlie.clone(), 0, 1, charlie.clone()),
+			Err("Caller is not allowed to transfer the item".into())
+		);
+
+		runtime.nfts.set_attribute(alice.clone(), 0, 1, "color".to_string(), "red".to_string()).unwrap();
+		assert_eq!(runtime.nfts.attribute(0, 1, "color"), Some(&"red".to_string()));
+
+		runtime.nfts.burn(alice.clone(), 0, 1).unwrap();
+		assert_eq!(runtime.nfts.owner(0, 1), None);
+		assert_eq!(runtime.nfts.attribute(0, 1, "color"), None);
+		assert_eq!(runtime.nfts.collection(&0).unwrap().items, 0);
+	}
+
+	#[test]
+	fn mint_certificate_of_claim() {
+		let mut runtime = setup();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		runtime.proof_of_existence.create_claim(bob.clone(), "document".to_string()).unwrap();
+
+		assert_eq!(
+			Pallet::mint_certificate(&mut runtime, alice.clone(), 0, 2, "document".to_string()),
+			Err("Caller is not the owner of the claim".into())
+		);
+
+		runtime.nfts.create_collection(bob.clone(), 1).unwrap();
+		Pallet::mint_certificate(&mut runtime, bob.clone(), 1, 0, "document".to_string()).unwrap();
+		assert_eq!(runtime.nfts.owner(1, 0), Some(&bob));
+		assert_eq!(runtime.nfts.certificate(1, 0).unwrap().claim, "document".to_string());
+		assert!(Pallet::is_valid_certificate(&runtime, 1, 0));
+
+		// A claim can only have one certificate at a time.
+		assert_eq!(
+			Pallet::mint_certificate(&mut runtime, bob.clone(), 1, 1, "document".to_string()),
+			Err("Claim already has a certificate".into())
+		);
+		runtime.nfts.burn(bob.clone(), 1, 0).unwrap();
+		assert_eq!(runtime.nfts.certified_by(&"document".to_string(), &bob), None);
+		Pallet::mint_certificate(&mut runtime, bob.clone(), 1, 1, "document".to_string()).unwrap();
+		assert_eq!(runtime.nfts.certified_by(&"document".to_string(), &bob), Some((1, 1)));
+	}
+
+	#[test]
+	fn certificate_follows_the_owner_of_the_claim() {
+		let mut runtime = setup();
+		let (bob, charlie) = ("bob".to_string(), "charlie".to_string());
+		runtime.proof_of_existence.create_claim(bob.clone(), "document".to_string()).unwrap();
+		runtime.nfts.create_collection(bob.clone(), 1).unwrap();
+		runtime.nfts.create_collection(charlie.clone(), 2).unwrap();
+		Pallet::mint_certificate(&mut runtime, bob.clone(), 1, 0, "document".to_string()).unwrap();
+
+		// Once `bob` no longer owns the claim, their certificate is no longer valid, and the new
+		// owner can mint their own.
+		runtime.proof_of_existence.transfer_claim(bob.clone(), "document".to_string(), charlie.clone()).unwrap();
+		assert!(!Pallet::is_valid_certificate(&runtime, 1, 0));
+		Pallet::mint_certificate(&mut runtime, charlie.clone(), 2, 0, "document".to_string()).unwrap();
+		assert!(Pallet::is_valid_certificate(&runtime, 2, 0));
+		assert_eq!(runtime.nfts.certified_by(&"document".to_string(), &charlie), Some((2, 0)));
+
+		// Revoking the claim invalidates every certificate of it.
+		runtime.proof_of_existence.revoke_claim(charlie.clone(), "document".to_string()).unwrap();
+		assert!(!Pallet::is_valid_certificate(&runtime, 2, 0));
+	}
+}
@@ -1,5 +1,5 @@
 use core::fmt::Debug;
-use std::collections::BTreeMap;
+use crate::support::ensure::ensure;
 use crate::support::DispatchResult;
 
 pub trait Config: crate::system::Config {
@@ -11,42 +11,35 @@ pub trait Config: crate::system::Config {
 
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
-#[derive(Debug)]
+#[macros::storage]
+#[derive(Debug, Clone)]
 pub struct Pallet<T: Config> {
 	/// A simple storage map from content to the owner of that content.
 	/// Accounts can make multiple different claims, but each claim can only have one owner.
-    claims: BTreeMap<T::Content, T::AccountId>,
+    claims: StorageMap<T::Content, T::AccountId>,
 }
 
 impl<T: Config> Pallet<T> {
-	/// Create a new instance of the Proof of Existence Module.
-	pub fn new() -> Self {
-		Self {
-            claims: BTreeMap::new(),
-        }
-	}
-
 	/// Get the owner (if any) of a claim.
 	pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
 		// `get` the `claim` from the `claims` map.
-		self.claims.get(claim)
+		self.claims.try_get(claim)
 	}
 }
 
-#[macros::call]
+#[macros::call(errors(
+	"Claim does not exist",
+	"Claim already exists",
+	"Caller is not the owner of the claim",
+))]
 impl<T: Config> Pallet<T> {
 	/// Create a new claim on behalf of the `caller`.
 	/// This function will return an error if someone already has claimed that content.
 	pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		// Check that a `claim` does not already exist. If so, return an error. 
-		// TODO: `insert` the claim on behalf of `caller`. 
-		match self.claims.get(&claim) {
-			Some(_) => Err("Claim already exists"),
-			None => {
-				self.claims.insert(claim, caller);
-				Ok(())
-			}
-		}
+		// Check that a `claim` does not already exist. If so, return an error.
+		ensure!(!self.claims.contains_key(&claim), "Claim already exists");
+		self.claims.insert(claim, caller);
+		Ok(())
 	}
 
 	/// Revoke an existing claim on some content.
@@ -54,17 +47,28 @@ impl<T: Config> Pallet<T> {
 	/// It will return an error if the claim does not exist, or if the caller is not the owner.
 	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
 		let claim_owner = self.get_claim(&claim).ok_or("Claim does not exist")?;
-
-		if claim_owner != &caller {
-			return Err("Caller is not the owner of the claim");
-		}
+		ensure!(claim_owner == &caller, "Caller is not the owner of the claim");
 
 		self.claims.remove(&claim);
 
 		Ok(())
 	}
+
+	/// Transfer an existing claim on some content to `dest`.
+	/// This function should only succeed if the caller is the owner of an existing claim.
+	/// It will return an error if the claim does not exist, or if the caller is not the owner.
+	pub fn transfer_claim(&mut self, caller: T::AccountId, claim: T::Content, dest: T::AccountId) -> DispatchResult {
+		let claim_owner = self.get_claim(&claim).ok_or("Claim does not exist")?;
+		ensure!(claim_owner == &caller, "Caller is not the owner of the claim");
+
+		self.claims.insert(claim, dest);
+
+		Ok(())
+	}
 }
 
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
 #[cfg(test)]
 mod test {
 	struct TestConfig;
@@ -97,6 +101,6 @@ mod test {
 		assert_eq!(poe.get_claim(&"Hello, World"), None);
 
 		// Check that revoking a claim that does not exist fails.
-		assert_eq!(poe.revoke_claim("alice", "Hello, World"), Err("Claim does not exist"));
+		assert_eq!(poe.revoke_claim("alice", "Hello, World"), Err("Claim does not exist".into()));
 	}
 }
\ No newline at end of file
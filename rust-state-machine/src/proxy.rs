@@ -0,0 +1,288 @@
+use core::fmt::Debug;
+use crate::support::ensure::{OriginFor, RawOrigin};
+use crate::support::storage::Encode;
+use crate::support::{Dispatch, DispatchResult, GetPallet};
+
+/// A type which decides which calls a proxy is allowed to make on behalf of an account.
+pub trait InstanceFilter<Call> {
+	/// Whether `call` is allowed.
+	fn filter(&self, call: &Call) -> bool;
+}
+
+/// The configuration of the Proxy Module.
+///
+/// Proxied calls are dispatched through the runtime, and deposits are held in the balances pallet,
+/// so the runtime must be able to dispatch the `RuntimeCall`s and give access to the balances.
+pub trait Config:
+	crate::balances::Config
+	+ Clone
+	+ Dispatch<Caller = OriginFor<Self>, Call = <Self as Config>::RuntimeCall>
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+{
+	/// The aggregated call type of the runtime, which is what proxies dispatch.
+	type RuntimeCall: Debug + Clone;
+	/// The kinds of proxy an account can register, each allowing a different set of calls.
+	/// This is defined by the runtime, since it filters the `RuntimeCall`s.
+	type ProxyType: InstanceFilter<<Self as Config>::RuntimeCall> + Debug + Clone + PartialEq;
+
+	/// The deposit reserved from an account for each of its proxies, until the proxy is removed.
+	const DEPOSIT: Self::Balance;
+	/// The maximum number of proxies an account can register.
+	const MAX_PROXIES: u32;
+}
+
+/// A delegate registered by an account, and the kind of calls it can make on its behalf.
+#[derive(Debug, Clone)]
+pub struct ProxyDefinition<T: Config> {
+	/// The account allowed to make calls on behalf of the real account.
+	pub delegate: T::AccountId,
+	/// The kind of calls the delegate is allowed to make.
+	pub proxy_type: T::ProxyType,
+}
+
+impl<T: Config> Encode for ProxyDefinition<T>
+where
+	T::AccountId: Encode,
+	T::ProxyType: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.delegate.encode_to(dest);
+		self.proxy_type.encode_to(dest);
+	}
+}
+
+/// The events emitted by the Proxy Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// `delegate` was registered as a proxy of `real`.
+	Added { real: T::AccountId, delegate: T::AccountId, proxy_type: T::ProxyType },
+	/// `delegate` is no longer a proxy of `real`.
+	Removed { real: T::AccountId, delegate: T::AccountId, proxy_type: T::ProxyType },
+	/// A call was dispatched on behalf of `real`, with the given `result`.
+	Executed { real: T::AccountId, result: DispatchResult },
+}
+
+/// This is the Proxy Module.
+/// It allows accounts to register delegates, which can then dispatch a restricted set of calls on
+/// their behalf. For example, a hot key can act on behalf of a cold account.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from an account to the delegates which can act on its behalf.
+	#[default(Vec::new())]
+	proxies: StorageMap<T::AccountId, Vec<ProxyDefinition<T>>>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the delegates registered by `real`.
+	pub fn proxies(&self, real: &T::AccountId) -> &[ProxyDefinition<T>] {
+		self.proxies.try_get(real).map(|proxies| proxies.as_slice()).unwrap_or(&[])
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+}
+
+#[macros::call(errors(
+	"Cannot proxy to self",
+	"Proxy already exists",
+	"Too many proxies",
+	"Proxy does not exist",
+	"Caller is not a proxy of the account for this call",
+	"Not enough funds.",
+	"Funds are locked.",
+	"Overflow",
+	"Not enough reserved funds.",
+))]
+impl<T: Config> Pallet<T> {
+	/// Register `delegate` as a proxy of the `caller`, allowed to make the calls of `proxy_type`,
+	/// reserving `T::DEPOSIT` from the `caller` until the proxy is removed.
+	/// This function will return an error if the same proxy is already registered, if the `caller`
+	/// already has `T::MAX_PROXIES` proxies, or if the deposit cannot be reserved.
+	pub fn add_proxy(
+		runtime: &mut T,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: T::ProxyType,
+	) -> DispatchResult {
+		if delegate == caller {
+			return Err("Cannot proxy to self".into());
+		}
+		let pallet: &Self = runtime.pallet();
+		let proxies = pallet.proxies(&caller);
+		if proxies.iter().any(|proxy| proxy.delegate == delegate && proxy.proxy_type == proxy_type) {
+			return Err("Proxy already exists".into());
+		}
+		if proxies.len() >= T::MAX_PROXIES as usize {
+			return Err("Too many proxies".into());
+		}
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.reserve(&caller, T::DEPOSIT)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		let proxy = ProxyDefinition { delegate: delegate.clone(), proxy_type: proxy_type.clone() };
+		pallet.proxies.mutate(caller.clone(), |proxies| proxies.push(proxy));
+		pallet.events.push(Event::Added { real: caller, delegate, proxy_type });
+		Ok(())
+	}
+
+	/// Unregister `delegate` as a proxy of the `caller` for `proxy_type`, returning its deposit.
+	/// This function will return an error if the proxy is not registered.
+	pub fn remove_proxy(
+		runtime: &mut T,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: T::ProxyType,
+	) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let index = pallet
+			.proxies(&caller)
+			.iter()
+			.position(|proxy| proxy.delegate == delegate && proxy.proxy_type == proxy_type)
+			.ok_or("Proxy does not exist")?;
+
+		// The deposit is returned first, so that the proxy is kept if it fails.
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.unreserve(&caller, T::DEPOSIT)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.proxies.mutate(caller.clone(), |proxies| {
+			proxies.remove(index);
+		});
+		if pallet.proxies(&caller).is_empty() {
+			pallet.proxies.remove(&caller);
+		}
+		pallet.events.push(Event::Removed { real: caller, delegate, proxy_type });
+		Ok(())
+	}
+
+	/// Dispatch `call` on behalf of `real`, using a proxy registered for the `caller`.
+	/// This function will return an error if the `caller` is not a proxy of `real` which is
+	/// allowed to make the call. The result of the call itself is reported with an event.
+	#[allow(clippy::boxed_local)]
+	pub fn proxy(
+		runtime: &mut T,
+		caller: T::AccountId,
+		real: T::AccountId,
+		#[runtime_call] call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let pallet: &mut Self = runtime.pallet_mut();
+		let allowed = pallet
+			.proxies(&real)
+			.iter()
+			.any(|proxy| proxy.delegate == caller && proxy.proxy_type.filter(&call));
+		if !allowed {
+			return Err("Caller is not a proxy of the account for this call".into());
+		}
+
+		let result = runtime.dispatch(RawOrigin::Signed(real.clone()), *call);
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.events.push(Event::Executed { real, result });
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, Pallet};
+	use crate::{balances, proof_of_existence, ProxyType, Runtime, RuntimeCall};
+
+	fn transfer(to: &str, amount: u128) -> Box<RuntimeCall> {
+		Box::new(RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount }))
+	}
+
+	fn create_claim(claim: &str) -> Box<RuntimeCall> {
+		let call = proof_of_existence::Call::create_claim { claim: claim.to_string() };
+		Box::new(RuntimeCall::proof_of_existence(call))
+	}
+
+	#[test]
+	fn proxy_is_filtered_by_type() {
+		let mut runtime = Runtime::new();
+		let (cold, hot) = ("cold".to_string(), "hot".to_string());
+		runtime.balances.set_balance(&cold, 100);
+
+		Pallet::add_proxy(&mut runtime, cold.clone(), hot.clone(), ProxyType::ProofOfExistence).unwrap();
+		assert_eq!(
+			Pallet::add_proxy(&mut runtime, cold.clone(), hot.clone(), ProxyType::ProofOfExistence),
+			Err("Proxy already exists".into())
+		);
+
+		// The claim is made on behalf of the cold account.
+		Pallet::proxy(&mut runtime, hot.clone(), cold.clone(), create_claim("doc")).unwrap();
+		assert_eq!(runtime.proof_of_existence.get_claim(&"doc".to_string()), Some(&cold));
+		assert!(matches!(
+			runtime.proxy.events().last(),
+			Some(Event::Executed { result: Ok(()), .. })
+		));
+
+		// But transfers are not allowed for this proxy type.
+		assert_eq!(
+			Pallet::proxy(&mut runtime, hot.clone(), cold.clone(), transfer("hot", 50)),
+			Err("Caller is not a proxy of the account for this call".into())
+		);
+
+		Pallet::add_proxy(&mut runtime, cold.clone(), hot.clone(), ProxyType::Transfer).unwrap();
+		Pallet::proxy(&mut runtime, hot.clone(), cold.clone(), transfer("hot", 50)).unwrap();
+		assert_eq!(runtime.balances.balance(&hot), 50);
+		// Each proxy holds its own deposit.
+		assert_eq!(runtime.balances.reserved_balance(&cold), 10);
+	}
+
+	#[test]
+	fn removed_proxy_cannot_act() {
+		let mut runtime = Runtime::new();
+		let (cold, hot) = ("cold".to_string(), "hot".to_string());
+		runtime.balances.set_balance(&cold, 100);
+
+		assert_eq!(
+			Pallet::add_proxy(&mut runtime, cold.clone(), cold.clone(), ProxyType::Any),
+			Err("Cannot proxy to self".into())
+		);
+		Pallet::add_proxy(&mut runtime, cold.clone(), hot.clone(), ProxyType::Any).unwrap();
+		Pallet::remove_proxy(&mut runtime, cold.clone(), hot.clone(), ProxyType::Any).unwrap();
+		assert_eq!(
+			Pallet::remove_proxy(&mut runtime, cold.clone(), hot.clone(), ProxyType::Any),
+			Err("Proxy does not exist".into())
+		);
+		assert!(runtime.proxy.proxies(&cold).is_empty());
+		assert_eq!(runtime.balances.reserved_balance(&cold), 0);
+
+		assert_eq!(
+			Pallet::proxy(&mut runtime, hot, cold, create_claim("doc")),
+			Err("Caller is not a proxy of the account for this call".into())
+		);
+	}
+
+	#[test]
+	fn proxies_are_limited_by_deposit_and_number() {
+		let mut runtime = Runtime::new();
+		let cold = "cold".to_string();
+		let hot = |i: u8| format!("hot{}", i);
+
+		assert_eq!(Pallet::add_proxy(&mut runtime, cold.clone(), hot(0), ProxyType::Any), Err("Not enough funds.".into()));
+		assert!(runtime.proxy.proxies(&cold).is_empty());
+
+		runtime.balances.set_balance(&cold, 100);
+		for i in 0..3 {
+			Pallet::add_proxy(&mut runtime, cold.clone(), hot(i), ProxyType::Any).unwrap();
+		}
+		assert_eq!(Pallet::add_proxy(&mut runtime, cold.clone(), hot(3), ProxyType::Any), Err("Too many proxies".into()));
+		assert_eq!(runtime.balances.reserved_balance(&cold), 15);
+
+		// Removing a proxy makes room for another one.
+		Pallet::remove_proxy(&mut runtime, cold.clone(), hot(0), ProxyType::Any).unwrap();
+		Pallet::add_proxy(&mut runtime, cold.clone(), hot(3), ProxyType::Any).unwrap();
+		assert_eq!(runtime.balances.balance(&cold), 85);
+	}
+}
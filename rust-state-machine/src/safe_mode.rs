@@ -0,0 +1,245 @@
+use num::traits::{CheckedAdd, Zero};
+use crate::support::ensure::{ensure, ensure_root, OriginFor};
+use crate::support::{CallFilter, DispatchResult, GetCallName, GetPallet, Hooks};
+
+/// The configuration of the Safe Mode Module.
+///
+/// The pallet filters the calls of the runtime, so the runtime must give it to
+/// `#[macros::runtime(call_filter = ..)]`, and give access to the block number of the system pallet.
+pub trait Config:
+	crate::system::Config<BlockNumber: CheckedAdd>
+	+ Sized
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::system::Pallet<Self>>
+{
+	/// The calls of the runtime, which are paused by the names of their pallet and function.
+	type RuntimeCall: GetCallName;
+}
+
+/// The events emitted by the Safe Mode Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// The calls of `pallet` were paused, or only its call `call` if given.
+	Paused { pallet: String, call: Option<String> },
+	/// The calls of `pallet` were unpaused, or only its call `call` if given.
+	Unpaused { pallet: String, call: Option<String> },
+	/// Safe mode was entered, until the block `until` included.
+	Entered { until: T::BlockNumber },
+	/// Safe mode was exited, by `Root` or because it expired.
+	Exited,
+}
+
+/// This is the Safe Mode Module.
+/// It lets `Root` halt user activity without stopping the chain, when something goes wrong. Either
+/// some pallets or calls are paused until they are unpaused, or the runtime enters a full safe mode
+/// for a number of blocks, in which every call is rejected. Calls made by `Root` are never
+/// filtered, so governance can still fix the runtime.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from the paused pallets and calls to the block at which they were paused. A pallet is
+	/// paused with no call, and a single call with the name of its function.
+	paused: StorageMap<(String, Option<String>), T::BlockNumber>,
+	/// The last block of the safe mode, if it is entered.
+	entered_until: StorageValue<T::BlockNumber>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Whether the calls of `pallet` are paused, or only its call `call` if given.
+	pub fn is_paused(&self, pallet: &str, call: Option<&str>) -> bool {
+		self.paused.contains_key(&(pallet.to_string(), call.map(str::to_string)))
+	}
+
+	/// Get the last block of the safe mode, if it is entered.
+	pub fn entered_until(&self) -> Option<T::BlockNumber> {
+		self.entered_until.get()
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+}
+
+#[macros::call(errors(
+	"Call is not paused",
+	"Overflow",
+	"Safe mode is not entered",
+	"Call is already paused",
+	"Safe mode must last at least one block",
+	"Unknown pallet",
+	"Unknown call",
+))]
+impl<T: Config> Pallet<T> {
+	/// Pause the calls of `pallet`, or only its call `call` if given, until they are unpaused.
+	/// Pallets and calls are named like the variants of `RuntimeCall`, like `balances` and
+	/// `transfer`.
+	/// This function should only succeed if the caller is the `Root` origin, and if the runtime has
+	/// calls with these names.
+	pub fn pause(runtime: &mut T, origin: OriginFor<T>, pallet: String, call: Option<String>) -> DispatchResult {
+		ensure_root(origin)?;
+		let calls = <<T as Config>::RuntimeCall as GetCallName>::call_names();
+		ensure!(calls.iter().any(|(name, _)| *name == pallet), "Unknown pallet");
+		if let Some(call) = &call {
+			ensure!(calls.iter().any(|(name, call_name)| *name == pallet && call_name == call), "Unknown call");
+		}
+		let block_number = GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number();
+
+		let safe_mode: &mut Self = runtime.pallet_mut();
+		let key = (pallet, call);
+		ensure!(!safe_mode.paused.contains_key(&key), "Call is already paused");
+		safe_mode.paused.insert(key.clone(), block_number);
+		safe_mode.events.push(Event::Paused { pallet: key.0, call: key.1 });
+		Ok(())
+	}
+
+	/// Unpause the calls of `pallet`, or only its call `call` if given. Pausing a pallet and one of
+	/// its calls are separate, so unpausing the pallet does not unpause the call.
+	/// This function should only succeed if the caller is the `Root` origin.
+	pub fn unpause(&mut self, origin: OriginFor<T>, pallet: String, call: Option<String>) -> DispatchResult {
+		ensure_root(origin)?;
+		let key = (pallet, call);
+		self.paused.remove(&key).ok_or("Call is not paused")?;
+		self.events.push(Event::Unpaused { pallet: key.0, call: key.1 });
+		Ok(())
+	}
+
+	/// Enter the safe mode for the next `blocks` blocks, in which every call is rejected. If the
+	/// safe mode is already entered, it now ends after these blocks instead.
+	/// This function should only succeed if the caller is the `Root` origin.
+	pub fn enter(runtime: &mut T, origin: OriginFor<T>, blocks: T::BlockNumber) -> DispatchResult {
+		ensure_root(origin)?;
+		ensure!(!blocks.is_zero(), "Safe mode must last at least one block");
+		let block_number = GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number();
+		let until = block_number.checked_add(&blocks).ok_or("Overflow")?;
+
+		let safe_mode: &mut Self = runtime.pallet_mut();
+		safe_mode.entered_until.put(until);
+		safe_mode.events.push(Event::Entered { until });
+		Ok(())
+	}
+
+	/// Exit the safe mode before it expires.
+	/// This function should only succeed if the caller is the `Root` origin.
+	pub fn exit(&mut self, origin: OriginFor<T>) -> DispatchResult {
+		ensure_root(origin)?;
+		self.entered_until.kill().ok_or("Safe mode is not entered")?;
+		self.events.push(Event::Exited);
+		Ok(())
+	}
+}
+
+/// The pallet is the call filter of the runtime, rejecting the paused calls, and every call while
+/// the safe mode is entered.
+impl<T: Config> CallFilter<T, <T as Config>::RuntimeCall> for Pallet<T> {
+	fn allows(runtime: &T, call: &<T as Config>::RuntimeCall) -> bool {
+		Self::check(runtime, call).is_ok()
+	}
+
+	fn check(runtime: &T, call: &<T as Config>::RuntimeCall) -> DispatchResult {
+		let safe_mode: &Self = runtime.pallet();
+		ensure!(!safe_mode.entered_until.exists(), "Safe mode is entered");
+		ensure!(
+			!safe_mode.is_paused(call.pallet_name(), None)
+				&& !safe_mode.is_paused(call.pallet_name(), Some(call.call_name())),
+			"Call is paused"
+		);
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+	/// Exit the safe mode once its last block has passed.
+	fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) {
+		let safe_mode: &mut Self = runtime.pallet_mut();
+		if safe_mode.entered_until.try_get().is_some_and(|until| *until < block_number) {
+			safe_mode.entered_until.kill();
+			safe_mode.events.push(Event::Exited);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Event;
+	use crate::support::ensure::RawOrigin;
+	use crate::support::{self, Dispatch};
+	use crate::{balances, proof_of_existence, safe_mode, types, Runtime, RuntimeCall};
+
+	fn run_to_block(runtime: &mut Runtime, block_number: u32) {
+		while runtime.system.block_number() < block_number {
+			let header = support::Header { block_number: runtime.system.block_number() + 1, author: "alice".to_string() };
+			let block: types::Block = support::Block { header, extrinsics: vec![] };
+			runtime.execute_block(block).unwrap();
+		}
+	}
+
+	fn transfer(to: &str, amount: u128) -> RuntimeCall {
+		RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount })
+	}
+
+	#[test]
+	fn paused_calls_are_rejected() {
+		let mut runtime = Runtime::new();
+		let alice = RawOrigin::Signed("alice".to_string());
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+
+		let pause = safe_mode::Call::pause { pallet: "balances".to_string(), call: Some("transfer".to_string()) };
+		assert_eq!(runtime.dispatch(alice.clone(), RuntimeCall::safe_mode(pause.clone())), Err(support::DispatchError::BadOrigin));
+		runtime.dispatch(RawOrigin::Root, RuntimeCall::safe_mode(pause)).unwrap();
+		assert_eq!(runtime.dispatch(alice.clone(), transfer("bob", 10)), Err("Call is paused".into()));
+
+		// The other calls of the pallet, and of other pallets, are not paused.
+		let claim = proof_of_existence::Call::create_claim { claim: "hello".to_string() };
+		runtime.dispatch(alice.clone(), RuntimeCall::proof_of_existence(claim)).unwrap();
+
+		// Pausing the whole pallet is separate from pausing one of its calls.
+		let pause = safe_mode::Call::pause { pallet: "balances".to_string(), call: None };
+		runtime.dispatch(RawOrigin::Root, RuntimeCall::safe_mode(pause)).unwrap();
+		runtime.safe_mode.unpause(RawOrigin::Root, "balances".to_string(), Some("transfer".to_string())).unwrap();
+		assert_eq!(runtime.dispatch(alice.clone(), transfer("bob", 10)), Err("Call is paused".into()));
+		runtime.safe_mode.unpause(RawOrigin::Root, "balances".to_string(), None).unwrap();
+		runtime.dispatch(alice, transfer("bob", 10)).unwrap();
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 10);
+	}
+
+	#[test]
+	fn only_calls_of_the_runtime_can_be_paused() {
+		let mut runtime = Runtime::new();
+		let pause = |pallet: &str, call: Option<&str>| {
+			RuntimeCall::safe_mode(safe_mode::Call::pause { pallet: pallet.to_string(), call: call.map(str::to_string) })
+		};
+
+		assert_eq!(runtime.dispatch(RawOrigin::Root, pause("balance", None)), Err("Unknown pallet".into()));
+		assert_eq!(runtime.dispatch(RawOrigin::Root, pause("balances", Some("transfr"))), Err("Unknown call".into()));
+		// The calls are named after the pallet they belong to, so other pallets do not match.
+		assert_eq!(runtime.dispatch(RawOrigin::Root, pause("assets", Some("create_claim"))), Err("Unknown call".into()));
+		runtime.dispatch(RawOrigin::Root, pause("rewards", Some("transfer"))).unwrap();
+		assert!(runtime.safe_mode.is_paused("rewards", Some("transfer")));
+	}
+
+	#[test]
+	fn safe_mode_expires() {
+		let mut runtime = Runtime::new();
+		let alice = RawOrigin::Signed("alice".to_string());
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		run_to_block(&mut runtime, 1);
+
+		runtime.dispatch(RawOrigin::Root, RuntimeCall::safe_mode(safe_mode::Call::enter { blocks: 2 })).unwrap();
+		assert_eq!(runtime.safe_mode.entered_until(), Some(3));
+		assert_eq!(runtime.dispatch(alice.clone(), transfer("bob", 10)), Err("Safe mode is entered".into()));
+		// Calls made by `Root` are still dispatched.
+		let set_balance = balances::Call::force_set_balance { who: "bob".to_string(), amount: 5 };
+		runtime.dispatch(RawOrigin::Root, RuntimeCall::balances(set_balance)).unwrap();
+
+		run_to_block(&mut runtime, 3);
+		assert_eq!(runtime.dispatch(alice.clone(), transfer("bob", 10)), Err("Safe mode is entered".into()));
+		run_to_block(&mut runtime, 4);
+		assert!(matches!(runtime.safe_mode.events().last(), Some(Event::Exited)));
+		runtime.dispatch(alice, transfer("bob", 10)).unwrap();
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 15);
+	}
+}
@@ -0,0 +1,339 @@
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, Zero};
+use crate::support::ensure::OriginFor;
+use crate::support::storage::Encode;
+use crate::support::{Dispatch, DispatchError, DispatchResult, GetPallet, Hooks};
+
+/// The identifier given to every scheduled task.
+pub type TaskId = u32;
+
+pub trait Config: crate::system::Config + Clone {
+	/// The aggregated call type of the runtime, which is what this pallet schedules.
+	/// Periodic tasks are dispatched more than once, so the call must be `Clone`.
+	type RuntimeCall: Debug + Clone;
+
+	/// The maximum number of tasks scheduled for the same block, which bounds the work done at the
+	/// beginning of every block.
+	const MAX_SCHEDULED_PER_BLOCK: u32;
+}
+
+/// A call which has been scheduled for dispatch at a future block.
+#[derive(Debug, Clone)]
+pub struct Scheduled<T: Config> {
+	/// The identifier of the task, which can be used to cancel it.
+	pub id: TaskId,
+	/// The origin the call is dispatched with.
+	pub origin: OriginFor<T>,
+	/// If set, the task is scheduled again this many blocks after each dispatch.
+	pub period: Option<T::BlockNumber>,
+	/// The call to dispatch.
+	pub call: T::RuntimeCall,
+}
+
+impl<T: Config> Encode for Scheduled<T>
+where
+	T::AccountId: Encode,
+	T::BlockNumber: Encode,
+	T::RuntimeCall: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.id.encode_to(dest);
+		self.origin.encode_to(dest);
+		self.period.encode_to(dest);
+		self.call.encode_to(dest);
+	}
+}
+
+/// The events emitted by the Scheduler Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// The task `id` was scheduled for dispatch at block `when`.
+	Scheduled { when: T::BlockNumber, id: TaskId },
+	/// The task `id`, which was scheduled for block `when`, was canceled.
+	Canceled { when: T::BlockNumber, id: TaskId },
+	/// The task `id` was dispatched, with the given `result`.
+	Dispatched { id: TaskId, result: DispatchResult },
+	/// The periodic task `id` could not be scheduled again, and was dropped.
+	PeriodicFailed { id: TaskId, error: DispatchError },
+}
+
+/// This is the Scheduler Module.
+/// It allows accounts to schedule calls to be dispatched at a future block, either once or
+/// periodically. Due calls are dispatched at the beginning of the block, before any extrinsic.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The scheduled tasks, grouped by the block they should be dispatched at.
+	#[default(Vec::new())]
+	agenda: StorageMap<T::BlockNumber, Vec<Scheduled<T>>>,
+	/// A map from a task to the block it is scheduled for, so tasks can be found by their id.
+	lookup: StorageMap<TaskId, T::BlockNumber>,
+	/// The id given to the next scheduled task.
+	#[default(0)]
+	next_id: StorageValue<TaskId>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the tasks scheduled for block `when`.
+	pub fn agenda(&self, when: &T::BlockNumber) -> &[Scheduled<T>] {
+		self.agenda.try_get(when).map(|tasks| tasks.as_slice()).unwrap_or(&[])
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+
+	/// Schedule `call` to be dispatched with `origin` at block `when`, and then every `period`
+	/// blocks if a period is given. Returns the id of the new task.
+	///
+	/// Unlike the `schedule` call, this lets the origin of the call be configured, so other pallets
+	/// can use it to dispatch calls with any origin, like the `Root` origin.
+	pub fn schedule_as(
+		&mut self,
+		origin: OriginFor<T>,
+		when: T::BlockNumber,
+		period: Option<T::BlockNumber>,
+		call: T::RuntimeCall,
+	) -> Result<TaskId, DispatchError> {
+		if period.is_some_and(|period| period.is_zero()) {
+			return Err("Period must not be zero".into());
+		}
+
+		let id = self.next_id.get();
+		let next_id = id.checked_add(1).ok_or("Overflow")?;
+
+		self.insert(when, Scheduled { id, origin, period, call })?;
+		self.next_id.put(next_id);
+		self.events.push(Event::Scheduled { when, id });
+
+		Ok(id)
+	}
+
+	/// Add `task` to the agenda of block `when`.
+	/// This function will return an error if the agenda is full.
+	fn insert(&mut self, when: T::BlockNumber, task: Scheduled<T>) -> DispatchResult {
+		let id = task.id;
+		self.agenda.try_mutate(when, |tasks| {
+			if tasks.len() >= T::MAX_SCHEDULED_PER_BLOCK as usize {
+				return Err("Agenda is full");
+			}
+			tasks.push(task);
+			Ok(())
+		})?;
+
+		self.lookup.insert(id, when);
+		Ok(())
+	}
+
+	/// Remove and return all the tasks which are due at block `now`.
+	/// Tasks scheduled for a block which has already passed are also due.
+	fn take_due(&mut self, now: T::BlockNumber) -> Vec<Scheduled<T>> {
+		let due_blocks = self.agenda.iter().map(|(when, _)| *when).take_while(|when| *when <= now).collect::<Vec<_>>();
+
+		let mut due = Vec::new();
+		for when in due_blocks {
+			for task in self.agenda.remove(&when).unwrap_or_default() {
+				self.lookup.remove(&task.id);
+				due.push(task);
+			}
+		}
+		due
+	}
+}
+
+#[macros::call(errors(
+	"Task does not exist",
+	"Caller is not the origin of the task",
+	"Period must not be zero",
+	"Overflow",
+	"Agenda is full",
+))]
+impl<T: Config> Pallet<T> {
+	/// Schedule `call` to be dispatched with the `origin` of this call at block `when`, and then
+	/// every `period` blocks if a period is given.
+	/// Tasks scheduled for a block which has already passed are dispatched at the next block.
+	#[allow(clippy::boxed_local)]
+	pub fn schedule(
+		&mut self,
+		origin: OriginFor<T>,
+		when: T::BlockNumber,
+		period: Option<T::BlockNumber>,
+		#[runtime_call] call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		self.schedule_as(origin, when, period, *call)?;
+		Ok(())
+	}
+
+	/// Cancel the scheduled task `id`.
+	/// This function will return an error if the task does not exist, or if the `origin` is not the
+	/// origin of the task.
+	pub fn cancel(&mut self, origin: OriginFor<T>, id: TaskId) -> DispatchResult {
+		let when = self.lookup.get(&id).ok_or("Task does not exist")?;
+		self.agenda.try_mutate(when, |tasks| {
+			let index = tasks.iter().position(|task| task.id == id).ok_or("Task does not exist")?;
+			if tasks[index].origin != origin {
+				return Err("Caller is not the origin of the task");
+			}
+			tasks.remove(index);
+			Ok(())
+		})?;
+		if self.agenda(&when).is_empty() {
+			self.agenda.remove(&when);
+		}
+		self.lookup.remove(&id);
+		self.events.push(Event::Canceled { when, id });
+
+		Ok(())
+	}
+}
+
+impl<T> Hooks<T, T::BlockNumber> for Pallet<T>
+where
+	T: Config + GetPallet<Self> + Dispatch<Caller = OriginFor<T>, Call = <T as Config>::RuntimeCall>,
+{
+	/// Dispatch all the tasks which are due, and schedule the periodic ones again. A periodic task
+	/// whose next block overflows, or whose next agenda is full, is dropped.
+	fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) {
+		let due = runtime.pallet_mut().take_due(block_number);
+
+		for Scheduled { id, origin, period, call } in due {
+			let (result, rescheduled) = match period {
+				Some(period) => {
+					let result = runtime.dispatch(origin.clone(), call.clone());
+					let task = Scheduled { id, origin, period: Some(period), call };
+					let pallet: &mut Self = runtime.pallet_mut();
+					let rescheduled = match block_number.checked_add(&period) {
+						Some(when) => pallet.insert(when, task),
+						None => Err("Overflow".into()),
+					};
+					(result, rescheduled)
+				},
+				None => (runtime.dispatch(origin, call), Ok(())),
+			};
+
+			let pallet: &mut Self = runtime.pallet_mut();
+			pallet.events.push(Event::Dispatched { id, result });
+			if let Err(error) = rescheduled {
+				pallet.events.push(Event::PeriodicFailed { id, error });
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Event;
+	use crate::support::ensure::RawOrigin;
+	use crate::support::Hooks;
+	use crate::{balances, support, types, Runtime, RuntimeCall};
+
+	fn transfer(to: &str, amount: u128) -> Box<RuntimeCall> {
+		Box::new(RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount }))
+	}
+
+	fn empty_block(block_number: u32) -> types::Block {
+		support::Block { header: support::Header { block_number, author: "alice".to_string() }, extrinsics: vec![] }
+	}
+
+	#[test]
+	fn scheduled_call_is_dispatched() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		runtime.scheduler.schedule(RawOrigin::Signed(alice.clone()), 2, None, transfer("bob", 20)).unwrap();
+		assert_eq!(runtime.scheduler.agenda(&2).len(), 1);
+
+		// Nothing happens before the scheduled block.
+		runtime.execute_block(empty_block(1)).unwrap();
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+
+		runtime.execute_block(empty_block(2)).unwrap();
+		assert_eq!(runtime.balances.balance(&alice), 80);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 20);
+		assert!(runtime.scheduler.agenda(&2).is_empty());
+		assert!(matches!(
+			runtime.scheduler.events().last(),
+			Some(Event::Dispatched { id: 0, result: Ok(()) })
+		));
+	}
+
+	#[test]
+	fn periodic_call_until_canceled() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		runtime.scheduler.schedule(RawOrigin::Signed(alice.clone()), 1, Some(2), transfer("bob", 10)).unwrap();
+		assert_eq!(
+			runtime.scheduler.schedule(RawOrigin::Signed(alice.clone()), 1, Some(0), transfer("bob", 10)),
+			Err("Period must not be zero".into())
+		);
+
+		for block_number in 1..=3 {
+			runtime.execute_block(empty_block(block_number)).unwrap();
+		}
+		// Dispatched at blocks 1 and 3, and scheduled again for block 5.
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 20);
+		assert_eq!(runtime.scheduler.agenda(&5).len(), 1);
+
+		// Only the origin of the task can cancel it.
+		assert_eq!(
+			runtime.scheduler.cancel(RawOrigin::Signed("bob".to_string()), 0),
+			Err("Caller is not the origin of the task".into())
+		);
+		runtime.scheduler.cancel(RawOrigin::Signed(alice.clone()), 0).unwrap();
+		assert_eq!(runtime.scheduler.cancel(RawOrigin::Signed(alice), 0), Err("Task does not exist".into()));
+
+		for block_number in 4..=5 {
+			runtime.execute_block(empty_block(block_number)).unwrap();
+		}
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 20);
+	}
+
+	#[test]
+	fn agenda_is_bounded() {
+		let mut runtime = Runtime::new();
+		let alice = RawOrigin::Signed("alice".to_string());
+
+		for _ in 0..50 {
+			runtime.scheduler.schedule(alice.clone(), 2, None, transfer("bob", 1)).unwrap();
+		}
+		assert_eq!(runtime.scheduler.schedule(alice.clone(), 2, None, transfer("bob", 1)), Err("Agenda is full".into()));
+		runtime.scheduler.schedule(alice, 3, None, transfer("bob", 1)).unwrap();
+		assert_eq!(runtime.scheduler.agenda(&3)[0].id, 50);
+	}
+
+	#[test]
+	fn periodic_call_is_dropped_on_overflow() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		runtime.scheduler.schedule(RawOrigin::Signed(alice.clone()), u32::MAX - 1, Some(2), transfer("bob", 10)).unwrap();
+		<super::Pallet<Runtime> as Hooks<_, _>>::on_initialize(&mut runtime, u32::MAX - 1);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 10);
+		assert!(matches!(
+			runtime.scheduler.events().last(),
+			Some(Event::PeriodicFailed { id: 0, error: support::DispatchError::Other("Overflow") })
+		));
+		assert!(runtime.scheduler.agenda(&u32::MAX).is_empty());
+	}
+
+	#[test]
+	fn calls_are_compared_and_displayed() {
+		assert_eq!(transfer("bob", 20), transfer("bob", 20));
+		assert_ne!(transfer("bob", 20), transfer("bob", 10));
+		assert_eq!(transfer("bob", 20).to_string(), "balances.transfer(to=bob, amount=20)");
+
+		let schedule = RuntimeCall::scheduler(super::Call::schedule { when: 4, period: None, call: transfer("bob", 20) });
+		assert_eq!(
+			schedule.to_string(),
+			"scheduler.schedule(when=4, period=None, call=balances.transfer(to=bob, amount=20))"
+		);
+	}
+}
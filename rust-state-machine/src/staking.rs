@@ -0,0 +1,587 @@
+use std::collections::BTreeMap;
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Zero};
+use crate::balances::LockIdentifier;
+use crate::support::ensure::{ensure_root, OriginFor};
+use crate::support::storage::Encode;
+use crate::support::{DispatchResult, GetPallet, Hooks, OnUnbalanced, ValidateAuthor};
+
+/// The identifier of the lock this pallet places on bonded funds.
+const STAKING_ID: LockIdentifier = *b"staking ";
+
+/// The index of an era, the period during which the same validators author blocks.
+pub type EraIndex = u32;
+
+/// The configuration of the Staking Module.
+///
+/// Bonded funds are locked in the balances pallet, and rewards and slashes are paid from it, so the
+/// runtime must give access to it.
+pub trait Config:
+	crate::balances::Config<Balance: From<u8> + From<u32> + CheckedDiv>
+	+ Clone
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+{
+	/// The number of blocks in an era.
+	const ERA_LENGTH: Self::BlockNumber;
+	/// The maximum number of validators elected for an era.
+	const VALIDATOR_COUNT: u32;
+	/// The number of eras unbonded funds stay locked for.
+	const BONDING_DURATION: EraIndex;
+	/// The amount minted at the end of every era, and shared between the validators and their
+	/// nominators.
+	const ERA_REWARD: Self::Balance;
+
+	/// The handler of the funds slashed from misbehaving validators and their nominators.
+	type Slash: OnUnbalanced<Self, Self::Balance>;
+}
+
+/// A part of the bonded funds of an account which is being unbonded.
+#[derive(Debug, Clone)]
+pub struct UnlockChunk<T: Config> {
+	/// The amount being unbonded.
+	pub value: T::Balance,
+	/// The era from which the amount can be withdrawn.
+	pub era: EraIndex,
+}
+
+impl<T: Config> Encode for UnlockChunk<T>
+where
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.value.encode_to(dest);
+		self.era.encode_to(dest);
+	}
+}
+
+/// The bonded funds of an account.
+#[derive(Debug, Clone)]
+pub struct StakingLedger<T: Config> {
+	/// The amount which is actively staked.
+	pub active: T::Balance,
+	/// The amounts which are being unbonded, and are still locked.
+	pub unlocking: Vec<UnlockChunk<T>>,
+}
+
+impl<T: Config> Encode for StakingLedger<T>
+where
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.active.encode_to(dest);
+		self.unlocking.encode_to(dest);
+	}
+}
+
+impl<T: Config> StakingLedger<T> {
+	/// The total amount locked by this ledger, active or unlocking.
+	fn total(&self) -> T::Balance {
+		self.unlocking
+			.iter()
+			.fold(self.active, |total, chunk| total.checked_add(&chunk.value).unwrap_or(total))
+	}
+}
+
+/// The funds backing an elected validator.
+#[derive(Debug, Clone)]
+pub struct Exposure<T: Config> {
+	/// The total amount backing the validator.
+	pub total: T::Balance,
+	/// The amount bonded by the validator itself.
+	pub own: T::Balance,
+	/// The nominators of the validator, and the amount of their bond backing it.
+	pub others: Vec<(T::AccountId, T::Balance)>,
+}
+
+impl<T: Config> Encode for Exposure<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.total.encode_to(dest);
+		self.own.encode_to(dest);
+		self.others.encode_to(dest);
+	}
+}
+
+impl<T: Config> Exposure<T> {
+	/// Every account backing the validator, including the validator itself, with their stake.
+	fn stakers<'a>(&'a self, validator: &'a T::AccountId) -> impl Iterator<Item = (&'a T::AccountId, T::Balance)> {
+		core::iter::once((validator, self.own)).chain(self.others.iter().map(|(who, stake)| (who, *stake)))
+	}
+}
+
+/// The events emitted by the Staking Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// The era `era` started, with `validators` elected to author its blocks.
+	NewEra { era: EraIndex, validators: Vec<T::AccountId> },
+	/// `who` was rewarded `amount` for staking during the last era.
+	Rewarded { who: T::AccountId, amount: T::Balance },
+	/// `amount` was slashed from the bond of `who`.
+	Slashed { who: T::AccountId, amount: T::Balance },
+}
+
+/// This is the Staking Module.
+/// It allows accounts to bond funds, and to use them either to validate, or to nominate validators.
+/// At the beginning of every era, the validators with the most backing are elected as the
+/// authorities allowed to author blocks, and everyone backing them is rewarded at the end of the era.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from an account to its bonded funds.
+	ledger: StorageMap<T::AccountId, StakingLedger<T>>,
+	/// The accounts which want to validate.
+	validators: StorageMap<T::AccountId, ()>,
+	/// A map from an account to the validators it nominates.
+	nominators: StorageMap<T::AccountId, Vec<T::AccountId>>,
+	/// The current era.
+	#[default(0)]
+	current_era: StorageValue<EraIndex>,
+	/// The block at which the next era starts, or `None` if it would overflow the block number.
+	#[default(Some(T::ERA_LENGTH))]
+	next_era_start: StorageValue<Option<T::BlockNumber>>,
+	/// A map from the validators elected for the current era to their backing.
+	exposures: StorageMap<T::AccountId, Exposure<T>>,
+	/// The validators elected for the current era, which are the authorities of block production.
+	#[default(Vec::new())]
+	authorities: StorageValue<Vec<T::AccountId>>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the bonded funds (if any) of `who`.
+	pub fn ledger(&self, who: &T::AccountId) -> Option<&StakingLedger<T>> {
+		self.ledger.try_get(who)
+	}
+
+	/// Get the current era.
+	pub fn current_era(&self) -> EraIndex {
+		self.current_era.get()
+	}
+
+	/// Get the backing (if any) of the elected validator `who`.
+	pub fn exposure(&self, who: &T::AccountId) -> Option<&Exposure<T>> {
+		self.exposures.try_get(who)
+	}
+
+	/// Get the validators elected for the current era, which are allowed to author blocks.
+	pub fn authorities(&self) -> &[T::AccountId] {
+		self.authorities.try_get().map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+
+	/// Store the ledger of `who`, and lock all of its funds. An empty ledger is removed, along with
+	/// the intentions of `who`.
+	fn update_ledger(runtime: &mut T, who: &T::AccountId, ledger: StakingLedger<T>) {
+		let total = ledger.total();
+		let pallet: &mut Self = runtime.pallet_mut();
+		if total.is_zero() {
+			pallet.ledger.remove(who);
+			pallet.validators.remove(who);
+			pallet.nominators.remove(who);
+		} else {
+			pallet.ledger.insert(who.clone(), ledger);
+		}
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		if total.is_zero() {
+			balances.remove_lock(STAKING_ID, who);
+		} else {
+			balances.set_lock(STAKING_ID, who, total);
+		}
+	}
+
+	/// Elect the validators with the most backing. The bond of a nominator is split evenly between
+	/// the validators it nominates.
+	fn elect(&self) -> BTreeMap<T::AccountId, Exposure<T>> {
+		let mut candidates = self
+			.validators
+			.iter()
+			.filter_map(|(who, _)| {
+				let own = self.ledger.try_get(who)?.active;
+				Some((who.clone(), Exposure { total: own, own, others: Vec::new() }))
+			})
+			.collect::<BTreeMap<T::AccountId, Exposure<T>>>();
+
+		for (nominator, targets) in self.nominators.iter() {
+			let Some(ledger) = self.ledger.try_get(nominator) else { continue };
+			let backed = targets.iter().filter(|target| candidates.contains_key(*target)).collect::<Vec<_>>();
+			let count: T::Balance = (backed.len() as u32).into();
+			let Some(share) = ledger.active.checked_div(&count) else { continue };
+
+			for target in backed {
+				if let Some(exposure) = candidates.get_mut(target) {
+					exposure.total = exposure.total.checked_add(&share).unwrap_or(exposure.total);
+					exposure.others.push((nominator.clone(), share));
+				}
+			}
+		}
+
+		// The sort is stable, so ties are broken by account.
+		let mut ranked = candidates.into_iter().collect::<Vec<_>>();
+		ranked.sort_by_key(|(_, exposure)| core::cmp::Reverse(exposure.total));
+		ranked.truncate(T::VALIDATOR_COUNT as usize);
+		ranked.into_iter().collect()
+	}
+
+	/// Mint the era reward, and share it between the elected validators, and then between everyone
+	/// backing them in proportion to their stake.
+	fn reward(runtime: &mut T) {
+		let pallet: &Self = runtime.pallet();
+		let count: T::Balance = (pallet.exposures.iter().count() as u32).into();
+		let Some(per_validator) = T::ERA_REWARD.checked_div(&count) else { return };
+
+		let payouts = pallet
+			.exposures
+			.iter()
+			.flat_map(|(validator, exposure)| {
+				exposure.stakers(validator).filter_map(move |(who, stake)| {
+					let amount = stake.checked_mul(&per_validator)?.checked_div(&exposure.total)?;
+					Some((who.clone(), amount))
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for (who, amount) in payouts {
+			let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+			if balances.deposit(&who, amount).is_ok() {
+				let pallet: &mut Self = runtime.pallet_mut();
+				pallet.events.push(Event::Rewarded { who, amount });
+			}
+		}
+	}
+
+	/// Slash up to `amount` from the active bond of `who`, handing the funds to `T::Slash`.
+	fn slash_staker(runtime: &mut T, who: &T::AccountId, amount: T::Balance) {
+		let pallet: &Self = runtime.pallet();
+		let Some(ledger) = pallet.ledger.try_get(who) else { return };
+		let amount = amount.min(ledger.active);
+		let active = ledger.active.checked_sub(&amount).unwrap_or(Zero::zero());
+		let unlocking = ledger.unlocking.clone();
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		let slashed = balances.slash(who, amount);
+		Self::update_ledger(runtime, who, StakingLedger { active, unlocking });
+		T::Slash::on_unbalanced(runtime, slashed);
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.events.push(Event::Slashed { who: who.clone(), amount: slashed });
+	}
+}
+
+#[macros::call(errors(
+	"Already bonded",
+	"Cannot bond zero",
+	"Not enough funds.",
+	"Not bonded",
+	"Overflow",
+	"Not enough bonded funds",
+	"No nomination targets",
+	"Invalid slash percentage",
+	"Validator is not elected",
+))]
+impl<T: Config> Pallet<T> {
+	/// Bond `value` of the funds of the `caller`, which are locked until they are unbonded.
+	/// This function will return an error if the caller is already bonded.
+	pub fn bond(runtime: &mut T, caller: T::AccountId, value: T::Balance) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		if pallet.ledger.contains_key(&caller) {
+			return Err("Already bonded".into());
+		}
+		if value.is_zero() {
+			return Err("Cannot bond zero".into());
+		}
+		let balances: &crate::balances::Pallet<T> = runtime.pallet();
+		if value > balances.balance(&caller) {
+			return Err("Not enough funds.".into());
+		}
+
+		Self::update_ledger(runtime, &caller, StakingLedger { active: value, unlocking: Vec::new() });
+		Ok(())
+	}
+
+	/// Bond `value` more of the funds of the `caller`.
+	/// This function will return an error if the caller is not bonded yet.
+	pub fn bond_extra(runtime: &mut T, caller: T::AccountId, value: T::Balance) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let ledger = pallet.ledger.try_get(&caller).ok_or("Not bonded")?;
+		let active = ledger.active.checked_add(&value).ok_or("Overflow")?;
+		let ledger = StakingLedger { active, unlocking: ledger.unlocking.clone() };
+
+		let balances: &crate::balances::Pallet<T> = runtime.pallet();
+		if ledger.total() > balances.balance(&caller) {
+			return Err("Not enough funds.".into());
+		}
+
+		Self::update_ledger(runtime, &caller, ledger);
+		Ok(())
+	}
+
+	/// Unbond `value` of the active bond of the `caller`. The funds stay locked for
+	/// `T::BONDING_DURATION` eras, after which they can be withdrawn.
+	pub fn unbond(runtime: &mut T, caller: T::AccountId, value: T::Balance) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let ledger = pallet.ledger.try_get(&caller).ok_or("Not bonded")?;
+		let active = ledger.active.checked_sub(&value).ok_or("Not enough bonded funds")?;
+		let era = pallet.current_era.get().checked_add(T::BONDING_DURATION).ok_or("Overflow")?;
+
+		let mut unlocking = ledger.unlocking.clone();
+		unlocking.push(UnlockChunk { value, era });
+		Self::update_ledger(runtime, &caller, StakingLedger { active, unlocking });
+		Ok(())
+	}
+
+	/// Unlock the unbonded funds of the `caller` which can be withdrawn. Once every fund has been
+	/// unbonded and withdrawn, the caller is no longer bonded.
+	pub fn withdraw_unbonded(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let ledger = pallet.ledger.try_get(&caller).ok_or("Not bonded")?;
+		let current_era = pallet.current_era.get();
+		let unlocking = ledger.unlocking.iter().filter(|chunk| chunk.era > current_era).cloned().collect();
+
+		Self::update_ledger(runtime, &caller, StakingLedger { active: ledger.active, unlocking });
+		Ok(())
+	}
+
+	/// Declare the intention of the `caller` to validate from the next era, replacing any
+	/// nominations.
+	pub fn validate(&mut self, caller: T::AccountId) -> DispatchResult {
+		if !self.ledger.contains_key(&caller) {
+			return Err("Not bonded".into());
+		}
+
+		self.nominators.remove(&caller);
+		self.validators.insert(caller, ());
+		Ok(())
+	}
+
+	/// Declare the intention of the `caller` to back `targets` from the next era, replacing any
+	/// intention to validate.
+	pub fn nominate(&mut self, caller: T::AccountId, mut targets: Vec<T::AccountId>) -> DispatchResult {
+		if !self.ledger.contains_key(&caller) {
+			return Err("Not bonded".into());
+		}
+		if targets.is_empty() {
+			return Err("No nomination targets".into());
+		}
+		targets.sort();
+		targets.dedup();
+
+		self.validators.remove(&caller);
+		self.nominators.insert(caller, targets);
+		Ok(())
+	}
+
+	/// Remove the intention of the `caller` to validate or to nominate, from the next era.
+	pub fn chill(&mut self, caller: T::AccountId) -> DispatchResult {
+		if !self.ledger.contains_key(&caller) {
+			return Err("Not bonded".into());
+		}
+
+		self.validators.remove(&caller);
+		self.nominators.remove(&caller);
+		Ok(())
+	}
+
+	/// Slash `percent` of the stake of everyone backing the elected `validator`, and stop it from
+	/// validating from the next era.
+	/// This function should only succeed if the caller is the `Root` origin.
+	pub fn slash(runtime: &mut T, origin: OriginFor<T>, validator: T::AccountId, percent: u8) -> DispatchResult {
+		ensure_root(origin)?;
+		if percent > 100 {
+			return Err("Invalid slash percentage".into());
+		}
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		let exposure = pallet.exposures.try_get(&validator).ok_or("Validator is not elected")?;
+		let (percent, hundred): (T::Balance, T::Balance) = (percent.into(), 100u8.into());
+		let slashes = exposure
+			.stakers(&validator)
+			.map(|(who, stake)| {
+				// Divide first if multiplying first overflows, at the cost of some precision.
+				let amount = stake
+					.checked_mul(&percent)
+					.and_then(|amount| amount.checked_div(&hundred))
+					.or_else(|| stake.checked_div(&hundred).and_then(|amount| amount.checked_mul(&percent)))
+					.unwrap_or(Zero::zero());
+				(who.clone(), amount)
+			})
+			.collect::<Vec<_>>();
+		pallet.validators.remove(&validator);
+
+		for (who, amount) in slashes {
+			Self::slash_staker(runtime, &who, amount);
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+	/// At era boundaries, reward the validators of the era which ended, and elect the validators of
+	/// the new one.
+	fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) {
+		let pallet: &Self = runtime.pallet();
+		match pallet.next_era_start.get() {
+			Some(next_era_start) if block_number >= next_era_start => {},
+			_ => return,
+		}
+
+		Self::reward(runtime);
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		// If the next era overflows, the new era is the last one, and its validators are kept.
+		pallet.next_era_start.put(block_number.checked_add(&T::ERA_LENGTH));
+		pallet.current_era.mutate(|era| *era = era.saturating_add(1));
+		let elected = pallet.elect();
+		let previous = pallet.exposures.iter().map(|(who, _)| who.clone()).collect::<Vec<_>>();
+		for who in previous {
+			pallet.exposures.remove(&who);
+		}
+		// If nobody could be elected, the previous validators keep authoring blocks, so that the
+		// chain does not stall or fall back to allowing any author.
+		if !elected.is_empty() {
+			pallet.authorities.put(elected.keys().cloned().collect());
+		}
+		for (who, exposure) in elected {
+			pallet.exposures.insert(who, exposure);
+		}
+		let event = Event::NewEra { era: pallet.current_era.get(), validators: pallet.authorities.get() };
+		pallet.events.push(event);
+	}
+}
+
+/// Only the validators elected for the current era can author blocks. Before the first election,
+/// there are no validators yet, so any account can author blocks.
+impl<T: Config> ValidateAuthor<T, T::AccountId> for Pallet<T> {
+	fn validate_author(runtime: &T, author: &T::AccountId) -> DispatchResult {
+		let pallet: &Self = runtime.pallet();
+		let authorities = pallet.authorities();
+		if !authorities.is_empty() && !authorities.contains(author) {
+			return Err("Block author is not an authority".into());
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Pallet;
+	use crate::support::ensure::RawOrigin;
+	use crate::support::Hooks;
+	use crate::{support, types, Runtime};
+
+	fn run_to_block(runtime: &mut Runtime, block_number: u32) {
+		while runtime.system.block_number() < block_number {
+			// Any of the current authorities can author the block.
+			let author = runtime.staking.authorities().first().cloned().unwrap_or_default();
+			let header = support::Header { block_number: runtime.system.block_number() + 1, author };
+			let block: types::Block = support::Block { header, extrinsics: vec![] };
+			runtime.execute_block(block).unwrap();
+		}
+	}
+
+	fn setup() -> Runtime {
+		let mut runtime = Runtime::new();
+		for (who, bond) in [("alice", 100), ("bob", 50), ("charlie", 30), ("dave", 100)] {
+			runtime.balances.set_balance(&who.to_string(), 200);
+			Pallet::bond(&mut runtime, who.to_string(), bond).unwrap();
+		}
+		runtime.staking.validate("alice".to_string()).unwrap();
+		runtime.staking.validate("bob".to_string()).unwrap();
+		runtime.staking.validate("charlie".to_string()).unwrap();
+		// `dave` backs `charlie`, who then has more backing than `bob`.
+		runtime.staking.nominate("dave".to_string(), vec!["charlie".to_string()]).unwrap();
+		runtime
+	}
+
+	#[test]
+	fn election_and_rewards() {
+		let mut runtime = setup();
+		let (alice, charlie, dave) = ("alice".to_string(), "charlie".to_string(), "dave".to_string());
+		assert_eq!(runtime.balances.transfer(dave.clone(), alice.clone(), 101), Err("Funds are locked.".into()));
+
+		run_to_block(&mut runtime, 5);
+		assert_eq!(runtime.staking.current_era(), 1);
+		assert_eq!(runtime.staking.authorities(), &[alice.clone(), charlie.clone()]);
+		assert_eq!(runtime.staking.exposure(&charlie).unwrap().total, 130);
+
+		// Only the elected validators can author the blocks of the era.
+		let block = |author: &String| types::Block {
+			header: support::Header { block_number: 6, author: author.clone() },
+			extrinsics: vec![],
+		};
+		assert_eq!(runtime.execute_block(block(&"bob".to_string())), Err("Block author is not an authority".into()));
+		assert_eq!(runtime.system.block_number(), 5);
+		runtime.execute_block(block(&charlie)).unwrap();
+
+		// The reward of 100 is split between the 2 validators, and then by stake.
+		run_to_block(&mut runtime, 10);
+		assert_eq!(runtime.balances.balance(&alice), 250);
+		assert_eq!(runtime.balances.balance(&charlie), 211);
+		assert_eq!(runtime.balances.balance(&dave), 238);
+	}
+
+	#[test]
+	fn slashed_funds_go_to_the_treasury() {
+		let mut runtime = setup();
+		let (charlie, dave) = ("charlie".to_string(), "dave".to_string());
+		let pot = crate::treasury::Pallet::<Runtime>::account_id();
+		run_to_block(&mut runtime, 5);
+
+		assert_eq!(Pallet::slash(&mut runtime, RawOrigin::Signed(charlie.clone()), charlie.clone(), 10), Err(support::DispatchError::BadOrigin));
+		Pallet::slash(&mut runtime, RawOrigin::Root, charlie.clone(), 10).unwrap();
+		assert_eq!(runtime.staking.ledger(&charlie).unwrap().active, 27);
+		assert_eq!(runtime.staking.ledger(&dave).unwrap().active, 90);
+		assert_eq!(runtime.balances.balance(&pot), 13);
+
+		// `charlie` is no longer a validator from the next era.
+		run_to_block(&mut runtime, 10);
+		assert!(!runtime.staking.authorities().contains(&charlie));
+
+		// Unbonded funds can only be withdrawn after the bonding duration.
+		Pallet::unbond(&mut runtime, dave.clone(), 90).unwrap();
+		Pallet::withdraw_unbonded(&mut runtime, dave.clone()).unwrap();
+		assert_eq!(runtime.balances.locked_balance(&dave), 90);
+		run_to_block(&mut runtime, 15);
+		Pallet::withdraw_unbonded(&mut runtime, dave.clone()).unwrap();
+		assert_eq!(runtime.balances.locked_balance(&dave), 0);
+		assert!(runtime.staking.ledger(&dave).is_none());
+	}
+
+	#[test]
+	fn large_stakes_are_slashed_by_their_percentage() {
+		let mut runtime = Runtime::new();
+		let erin = "erin".to_string();
+		let stake = u128::MAX / 2;
+		runtime.balances.set_balance(&erin, stake);
+		Pallet::bond(&mut runtime, erin.clone(), stake).unwrap();
+		runtime.staking.validate(erin.clone()).unwrap();
+		run_to_block(&mut runtime, 5);
+
+		// Multiplying the stake by the percentage overflows, so it is divided first.
+		Pallet::slash(&mut runtime, RawOrigin::Root, erin.clone(), 10).unwrap();
+		assert_eq!(runtime.staking.ledger(&erin).unwrap().active, stake - stake / 100 * 10);
+	}
+
+	#[test]
+	fn eras_stop_when_the_next_era_overflows() {
+		let mut runtime = setup();
+		runtime.staking.next_era_start.put(Some(u32::MAX - 1));
+		<Pallet<Runtime> as Hooks<_, _>>::on_initialize(&mut runtime, u32::MAX - 1);
+		assert_eq!(runtime.staking.current_era(), 1);
+		assert_eq!(runtime.staking.next_era_start.get(), None);
+
+		// The last era goes on, with the same validators.
+		<Pallet<Runtime> as Hooks<_, _>>::on_initialize(&mut runtime, u32::MAX);
+		assert_eq!(runtime.staking.current_era(), 1);
+		assert_eq!(runtime.staking.authorities(), &["alice".to_string(), "charlie".to_string()]);
+	}
+}
@@ -1,3 +1,7 @@
+pub mod ensure;
+pub mod metadata;
+pub mod storage;
+
 /// The most primitive representation of a Blockchain block.
 pub struct Block<Header, Extrinsic> {
 	/// The block header contains metadata about the block.
@@ -6,14 +10,17 @@ pub struct Block<Header, Extrinsic> {
 	pub extrinsics: Vec<Extrinsic>,
 }
 
-/// We are using an extremely simplified header which only contains the current block number.
+/// We are using an extremely simplified header which only contains the current block number, and
+/// the account which authored the block.
 /// On a real blockchain, you would expect to also find:
 /// - parent block hash
 /// - state root
 /// - extrinsics root
+/// - a signature of the author
 /// - etc...
-pub struct Header<BlockNumber> {
+pub struct Header<BlockNumber, AccountId> {
 	pub block_number: BlockNumber,
+	pub author: AccountId,
 }
 
 /// This is an "extrinsic": literally an external message from outside of the blockchain.
@@ -24,19 +31,359 @@ pub struct Extrinsic<Caller, Call> {
 	pub call: Call,
 }
 
+/// The error of a dispatched call.
+///
+/// Pallets return their own errors as static messages, which `?` and `into()` turn into `Other`.
+/// The other variants are the errors any call can fail with, whatever its pallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DispatchError {
+	/// The call was made by an origin which is not allowed to make it.
+	BadOrigin,
+	/// The call was rejected by the call filter of the runtime.
+	Filtered,
+	/// An error of the pallet of the call.
+	Other(&'static str),
+}
+
+impl From<&'static str> for DispatchError {
+	fn from(error: &'static str) -> Self {
+		DispatchError::Other(error)
+	}
+}
+
+impl From<ensure::OriginError> for DispatchError {
+	fn from(_error: ensure::OriginError) -> Self {
+		DispatchError::BadOrigin
+	}
+}
+
+impl core::fmt::Display for DispatchError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			DispatchError::BadOrigin => f.write_str("Bad origin"),
+			DispatchError::Filtered => f.write_str("Call is filtered"),
+			DispatchError::Other(error) => f.write_str(error),
+		}
+	}
+}
+
 /// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
-/// otherwise return a static error message.
-pub type DispatchResult = Result<(), &'static str>;
+/// otherwise return a `DispatchError`.
+pub type DispatchResult = Result<(), DispatchError>;
+
+/// The identifier of a pallet, from which the runtime derives an account owned by the pallet, for
+/// example to hold the funds of a treasury.
+pub type PalletId = [u8; 8];
+
+/// Write an argument of a call for the `Display` of the call, which `#[macros::call]` generates.
+///
+/// Arguments are written with their `Debug` representation, except that strings which are a single
+/// word, like account ids, are written without quotes. A transfer is then written as
+/// `transfer(to=bob, amount=20)`.
+pub fn fmt_call_arg<Arg: core::fmt::Debug + ?Sized>(
+	arg: &Arg,
+	f: &mut core::fmt::Formatter<'_>,
+) -> core::fmt::Result {
+	let debug = format!("{:?}", arg);
+	match debug.strip_prefix('"').and_then(|debug| debug.strip_suffix('"')) {
+		Some(word) if !word.is_empty() && word.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') => {
+			f.write_str(word)
+		},
+		_ => f.write_str(&debug),
+	}
+}
+
+/// Write the calls given as an argument of another call, like the call of a scheduled task or the
+/// calls of a batch, with their `Display` rather than their `Debug` representation.
+///
+/// `#[macros::call]` uses this instead of `fmt_call_arg` for the arguments whose type mentions
+/// `RuntimeCall`, so a scheduled transfer is written as
+/// `schedule(when=4, period=None, call=balances.transfer(to=bob, amount=20))`.
+pub trait FormatCallArg {
+	/// Write the calls in `self`.
+	fn fmt_call_arg(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result;
+}
+
+impl<Call: core::fmt::Display> FormatCallArg for Box<Call> {
+	fn fmt_call_arg(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		core::fmt::Display::fmt(&**self, f)
+	}
+}
+
+impl<Call: core::fmt::Display> FormatCallArg for Vec<Call> {
+	fn fmt_call_arg(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "[")?;
+		for (i, call) in self.iter().enumerate() {
+			if i > 0 {
+				write!(f, ", ")?;
+			}
+			core::fmt::Display::fmt(call, f)?;
+		}
+		write!(f, "]")
+	}
+}
 
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
 pub trait Dispatch {
-	/// The type used to identify the caller of the function.
+	/// The type used to identify the caller of the function, which is the `ensure::RawOrigin` of
+	/// the call.
 	type Caller;
 	/// The state transition function call the caller is trying to access.
 	type Call;
 
-	/// This function takes a `caller` and the `call` they want to make, and returns a `Result`
-	/// based on the outcome of that function call.
+	/// This function takes the origin of the `caller` and the `call` they want to make, and returns
+	/// a `Result` based on the outcome of that function call.
 	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
-}
\ No newline at end of file
+}
+
+/// Execute `f` on the `runtime`, reverting every change it made to the runtime if it returns an
+/// error.
+///
+/// To keep things simple, we just keep a copy of the whole runtime around until `f` completes.
+pub fn transactional<Runtime: Clone, R, E>(
+	runtime: &mut Runtime,
+	f: impl FnOnce(&mut Runtime) -> Result<R, E>,
+) -> Result<R, E> {
+	let snapshot = runtime.clone();
+	let result = f(runtime);
+	if result.is_err() {
+		*runtime = snapshot;
+	}
+	result
+}
+
+/// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
+/// function call of a pallet, giving that function access to the whole runtime rather than just the
+/// pallet. This lets calls dispatch other calls, or update the state of other pallets.
+///
+/// `#[macros::call]` implements this for every pallet, and `#[macros::runtime]` uses it to route
+/// calls to the pallets.
+pub trait RuntimeDispatch<Runtime> {
+	/// The type used to identify the caller of the function, which is the `ensure::RawOrigin` of
+	/// the call.
+	type Caller;
+	/// The state transition function call the caller is trying to access.
+	type Call;
+
+	/// This function takes the `runtime`, the origin of the `caller` and the `call` they want to
+	/// make, and returns a `Result` based on the outcome of that function call.
+	fn dispatch(runtime: &mut Runtime, caller: Self::Caller, call: Self::Call) -> DispatchResult;
+}
+
+/// A trait which gives access to a pallet stored inside of the runtime.
+///
+/// `#[macros::runtime]` implements this for every pallet included in the `Runtime` struct. Since
+/// the runtime is also the type which implements every pallet `Config`, pallets which need to
+/// interact with other pallets can require `T: GetPallet<..>` and reach them through `T`.
+pub trait GetPallet<Pallet> {
+	/// Get a reference to the pallet.
+	fn pallet(&self) -> &Pallet;
+	/// Get a mutable reference to the pallet.
+	fn pallet_mut(&mut self) -> &mut Pallet;
+}
+
+/// An instance of a pallet, which lets a runtime include the same pallet more than once.
+///
+/// Pallets which support instances take an instance as their second generic parameter, like
+/// `Pallet<T, I = ()>` with `Config<I>`, so the runtime implements a separate `Config` for every
+/// instance. The unit type is the default instance, and the prefix of the instance keeps the
+/// storage of every instance apart.
+pub trait Instance: 'static {
+	/// The prefix of the storage of this instance. The default instance has an empty prefix.
+	const PREFIX: &'static str;
+}
+
+impl Instance for () {
+	const PREFIX: &'static str = "";
+}
+
+/// The first instance of a pallet, besides the default one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instance1;
+
+impl Instance for Instance1 {
+	const PREFIX: &'static str = "Instance1";
+}
+
+/// Logic which a pallet can execute at fixed points of the block execution.
+///
+/// Hooks are given access to the whole runtime rather than a single pallet, so they can dispatch
+/// calls or update the state of other pallets. Every pallet included in the runtime must implement
+/// this trait, but all the hooks have a default implementation which does nothing.
+pub trait Hooks<Runtime, BlockNumber> {
+	/// Called at the beginning of every block, after the block number has been incremented and
+	/// before any of the extrinsics are executed.
+	fn on_initialize(_runtime: &mut Runtime, _block_number: BlockNumber) {}
+}
+
+/// Nothing to clear, for the pallets which do not emit events.
+///
+/// `#[macros::runtime]` clears the events of every pallet at the beginning of every block, with
+/// this trait in scope. Like `metadata::DefaultMetadata`, the `clear_events` generated by
+/// `#[macros::event]` is used when it exists, since inherent functions take precedence.
+pub trait DefaultEvents {
+	fn clear_events(&mut self) {}
+}
+
+impl<Pallet> DefaultEvents for Pallet {}
+
+/// Logic which upgrades the state of the runtime, when the shape of the storage of pallets changes.
+///
+/// Migrations are given to `#[macros::runtime(migrations = ..)]`, which can be a tuple of
+/// migrations. The runtime runs them in order in `Runtime::upgrade`, each one with its
+/// `pre_upgrade`, `migrate` and `post_upgrade` hooks. If any of the checks fails, the whole upgrade
+/// is reverted.
+pub trait OnRuntimeUpgrade<Runtime> {
+	/// Check the state before the migration, and return anything `post_upgrade` needs to check the
+	/// result of the migration, encoded.
+	fn pre_upgrade(_runtime: &Runtime) -> Result<Vec<u8>, DispatchError> {
+		Ok(Vec::new())
+	}
+
+	/// Upgrade the state of the `runtime`.
+	fn migrate(runtime: &mut Runtime);
+
+	/// Check the state after the migration, given the `state` returned by `pre_upgrade`.
+	fn post_upgrade(_runtime: &Runtime, _state: Vec<u8>) -> DispatchResult {
+		Ok(())
+	}
+
+	/// Run `pre_upgrade`, `migrate` and `post_upgrade` in order.
+	fn try_upgrade(runtime: &mut Runtime) -> DispatchResult {
+		let state = Self::pre_upgrade(runtime)?;
+		Self::migrate(runtime);
+		Self::post_upgrade(runtime, state)
+	}
+}
+
+impl<Runtime> OnRuntimeUpgrade<Runtime> for () {
+	fn migrate(_runtime: &mut Runtime) {}
+}
+
+// A tuple of migrations runs them one after the other, so every migration is checked against the
+// state left by the previous ones.
+macro_rules! impl_on_runtime_upgrade_for_tuples {
+	($( ($($migration:ident),+) ),* $(,)?) => {
+		$(
+			impl<Runtime, $($migration: OnRuntimeUpgrade<Runtime>),+> OnRuntimeUpgrade<Runtime>
+				for ($($migration,)+)
+			{
+				fn migrate(runtime: &mut Runtime) {
+					$( $migration::migrate(runtime); )+
+				}
+
+				fn try_upgrade(runtime: &mut Runtime) -> DispatchResult {
+					$( $migration::try_upgrade(runtime)?; )+
+					Ok(())
+				}
+			}
+		)*
+	};
+}
+
+impl_on_runtime_upgrade_for_tuples!((A), (A, B), (A, B, C), (A, B, C, D), (A, B, C, D, E));
+
+/// Run the migration `M` on a copy of `snapshot`, the state of a runtime before an upgrade, and
+/// return the upgraded copy.
+///
+/// This is meant to test migrations: besides the checks of the migration itself, the migration is
+/// run a second time, which must leave the encoded storage untouched. Tests can then check any
+/// other invariant on the upgraded state.
+#[cfg(test)]
+pub fn try_runtime_upgrade<Runtime, M>(snapshot: &Runtime) -> Result<Runtime, DispatchError>
+where
+	Runtime: Clone + storage::RuntimeStorage,
+	M: OnRuntimeUpgrade<Runtime>,
+{
+	let mut runtime = snapshot.clone();
+	M::try_upgrade(&mut runtime)?;
+
+	let upgraded = runtime.storage_entries();
+	M::try_upgrade(&mut runtime)?;
+	if runtime.storage_entries() != upgraded {
+		return Err("Migration changed the state again".into());
+	}
+
+	Ok(runtime)
+}
+
+/// A filter on the calls dispatched by the runtime, given to `#[macros::runtime(call_filter = ..)]`.
+///
+/// The filter is consulted for every call, including the calls dispatched by other calls, like
+/// batches or scheduled calls, except for the calls made by the `Root` origin, so governance can
+/// always fix the runtime. It is given the runtime, so it can depend on the state of pallets.
+pub trait CallFilter<Runtime, Call> {
+	/// Whether `call` can be dispatched.
+	fn allows(runtime: &Runtime, call: &Call) -> bool;
+
+	/// Check that `call` can be dispatched, returning the error of the call otherwise. Filters can
+	/// override this to explain why a call is rejected.
+	fn check(runtime: &Runtime, call: &Call) -> DispatchResult {
+		if Self::allows(runtime, call) {
+			Ok(())
+		} else {
+			Err(DispatchError::Filtered)
+		}
+	}
+}
+
+/// A filter which allows every call, which is the default filter of the runtime.
+// The runtime of this example filters its calls through the safe mode pallet instead.
+#[allow(dead_code)]
+pub struct Everything;
+
+impl<Runtime, Call> CallFilter<Runtime, Call> for Everything {
+	fn allows(_runtime: &Runtime, _call: &Call) -> bool {
+		true
+	}
+}
+
+/// A trait which gives the names of a call, for example to filter calls by name.
+///
+/// `#[macros::runtime]` implements this for the `RuntimeCall` enum, where the name of the pallet is
+/// the name of its field in the runtime, like `balances`, and the name of the call is the name of
+/// its function, like `transfer`.
+pub trait GetCallName {
+	/// Get the name of the pallet of the call.
+	fn pallet_name(&self) -> &'static str;
+	/// Get the name of the call in its pallet.
+	fn call_name(&self) -> &'static str;
+	/// Get the names of the pallet and of the call of every call of the runtime.
+	fn call_names() -> Vec<(&'static str, &'static str)>;
+}
+
+/// A handler for funds which were removed from an account without being deposited anywhere else,
+/// like fees or slashes.
+///
+/// The unit type simply drops the funds, which burns them.
+pub trait OnUnbalanced<Runtime, Balance> {
+	/// Handle `amount`, which was removed from an account.
+	fn on_unbalanced(_runtime: &mut Runtime, _amount: Balance) {}
+}
+
+impl<Runtime, Balance> OnUnbalanced<Runtime, Balance> for () {}
+
+/// A check of the author of every block, before the block is executed.
+///
+/// The unit type allows any account to author blocks.
+pub trait ValidateAuthor<Runtime, AccountId> {
+	/// Check that `author` is allowed to author the next block of the `runtime`.
+	fn validate_author(_runtime: &Runtime, _author: &AccountId) -> DispatchResult {
+		Ok(())
+	}
+}
+
+impl<Runtime, AccountId> ValidateAuthor<Runtime, AccountId> for () {}
+
+/// A way to charge a fee from the caller of every extrinsic, before it is dispatched.
+///
+/// The unit type charges no fee.
+pub trait ChargeFee<Runtime, AccountId, Call> {
+	/// Charge the fee of `call` from `who`. The extrinsic is not dispatched if this fails.
+	fn charge_fee(_runtime: &mut Runtime, _who: &AccountId, _call: &Call) -> DispatchResult {
+		Ok(())
+	}
+}
+
+impl<Runtime, AccountId, Call> ChargeFee<Runtime, AccountId, Call> for () {}
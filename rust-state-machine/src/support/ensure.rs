@@ -0,0 +1,170 @@
+//! Helpers which check who is making a call, and whether they are allowed to make it.
+//!
+//! Calls are dispatched with a typed `RawOrigin`. Extrinsics are always dispatched with the
+//! `Signed` origin of the account which submitted them, so only the runtime itself, like the
+//! democracy pallet enacting a proposal, can dispatch a call with the `Root` origin. The helpers of
+//! this module check that a call is made by the origin it expects.
+
+use crate::support::storage::Encode;
+
+/// The origin of a call.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RawOrigin<AccountId> {
+	/// The call is made by the runtime itself, through governance or another privileged pallet.
+	Root,
+	/// The call is made by an account.
+	Signed(AccountId),
+}
+
+/// An origin is encoded as the index of its variant, followed by the account of a signed origin.
+impl<AccountId: Encode> Encode for RawOrigin<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			RawOrigin::Root => dest.push(0),
+			RawOrigin::Signed(who) => {
+				dest.push(1);
+				who.encode_to(dest);
+			},
+		}
+	}
+}
+
+/// The origin of a call, as configured for the runtime.
+pub type OriginFor<T> = RawOrigin<<T as crate::system::Config>::AccountId>;
+
+/// The errors returned when a call is made by the wrong origin. Calls fail with
+/// `DispatchError::BadOrigin` for both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OriginError {
+	/// The call can only be made by the `Root` origin.
+	NotRoot,
+	/// The call can only be made by an account.
+	NotSigned,
+}
+
+/// Check that `origin` is an account rather than the `Root` origin, and return the account.
+pub fn ensure_signed<AccountId>(origin: RawOrigin<AccountId>) -> Result<AccountId, OriginError> {
+	match origin {
+		RawOrigin::Signed(who) => Ok(who),
+		RawOrigin::Root => Err(OriginError::NotSigned),
+	}
+}
+
+/// Check that `origin` is the `Root` origin.
+pub fn ensure_root<AccountId>(origin: RawOrigin<AccountId>) -> Result<(), OriginError> {
+	match origin {
+		RawOrigin::Root => Ok(()),
+		RawOrigin::Signed(_) => Err(OriginError::NotRoot),
+	}
+}
+
+/// Return `error` from the current function unless `condition` holds.
+///
+/// The error is converted with `Into`, so typed errors like `OriginError` can be used in calls which
+/// return a `DispatchResult`:
+///
+/// ```ignore
+/// ensure!(owner == &caller, "Caller is not the owner of the claim");
+/// ```
+macro_rules! ensure {
+	($condition:expr, $error:expr $(,)?) => {
+		if !$condition {
+			return Err($error.into());
+		}
+	};
+}
+
+pub(crate) use ensure;
+
+#[cfg(test)]
+mod tests {
+	use super::{ensure_root, ensure_signed, OriginError, RawOrigin};
+	use crate::support::{self, CallFilter};
+	use crate::{balances, types, Runtime, RuntimeCall};
+
+	// A runtime whose filter blocks transfers, as if they were paused during an incident.
+	type FilteredBlock =
+		support::Block<support::Header<u32, String>, support::Extrinsic<String, FilteredCall>>;
+
+	#[macros::runtime(block = FilteredBlock, system = crate::system, call = FilteredCall, call_filter = NoTransfers)]
+	#[derive(Debug, Clone)]
+	pub struct FilteredRuntime {
+		system: crate::system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+	}
+
+	impl crate::system::Config for FilteredRuntime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl balances::Config for FilteredRuntime {
+		type Balance = u128;
+	}
+
+	pub struct NoTransfers;
+
+	impl CallFilter<FilteredRuntime, FilteredCall> for NoTransfers {
+		fn allows(_runtime: &FilteredRuntime, call: &FilteredCall) -> bool {
+			!matches!(call, FilteredCall::balances(balances::Call::transfer { .. }))
+		}
+	}
+
+	#[test]
+	fn origins_are_checked() {
+		let alice = "alice".to_string();
+		assert_eq!(ensure_signed(RawOrigin::Signed(alice.clone())), Ok(alice.clone()));
+		assert_eq!(ensure_signed(RawOrigin::<String>::Root), Err(OriginError::NotSigned));
+		assert_eq!(ensure_root(RawOrigin::<String>::Root), Ok(()));
+
+		let check = |origin: RawOrigin<String>| -> support::DispatchResult {
+			ensure_root(origin)?;
+			Ok(())
+		};
+		assert_eq!(check(RawOrigin::Signed(alice)), Err(support::DispatchError::BadOrigin));
+	}
+
+	#[test]
+	fn extrinsics_cannot_be_root() {
+		use support::Dispatch;
+
+		let mut runtime = Runtime::new();
+		let root = "root".to_string();
+		runtime.balances.set_balance(&root, 10);
+
+		// An account named like the root origin is just another signed account, which pays the fee.
+		let call = RuntimeCall::balances(balances::Call::force_set_balance { who: root.clone(), amount: 1000 });
+		let extrinsic = support::Extrinsic { caller: root.clone(), call };
+		let block: types::Block = support::Block { header: support::Header { block_number: 1, author: "alice".to_string() }, extrinsics: vec![extrinsic] };
+		runtime.execute_block(block).unwrap();
+		assert_eq!(runtime.balances.balance(&root), 8);
+		let call = balances::Call::force_set_balance { who: root.clone(), amount: 1000 };
+		assert_eq!(runtime.dispatch(RawOrigin::Signed(root), RuntimeCall::balances(call)), Err(support::DispatchError::BadOrigin));
+	}
+
+	#[test]
+	fn call_filter_blocks_calls() {
+		use support::Dispatch;
+
+		let mut runtime = FilteredRuntime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		runtime.balances.set_balance(&alice, 100);
+
+		let transfer = FilteredCall::balances(balances::Call::transfer { to: bob.clone(), amount: 30 });
+		assert_eq!(runtime.dispatch(RawOrigin::Signed(alice.clone()), transfer.clone()), Err(support::DispatchError::Filtered));
+		let signed_as_root = RawOrigin::Signed("root".to_string());
+		assert_eq!(runtime.dispatch(signed_as_root, transfer.clone()), Err(support::DispatchError::Filtered));
+		let extrinsic = support::Extrinsic { caller: alice.clone(), call: transfer.clone() };
+		let block: FilteredBlock = support::Block { header: support::Header { block_number: 1, author: "alice".to_string() }, extrinsics: vec![extrinsic] };
+		runtime.execute_block(block).unwrap();
+		assert_eq!(runtime.balances.balance(&alice), 100);
+
+		// Calls made by `Root` bypass the filter, and other calls are still dispatched. The transfer
+		// then fails in the pallet, since it needs a signed origin.
+		assert_eq!(runtime.dispatch(RawOrigin::Root, transfer), Err(support::DispatchError::BadOrigin));
+		let set_balance = balances::Call::force_set_balance { who: bob.clone(), amount: 10 };
+		runtime.dispatch(RawOrigin::Root, FilteredCall::balances(set_balance)).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), 10);
+	}
+}
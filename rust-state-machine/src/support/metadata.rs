@@ -0,0 +1,167 @@
+//! A machine-readable description of the runtime, which clients like wallets and CLI tools can use
+//! to build calls and read events and storage without being written against `RuntimeCall` by hand.
+//!
+//! The macros generate the metadata of each pallet: `#[macros::call]` describes its calls and the
+//! errors they can return, `#[macros::storage]` its storage items, and `#[macros::event]` its events.
+//! `#[macros::runtime]` then gathers the metadata of every pallet in `Runtime::metadata()`.
+
+use serde::Serialize;
+
+/// The description of the whole runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuntimeMetadata {
+	/// The pallets of the runtime, ordered by index.
+	pub pallets: Vec<PalletMetadata>,
+}
+
+impl RuntimeMetadata {
+	/// Get the metadata of the pallet named `name`, if any.
+	pub fn pallet(&self, name: &str) -> Option<&PalletMetadata> {
+		self.pallets.iter().find(|pallet| pallet.name == name)
+	}
+
+	/// Serialize the metadata to JSON.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).expect("metadata only contains strings and integers; qed")
+	}
+}
+
+/// The description of a pallet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PalletMetadata {
+	/// The name of the pallet, which is the name of its field in the runtime.
+	pub name: &'static str,
+	/// The index of the pallet in the runtime.
+	pub index: u8,
+	/// The calls of the pallet, ordered by index.
+	pub calls: Vec<CallMetadata>,
+	/// The storage items of the pallet.
+	pub storage: Vec<StorageEntryMetadata>,
+	/// The events the pallet emits.
+	pub events: Vec<EventMetadata>,
+	/// The errors the calls of the pallet can return, as declared with `#[macros::call(errors(..))]`.
+	/// The errors of the calls dispatched by another call, like the calls of a batch, are not
+	/// included, nor the errors every call can return, like `DispatchError::BadOrigin`.
+	pub errors: Vec<&'static str>,
+}
+
+/// The description of a call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CallMetadata {
+	/// The name of the call.
+	pub name: &'static str,
+	/// The index of the call in its pallet.
+	pub index: u8,
+	/// The arguments of the call, not including the caller.
+	pub args: Vec<FieldMetadata>,
+	/// The doc comments of the call, one line per item.
+	pub docs: Vec<&'static str>,
+}
+
+/// The description of a named and typed value, like an argument of a call or a field of an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldMetadata {
+	/// The name of the field. Fields of tuple variants are named after their position.
+	pub name: &'static str,
+	/// The full name of the type of the field, as configured in the runtime.
+	#[serde(rename = "type")]
+	pub ty: &'static str,
+}
+
+/// The kinds of storage items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StorageKind {
+	/// A single value.
+	Value,
+	/// A map from a key to a value.
+	Map,
+	/// A map from two keys to a value.
+	DoubleMap,
+}
+
+/// The description of a storage item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageEntryMetadata {
+	/// The name of the item.
+	pub name: &'static str,
+	/// The kind of item.
+	pub kind: StorageKind,
+	/// The types of the keys of the item, in order.
+	pub keys: Vec<&'static str>,
+	/// The type of the values of the item.
+	pub value: &'static str,
+	/// Whether queries return a default value rather than `None` when nothing is stored.
+	pub has_default: bool,
+	/// The doc comments of the item, one line per item.
+	pub docs: Vec<&'static str>,
+}
+
+/// The description of an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EventMetadata {
+	/// The name of the event.
+	pub name: &'static str,
+	/// The fields of the event.
+	pub fields: Vec<FieldMetadata>,
+	/// The doc comments of the event, one line per item.
+	pub docs: Vec<&'static str>,
+}
+
+/// Empty metadata, for the pallets which do not use some of the macros.
+///
+/// `#[macros::runtime]` calls these functions on every pallet, with this trait in scope. Since
+/// inherent functions take precedence over the functions of a trait, the functions generated by
+/// the macros of a pallet are used when they exist, and these ones otherwise.
+pub trait DefaultMetadata {
+	fn call_metadata() -> Vec<CallMetadata> {
+		Vec::new()
+	}
+
+	fn event_metadata() -> Vec<EventMetadata> {
+		Vec::new()
+	}
+
+	fn error_metadata() -> Vec<&'static str> {
+		Vec::new()
+	}
+}
+
+impl<Pallet> DefaultMetadata for Pallet {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn runtime_describes_its_pallets() {
+		let metadata = crate::Runtime::metadata();
+		assert_eq!(metadata.pallets[0].name, "system");
+
+		let balances = metadata.pallet("balances").unwrap();
+		assert_eq!(balances.index, 1);
+		let transfer = &balances.calls[0];
+		assert_eq!(transfer.name, "transfer");
+		assert_eq!(
+			transfer.args,
+			vec![
+				FieldMetadata { name: "to", ty: "alloc::string::String" },
+				FieldMetadata { name: "amount", ty: "u128" },
+			]
+		);
+		assert_eq!(transfer.docs[0], "Transfer `amount` from one account to another.");
+		let call = crate::balances::Call::force_set_balance { who: "alice".to_string(), amount: 1 };
+		assert_eq!(call.call_index(), balances.calls[1].index);
+		assert_eq!(crate::RuntimeCall::balances(call).pallet_index(), balances.index);
+		assert!(balances.errors.contains(&"Not enough funds."));
+		assert!(balances.storage.iter().any(|item| item.name == "locks" && item.kind == StorageKind::DoubleMap));
+
+		let htlc = metadata.pallet("htlc").unwrap();
+		assert_eq!(htlc.events[0].name, "Created");
+		assert!(htlc.errors.contains(&"Not enough reserved funds."));
+		assert!(htlc.storage.iter().any(|item| item.name == "swaps" && item.kind == StorageKind::DoubleMap));
+		assert!(metadata.to_json().contains("\"name\": \"claim_swap\""));
+
+		let proof_of_existence = metadata.pallet("proof_of_existence").unwrap();
+		assert!(proof_of_existence.errors.contains(&"Claim already exists"));
+	}
+}
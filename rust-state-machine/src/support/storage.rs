@@ -0,0 +1,625 @@
+//! The storage items which pallets declare with `#[macros::storage]`.
+//!
+//! Each item keeps its values in memory, like the `BTreeMap`s pallets used to hand-roll, but also
+//! knows the name of its pallet and its own name. Together they form a prefix which is unique to the
+//! item, so every value of the runtime can be given a unique key, as if it was stored in a single
+//! key-value database. `StorageItem::entries` exposes the values with these keys, which is what we
+//! would persist to disk, or build a Merkle trie from.
+
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use std::collections::BTreeMap;
+use crate::support::{DispatchError, DispatchResult, GetPallet, OnRuntimeUpgrade};
+
+/// A type which can be encoded into bytes, to build the keys and values of the storage.
+///
+/// Like SCALE, integers are encoded in little endian, and collections are prefixed with their
+/// length.
+pub trait Encode {
+	/// Append the encoding of `self` to `dest`.
+	fn encode_to(&self, dest: &mut Vec<u8>);
+
+	/// Get the encoding of `self`.
+	fn encode(&self) -> Vec<u8> {
+		let mut dest = Vec::new();
+		self.encode_to(&mut dest);
+		dest
+	}
+}
+
+macro_rules! impl_encode_for_integers {
+	($($ty:ty),*) => {
+		$(impl Encode for $ty {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				dest.extend_from_slice(&self.to_le_bytes());
+			}
+		})*
+	};
+}
+
+impl_encode_for_integers!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Encode for () {
+	fn encode_to(&self, _dest: &mut Vec<u8>) {}
+}
+
+impl Encode for bool {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(*self as u8);
+	}
+}
+
+impl Encode for str {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(self.len() as u32).encode_to(dest);
+		dest.extend_from_slice(self.as_bytes());
+	}
+}
+
+impl Encode for String {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_str().encode_to(dest);
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest);
+	}
+}
+
+impl<T: Encode> Encode for [T] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(self.len() as u32).encode_to(dest);
+		self.iter().for_each(|item| item.encode_to(dest));
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_slice().encode_to(dest);
+	}
+}
+
+/// Arrays have a fixed length, so it is not encoded.
+impl<T: Encode, const N: usize> Encode for [T; N] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.iter().for_each(|item| item.encode_to(dest));
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest);
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			None => dest.push(0),
+			Some(value) => {
+				dest.push(1);
+				value.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+		self.1.encode_to(dest);
+	}
+}
+
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+		self.1.encode_to(dest);
+		self.2.encode_to(dest);
+	}
+}
+
+/// What a storage item returns when it is queried for a value.
+pub trait QueryKind<V> {
+	/// What the item needs to build its default value, if it has one.
+	type Default: Copy;
+	/// The type returned by a query.
+	type Query;
+
+	/// Turn the stored value (if any) into the result of a query.
+	fn from_stored(stored: Option<&V>, default: Self::Default) -> Self::Query;
+	/// Turn the result of a query back into the value (if any) to store.
+	fn into_stored(query: Self::Query) -> Option<V>;
+}
+
+/// Queries return `None` when no value is stored.
+#[derive(Debug, Clone, Copy)]
+pub struct OptionQuery;
+
+impl<V: Clone> QueryKind<V> for OptionQuery {
+	type Default = ();
+	type Query = Option<V>;
+
+	fn from_stored(stored: Option<&V>, _default: ()) -> Option<V> {
+		stored.cloned()
+	}
+
+	fn into_stored(query: Option<V>) -> Option<V> {
+		query
+	}
+}
+
+/// Queries return a default value when no value is stored. This is what `#[default(..)]` selects.
+#[derive(Debug, Clone, Copy)]
+pub struct ValueQuery;
+
+impl<V: Clone> QueryKind<V> for ValueQuery {
+	type Default = fn() -> V;
+	type Query = V;
+
+	fn from_stored(stored: Option<&V>, default: fn() -> V) -> V {
+		stored.cloned().unwrap_or_else(default)
+	}
+
+	fn into_stored(query: V) -> Option<V> {
+		Some(query)
+	}
+}
+
+/// The names which identify a storage item in the runtime.
+#[derive(Debug, Clone, Copy)]
+pub struct StoragePrefix {
+	/// The module path of the pallet, as given by `module_path!()`.
+	pub pallet: &'static str,
+	/// The prefix of the instance of the pallet, which is empty for the default instance.
+	pub instance: &'static str,
+	/// The name of the storage item in its pallet.
+	pub item: &'static str,
+}
+
+impl StoragePrefix {
+	/// The name of the pallet, which is the last segment of its module path.
+	pub fn pallet_name(&self) -> &'static str {
+		self.pallet.rsplit("::").next().unwrap_or(self.pallet)
+	}
+
+	/// The prefix of all the keys of the item. Instances other than the default one also include
+	/// their own prefix, so their keys do not collide with the ones of the default instance.
+	pub fn key(&self) -> Vec<u8> {
+		if self.instance.is_empty() {
+			(self.pallet_name(), self.item).encode()
+		} else {
+			(self.pallet_name(), self.instance, self.item).encode()
+		}
+	}
+}
+
+/// The version of the storage of a pallet.
+///
+/// It is increased whenever the shape of the storage of the pallet changes, and recorded in the
+/// storage itself, so migrations can tell whether the state was already upgraded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StorageVersion(u16);
+
+impl StorageVersion {
+	/// Create a new storage version.
+	pub const fn new(version: u16) -> Self {
+		Self(version)
+	}
+}
+
+impl Encode for StorageVersion {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+	}
+}
+
+/// Access to the storage version of a pallet.
+///
+/// `#[macros::storage]` implements this for every pallet, and records the version in a
+/// `storage_version` item.
+pub trait GetStorageVersion {
+	/// The version of the storage declared in the code of the pallet.
+	#[allow(dead_code)]
+	fn current_storage_version() -> StorageVersion;
+	/// The version of the storage as recorded in the state. It is behind the current version until
+	/// the state is migrated.
+	fn on_chain_storage_version(&self) -> StorageVersion;
+	/// Record the version of the storage in the state.
+	fn set_on_chain_storage_version(&mut self, version: StorageVersion);
+}
+
+/// A migration which only runs `Inner` if the on-chain storage version of `Pallet` is `FROM`, and
+/// then records `TO` as the new version. Otherwise, the state was already upgraded and it does
+/// nothing.
+pub struct VersionedMigration<const FROM: u16, const TO: u16, Inner, Pallet>(PhantomData<(Inner, Pallet)>);
+
+impl<const FROM: u16, const TO: u16, Inner, Pallet> VersionedMigration<FROM, TO, Inner, Pallet> {
+	/// Whether the migration needs to run on `runtime`.
+	fn should_run<Runtime: GetPallet<Pallet>>(runtime: &Runtime) -> bool
+	where
+		Pallet: GetStorageVersion,
+	{
+		runtime.pallet().on_chain_storage_version() == StorageVersion::new(FROM)
+	}
+}
+
+impl<Runtime, const FROM: u16, const TO: u16, Inner, Pallet> OnRuntimeUpgrade<Runtime>
+	for VersionedMigration<FROM, TO, Inner, Pallet>
+where
+	Runtime: GetPallet<Pallet>,
+	Inner: OnRuntimeUpgrade<Runtime>,
+	Pallet: GetStorageVersion,
+{
+	fn pre_upgrade(runtime: &Runtime) -> Result<Vec<u8>, DispatchError> {
+		// We prefix the state of `Inner` with whether it runs, for `post_upgrade`.
+		if Self::should_run(runtime) {
+			Ok([vec![1], Inner::pre_upgrade(runtime)?].concat())
+		} else {
+			Ok(vec![0])
+		}
+	}
+
+	fn migrate(runtime: &mut Runtime) {
+		if Self::should_run(runtime) {
+			Inner::migrate(runtime);
+			runtime.pallet_mut().set_on_chain_storage_version(StorageVersion::new(TO));
+		}
+	}
+
+	fn post_upgrade(runtime: &Runtime, state: Vec<u8>) -> DispatchResult {
+		match state.split_first() {
+			Some((1, inner_state)) => {
+				if runtime.pallet().on_chain_storage_version() != StorageVersion::new(TO) {
+					return Err("Storage version was not updated".into());
+				}
+				Inner::post_upgrade(runtime, inner_state.to_vec())
+			},
+			_ => Ok(()),
+		}
+	}
+}
+
+/// Access to all the values of a storage item with their full storage keys.
+pub trait StorageItem {
+	/// Get the names identifying the item.
+	fn prefix(&self) -> StoragePrefix;
+	/// Get the encoded `(key, value)` of every value stored in the item, ordered by key.
+	fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)>;
+}
+
+/// Access to the storage of every pallet of a runtime, implemented by `#[macros::runtime]`. Tests
+/// compare whole states with it, for example to check that a migration only runs once.
+#[cfg(test)]
+pub trait RuntimeStorage {
+	/// Get the encoded `(key, value)` of every value in the storage of every pallet, ordered by key.
+	fn storage_entries(&self) -> Vec<(Vec<u8>, Vec<u8>)>;
+}
+
+/// A single value.
+#[derive(Clone)]
+pub struct StorageValue<V, Q: QueryKind<V> = OptionQuery> {
+	prefix: StoragePrefix,
+	default: Q::Default,
+	value: Option<V>,
+}
+
+impl<V, Q: QueryKind<V>> StorageValue<V, Q> {
+	/// Create a new empty storage value.
+	pub fn new(prefix: StoragePrefix, default: Q::Default) -> Self {
+		Self { prefix, default, value: None }
+	}
+
+	/// Get the value, or the result of an empty query if no value is stored.
+	pub fn get(&self) -> Q::Query {
+		Q::from_stored(self.value.as_ref(), self.default)
+	}
+
+	/// Get a reference to the stored value, if any.
+	pub fn try_get(&self) -> Option<&V> {
+		self.value.as_ref()
+	}
+
+	/// Whether a value is stored.
+	pub fn exists(&self) -> bool {
+		self.value.is_some()
+	}
+
+	/// Store `value`.
+	pub fn put(&mut self, value: V) {
+		self.value = Some(value);
+	}
+
+	/// Mutate the value with `f`, and return its result.
+	pub fn mutate<R>(&mut self, f: impl FnOnce(&mut Q::Query) -> R) -> R {
+		let mut query = Q::from_stored(self.value.as_ref(), self.default);
+		let result = f(&mut query);
+		self.value = Q::into_stored(query);
+		result
+	}
+
+	/// Mutate the value with `f`, keeping the change only if `f` returns `Ok`.
+	pub fn try_mutate<R, E>(&mut self, f: impl FnOnce(&mut Q::Query) -> Result<R, E>) -> Result<R, E> {
+		let mut query = Q::from_stored(self.value.as_ref(), self.default);
+		let result = f(&mut query)?;
+		self.value = Q::into_stored(query);
+		Ok(result)
+	}
+
+	/// Remove the value, and return it.
+	pub fn kill(&mut self) -> Option<V> {
+		self.value.take()
+	}
+}
+
+impl<V: Debug, Q: QueryKind<V>> Debug for StorageValue<V, Q> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		self.value.fmt(f)
+	}
+}
+
+impl<V: Encode, Q: QueryKind<V>> StorageItem for StorageValue<V, Q> {
+	fn prefix(&self) -> StoragePrefix {
+		self.prefix
+	}
+
+	fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+		self.value.iter().map(|value| (self.prefix.key(), value.encode())).collect()
+	}
+}
+
+/// A map from keys to values.
+#[derive(Clone)]
+pub struct StorageMap<K, V, Q: QueryKind<V> = OptionQuery> {
+	prefix: StoragePrefix,
+	default: Q::Default,
+	map: BTreeMap<K, V>,
+}
+
+impl<K: Ord, V, Q: QueryKind<V>> StorageMap<K, V, Q> {
+	/// Create a new empty storage map.
+	pub fn new(prefix: StoragePrefix, default: Q::Default) -> Self {
+		Self { prefix, default, map: BTreeMap::new() }
+	}
+
+	/// Get the value of `key`, or the result of an empty query if no value is stored.
+	pub fn get(&self, key: &K) -> Q::Query {
+		Q::from_stored(self.map.get(key), self.default)
+	}
+
+	/// Get a reference to the stored value of `key`, if any.
+	pub fn try_get(&self, key: &K) -> Option<&V> {
+		self.map.get(key)
+	}
+
+	/// Whether a value is stored for `key`.
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.map.contains_key(key)
+	}
+
+	/// Store `value` for `key`.
+	pub fn insert(&mut self, key: K, value: V) {
+		self.map.insert(key, value);
+	}
+
+	/// Mutate the value of `key` with `f`, and return its result.
+	pub fn mutate<R>(&mut self, key: K, f: impl FnOnce(&mut Q::Query) -> R) -> R {
+		let mut query = Q::from_stored(self.map.get(&key), self.default);
+		let result = f(&mut query);
+		self.set(key, Q::into_stored(query));
+		result
+	}
+
+	/// Mutate the value of `key` with `f`, keeping the change only if `f` returns `Ok`.
+	pub fn try_mutate<R, E>(&mut self, key: K, f: impl FnOnce(&mut Q::Query) -> Result<R, E>) -> Result<R, E> {
+		let mut query = Q::from_stored(self.map.get(&key), self.default);
+		let result = f(&mut query)?;
+		self.set(key, Q::into_stored(query));
+		Ok(result)
+	}
+
+	/// Remove the value of `key`, and return it.
+	pub fn remove(&mut self, key: &K) -> Option<V> {
+		self.map.remove(key)
+	}
+
+	/// Iterate over the stored keys and values, ordered by key.
+	pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+		self.map.iter()
+	}
+
+	/// Store `value` for `key`, or remove the value of `key` if there is none.
+	fn set(&mut self, key: K, value: Option<V>) {
+		match value {
+			Some(value) => self.map.insert(key, value),
+			None => self.map.remove(&key),
+		};
+	}
+}
+
+impl<K: Debug, V: Debug, Q: QueryKind<V>> Debug for StorageMap<K, V, Q> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		self.map.fmt(f)
+	}
+}
+
+impl<K: Encode, V: Encode, Q: QueryKind<V>> StorageItem for StorageMap<K, V, Q> {
+	fn prefix(&self) -> StoragePrefix {
+		self.prefix
+	}
+
+	fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+		let prefix = self.prefix.key();
+		let mut entries = self
+			.map
+			.iter()
+			.map(|(key, value)| ([prefix.clone(), key.encode()].concat(), value.encode()))
+			.collect::<Vec<_>>();
+		// Encoded keys are not necessarily ordered like the keys themselves.
+		entries.sort();
+		entries
+	}
+}
+
+/// A map from pairs of keys to values, which can also be iterated by the first key.
+#[derive(Clone)]
+pub struct StorageDoubleMap<K1, K2, V, Q: QueryKind<V> = OptionQuery> {
+	prefix: StoragePrefix,
+	default: Q::Default,
+	map: BTreeMap<K1, BTreeMap<K2, V>>,
+}
+
+impl<K1: Ord + Clone, K2: Ord, V, Q: QueryKind<V>> StorageDoubleMap<K1, K2, V, Q> {
+	/// Create a new empty storage double map.
+	pub fn new(prefix: StoragePrefix, default: Q::Default) -> Self {
+		Self { prefix, default, map: BTreeMap::new() }
+	}
+
+	/// Get the value of `(key1, key2)`, or the result of an empty query if no value is stored.
+	pub fn get(&self, key1: &K1, key2: &K2) -> Q::Query {
+		Q::from_stored(self.try_get(key1, key2), self.default)
+	}
+
+	/// Get a reference to the stored value of `(key1, key2)`, if any.
+	pub fn try_get(&self, key1: &K1, key2: &K2) -> Option<&V> {
+		self.map.get(key1).and_then(|map| map.get(key2))
+	}
+
+	/// Whether a value is stored for `(key1, key2)`.
+	pub fn contains_key(&self, key1: &K1, key2: &K2) -> bool {
+		self.try_get(key1, key2).is_some()
+	}
+
+	/// Store `value` for `(key1, key2)`.
+	pub fn insert(&mut self, key1: K1, key2: K2, value: V) {
+		self.map.entry(key1).or_default().insert(key2, value);
+	}
+
+	/// Mutate the value of `(key1, key2)` with `f`, and return its result.
+	pub fn mutate<R>(&mut self, key1: K1, key2: K2, f: impl FnOnce(&mut Q::Query) -> R) -> R {
+		let mut query = Q::from_stored(self.try_get(&key1, &key2), self.default);
+		let result = f(&mut query);
+		self.set(key1, key2, Q::into_stored(query));
+		result
+	}
+
+	/// Mutate the value of `(key1, key2)` with `f`, keeping the change only if `f` returns `Ok`.
+	pub fn try_mutate<R, E>(
+		&mut self,
+		key1: K1,
+		key2: K2,
+		f: impl FnOnce(&mut Q::Query) -> Result<R, E>,
+	) -> Result<R, E> {
+		let mut query = Q::from_stored(self.try_get(&key1, &key2), self.default);
+		let result = f(&mut query)?;
+		self.set(key1, key2, Q::into_stored(query));
+		Ok(result)
+	}
+
+	/// Remove the value of `(key1, key2)`, and return it.
+	pub fn remove(&mut self, key1: &K1, key2: &K2) -> Option<V> {
+		let map = self.map.get_mut(key1)?;
+		let value = map.remove(key2);
+		if map.is_empty() {
+			self.map.remove(key1);
+		}
+		value
+	}
+
+	/// Iterate over the stored keys and values, ordered by keys.
+	pub fn iter(&self) -> impl Iterator<Item = (&K1, &K2, &V)> {
+		self.map.iter().flat_map(|(key1, map)| map.iter().map(move |(key2, value)| (key1, key2, value)))
+	}
+
+	/// Iterate over the second keys and values stored under `key1`, ordered by key.
+	pub fn iter_prefix(&self, key1: &K1) -> impl Iterator<Item = (&K2, &V)> {
+		self.map.get(key1).into_iter().flat_map(|map| map.iter())
+	}
+
+	/// Store `value` for `(key1, key2)`, or remove the value of `(key1, key2)` if there is none.
+	fn set(&mut self, key1: K1, key2: K2, value: Option<V>) {
+		match value {
+			Some(value) => self.insert(key1, key2, value),
+			None => {
+				self.remove(&key1, &key2);
+			},
+		}
+	}
+}
+
+impl<K1: Debug, K2: Debug, V: Debug, Q: QueryKind<V>> Debug for StorageDoubleMap<K1, K2, V, Q> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		self.map.fmt(f)
+	}
+}
+
+impl<K1: Encode, K2: Encode, V: Encode, Q: QueryKind<V>> StorageItem for StorageDoubleMap<K1, K2, V, Q> {
+	fn prefix(&self) -> StoragePrefix {
+		self.prefix
+	}
+
+	fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+		let prefix = self.prefix.key();
+		let mut entries = self
+			.map
+			.iter()
+			.flat_map(|(key1, map)| {
+				let prefix = [prefix.clone(), key1.encode()].concat();
+				map.iter().map(move |(key2, value)| ([prefix.clone(), key2.encode()].concat(), value.encode()))
+			})
+			.collect::<Vec<_>>();
+		// Encoded keys are not necessarily ordered like the keys themselves.
+		entries.sort();
+		entries
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn prefix(item: &'static str) -> StoragePrefix {
+		StoragePrefix { pallet: "rust_state_machine::example", instance: "", item }
+	}
+
+	#[test]
+	fn queries_and_mutations() {
+		let mut value = StorageValue::<u32, ValueQuery>::new(prefix("value"), || 7);
+		assert_eq!(value.get(), 7);
+		assert!(!value.exists());
+		value.mutate(|value| *value += 1);
+		assert_eq!(value.try_get(), Some(&8));
+
+		let mut map = StorageMap::<String, u32>::new(prefix("map"), ());
+		assert_eq!(map.get(&"alice".to_string()), None);
+		map.insert("alice".to_string(), 1);
+		let result: Result<(), &str> = map.try_mutate("alice".to_string(), |value| {
+			*value = None;
+			Err("Reverted")
+		});
+		assert_eq!(result, Err("Reverted"));
+		assert_eq!(map.get(&"alice".to_string()), Some(1));
+		map.mutate("alice".to_string(), |value| *value = None);
+		assert!(!map.contains_key(&"alice".to_string()));
+
+		let mut double_map = StorageDoubleMap::<u8, u8, u8>::new(prefix("double_map"), ());
+		double_map.insert(1, 2, 3);
+		double_map.insert(1, 3, 4);
+		double_map.insert(2, 2, 5);
+		assert_eq!(double_map.iter_prefix(&1).collect::<Vec<_>>(), vec![(&2, &3), (&3, &4)]);
+		assert_eq!(double_map.remove(&2, &2), Some(5));
+		assert_eq!(double_map.iter().count(), 2);
+	}
+
+	#[test]
+	fn entries_have_prefixed_keys() {
+		let mut map = StorageMap::<u8, bool>::new(prefix("map"), ());
+		map.insert(5, true);
+
+		let key = [(7u32, *b"example").encode(), (3u32, *b"map").encode(), vec![5]].concat();
+		assert_eq!(map.prefix().pallet_name(), "example");
+		assert_eq!(map.entries(), vec![(key, vec![1])]);
+	}
+}
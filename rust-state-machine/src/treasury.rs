@@ -0,0 +1,364 @@
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, Zero};
+use crate::support::ensure::{ensure_root, OriginFor};
+use crate::support::storage::Encode;
+use crate::support::{DispatchResult, GetPallet, Hooks, OnUnbalanced, PalletId};
+
+/// The identifier given to every spending proposal.
+pub type ProposalIndex = u32;
+
+/// The configuration of the Treasury Module.
+///
+/// The funds of the treasury are held in the balances pallet, so the runtime must give access to
+/// it.
+pub trait Config:
+	crate::balances::Config<Balance: From<u8> + CheckedDiv>
+	+ Clone
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+{
+	/// The identifier of the treasury, from which the account of the pot is derived.
+	const PALLET_ID: PalletId;
+	/// The amount reserved from the proposer of a spend, which is slashed if it is rejected.
+	const PROPOSAL_BOND: Self::Balance;
+	/// The number of blocks between two spends of the approved proposals.
+	const SPEND_PERIOD: Self::BlockNumber;
+	/// The percentage of the transaction fees handed to `FeeShare` which goes to the pot. The rest
+	/// is burned.
+	const FEE_SHARE: u8;
+
+	/// Derive the account owned by the pallet `id`.
+	fn pallet_account(id: PalletId) -> Self::AccountId;
+}
+
+/// A proposal to spend funds of the treasury.
+#[derive(Debug, Clone)]
+pub struct Proposal<T: Config> {
+	/// The account which made the proposal.
+	pub proposer: T::AccountId,
+	/// The amount to pay to the beneficiary.
+	pub value: T::Balance,
+	/// The account to pay.
+	pub beneficiary: T::AccountId,
+	/// The amount reserved from the proposer.
+	pub bond: T::Balance,
+}
+
+impl<T: Config> Encode for Proposal<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.proposer.encode_to(dest);
+		self.value.encode_to(dest);
+		self.beneficiary.encode_to(dest);
+		self.bond.encode_to(dest);
+	}
+}
+
+/// The events emitted by the Treasury Module.
+#[macros::event]
+#[derive(Debug, Clone)]
+pub enum Event<T: Config> {
+	/// The spending proposal `index` was made.
+	Proposed { index: ProposalIndex },
+	/// The spending proposal `index` was rejected, and the bond of the proposer slashed.
+	Rejected { index: ProposalIndex, slashed: T::Balance },
+	/// `value` was paid to `beneficiary`, as approved by the spending proposal `index`.
+	Awarded { index: ProposalIndex, value: T::Balance, beneficiary: T::AccountId },
+	/// `amount` was deposited into the pot.
+	Deposit { amount: T::Balance },
+}
+
+/// This is the Treasury Module.
+/// It holds a pot of funds, filled by a share of the transaction fees and by slashes. Accounts can
+/// propose to spend these funds, and the approved proposals are paid every spend period.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from a spending proposal to its details, until it is rejected or paid.
+	proposals: StorageMap<ProposalIndex, Proposal<T>>,
+	/// The approved proposals, which are paid at the next spend period if the pot allows it.
+	#[default(Vec::new())]
+	approvals: StorageValue<Vec<ProposalIndex>>,
+	/// The index given to the next proposal.
+	#[default(0)]
+	next_index: StorageValue<ProposalIndex>,
+	/// The block of the next spend period, or `None` if it would overflow the block number.
+	#[default(Some(T::SPEND_PERIOD))]
+	next_spend: StorageValue<Option<T::BlockNumber>>,
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+
+	/// Get the account of the pot.
+	pub fn account_id() -> T::AccountId {
+		T::pallet_account(T::PALLET_ID)
+	}
+
+	/// Get the details (if any) of the spending proposal `index`.
+	pub fn proposal(&self, index: ProposalIndex) -> Option<&Proposal<T>> {
+		self.proposals.try_get(&index)
+	}
+
+	/// Get the approved proposals, which have not been paid yet.
+	pub fn approvals(&self) -> &[ProposalIndex] {
+		self.approvals.try_get().map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event<T>] {
+		&self.events
+	}
+
+	/// Pay the approved proposals which the pot can afford. The others stay approved until the next
+	/// spend period.
+	fn spend_funds(runtime: &mut T) {
+		let pot = Self::account_id();
+		let pallet: &mut Self = runtime.pallet_mut();
+		let approvals = pallet.approvals.mutate(core::mem::take);
+
+		for index in approvals {
+			let pallet: &mut Self = runtime.pallet_mut();
+			let Some(proposal) = pallet.proposals.try_get(&index) else { continue };
+			let (proposer, value, beneficiary, bond) =
+				(proposal.proposer.clone(), proposal.value, proposal.beneficiary.clone(), proposal.bond);
+
+			let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+			if balances.transfer(pot.clone(), beneficiary.clone(), value).is_err() {
+				let pallet: &mut Self = runtime.pallet_mut();
+				pallet.approvals.mutate(|approvals| approvals.push(index));
+				continue;
+			}
+			// The bond was reserved when the proposal was made, so this cannot fail.
+			let _ = balances.unreserve(&proposer, bond);
+
+			let pallet: &mut Self = runtime.pallet_mut();
+			pallet.proposals.remove(&index);
+			pallet.events.push(Event::Awarded { index, value, beneficiary });
+		}
+	}
+}
+
+#[macros::call(errors(
+	"Overflow",
+	"Proposal does not exist",
+	"Proposal is already approved",
+	"Not enough funds.",
+	"Funds are locked.",
+))]
+impl<T: Config> Pallet<T> {
+	/// Propose to pay `value` from the pot to `beneficiary`, reserving `T::PROPOSAL_BOND` from the
+	/// `caller` until the proposal is paid or rejected.
+	pub fn propose_spend(
+		runtime: &mut T,
+		caller: T::AccountId,
+		value: T::Balance,
+		beneficiary: T::AccountId,
+	) -> DispatchResult {
+		let bond = T::PROPOSAL_BOND;
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		balances.reserve(&caller, bond)?;
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		let index = pallet.next_index.get();
+		pallet.next_index.put(index.checked_add(1).ok_or("Overflow")?);
+		pallet.proposals.insert(index, Proposal { proposer: caller, value, beneficiary, bond });
+		pallet.events.push(Event::Proposed { index });
+
+		Ok(())
+	}
+
+	/// Approve the spending proposal `index`, which is paid at the next spend period.
+	/// This function should only succeed if the caller is the `Root` origin.
+	pub fn approve_proposal(&mut self, origin: OriginFor<T>, index: ProposalIndex) -> DispatchResult {
+		ensure_root(origin)?;
+		if !self.proposals.contains_key(&index) {
+			return Err("Proposal does not exist".into());
+		}
+		self.approvals.try_mutate(|approvals| -> DispatchResult {
+			if approvals.contains(&index) {
+				return Err("Proposal is already approved".into());
+			}
+
+			approvals.push(index);
+			Ok(())
+		})
+	}
+
+	/// Reject the spending proposal `index`, slashing the bond of the proposer into the pot.
+	/// This function should only succeed if the caller is the `Root` origin.
+	pub fn reject_proposal(runtime: &mut T, origin: OriginFor<T>, index: ProposalIndex) -> DispatchResult {
+		ensure_root(origin)?;
+		let pallet: &mut Self = runtime.pallet_mut();
+		let proposal = pallet.proposals.remove(&index).ok_or("Proposal does not exist")?;
+		pallet.approvals.mutate(|approvals| approvals.retain(|approved| *approved != index));
+
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		let slashed = balances.slash_reserved(&proposal.proposer, proposal.bond);
+		<Self as OnUnbalanced<T, T::Balance>>::on_unbalanced(runtime, slashed);
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.events.push(Event::Rejected { index, slashed });
+		Ok(())
+	}
+}
+
+/// Slashes handed to the treasury are deposited into the pot.
+impl<T: Config> OnUnbalanced<T, T::Balance> for Pallet<T> {
+	fn on_unbalanced(runtime: &mut T, amount: T::Balance) {
+		let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+		// If the pot overflows, the funds are burned instead.
+		if balances.deposit(&Self::account_id(), amount).is_ok() {
+			let pallet: &mut Self = runtime.pallet_mut();
+			pallet.events.push(Event::Deposit { amount });
+		}
+	}
+}
+
+/// A handler for transaction fees: `T::FEE_SHARE` percent of each fee is deposited into the pot, and
+/// the rest is burned.
+pub struct FeeShare<T>(core::marker::PhantomData<T>);
+
+impl<T: Config> OnUnbalanced<T, T::Balance> for FeeShare<T> {
+	fn on_unbalanced(runtime: &mut T, fee: T::Balance) {
+		let percent: T::Balance = T::FEE_SHARE.into();
+		let hundred: T::Balance = 100.into();
+		// Divide first if multiplying first overflows, at the cost of some precision.
+		let share = fee
+			.checked_mul(&percent)
+			.and_then(|share| share.checked_div(&hundred))
+			.or_else(|| fee.checked_div(&hundred).and_then(|share| share.checked_mul(&percent)))
+			.unwrap_or(Zero::zero());
+		<Pallet<T> as OnUnbalanced<T, T::Balance>>::on_unbalanced(runtime, share);
+	}
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+	/// Pay the approved proposals at the beginning of every spend period.
+	fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) {
+		let pallet: &mut Self = runtime.pallet_mut();
+		match pallet.next_spend.get() {
+			Some(next_spend) if block_number >= next_spend => {},
+			_ => return,
+		}
+		// If the next spend period overflows, no more spends are scheduled.
+		pallet.next_spend.put(block_number.checked_add(&T::SPEND_PERIOD));
+
+		Self::spend_funds(runtime);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, FeeShare, Pallet};
+	use crate::support::ensure::RawOrigin;
+	use crate::support::{Hooks, OnUnbalanced};
+	use crate::{balances, support, types, Runtime, RuntimeCall};
+
+	fn run_to_block(runtime: &mut Runtime, block_number: u32) {
+		while runtime.system.block_number() < block_number {
+			let header = support::Header { block_number: runtime.system.block_number() + 1, author: "alice".to_string() };
+			let block: types::Block = support::Block { header, extrinsics: vec![] };
+			runtime.execute_block(block).unwrap();
+		}
+	}
+
+	#[test]
+	fn approved_proposals_are_paid_every_spend_period() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		let pot = Pallet::<Runtime>::account_id();
+		runtime.balances.set_balance(&alice, 100);
+
+		// Slashes handed to the treasury go to the pot.
+		Pallet::on_unbalanced(&mut runtime, 50);
+		assert_eq!(runtime.balances.balance(&pot), 50);
+
+		Pallet::propose_spend(&mut runtime, alice.clone(), 40, bob.clone()).unwrap();
+		Pallet::propose_spend(&mut runtime, alice.clone(), 40, bob.clone()).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+		assert_eq!(runtime.treasury.approve_proposal(RawOrigin::Signed(alice.clone()), 0), Err(support::DispatchError::BadOrigin));
+		runtime.treasury.approve_proposal(RawOrigin::Root, 0).unwrap();
+		runtime.treasury.approve_proposal(RawOrigin::Root, 1).unwrap();
+
+		// The pot can only afford the first proposal at the first spend period.
+		run_to_block(&mut runtime, 4);
+		assert_eq!(runtime.balances.balance(&bob), 0);
+		run_to_block(&mut runtime, 5);
+		assert_eq!(runtime.balances.balance(&bob), 40);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+		assert_eq!(runtime.treasury.approvals(), &[1]);
+
+		Pallet::on_unbalanced(&mut runtime, 30);
+		run_to_block(&mut runtime, 10);
+		assert_eq!(runtime.balances.balance(&bob), 80);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert!(runtime.treasury.approvals().is_empty());
+	}
+
+	#[test]
+	fn rejected_proposal_bond_is_slashed_into_the_pot() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		let pot = Pallet::<Runtime>::account_id();
+		runtime.balances.set_balance(&alice, 100);
+
+		Pallet::propose_spend(&mut runtime, alice.clone(), 1000, alice.clone()).unwrap();
+		Pallet::reject_proposal(&mut runtime, RawOrigin::Root, 0).unwrap();
+		assert!(matches!(runtime.treasury.events().last(), Some(Event::Rejected { index: 0, slashed: 10 })));
+		assert_eq!(runtime.balances.balance(&alice), 90);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert_eq!(runtime.balances.balance(&pot), 10);
+	}
+
+	#[test]
+	fn share_of_transaction_fees_goes_to_the_pot() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		let pot = Pallet::<Runtime>::account_id();
+		runtime.balances.set_balance(&alice, 100);
+
+		// Half of the fees go to the pot, and the rest is burned.
+		FeeShare::on_unbalanced(&mut runtime, 100);
+		assert_eq!(runtime.balances.balance(&pot), 50);
+
+		// Every extrinsic of the runtime pays a fee of 2, even if its call fails.
+		let transfer = |to: &String, amount| RuntimeCall::balances(balances::Call::transfer { to: to.clone(), amount });
+		let extrinsics = vec![
+			// `bob` cannot pay the fee yet, so this transfer is not dispatched.
+			support::Extrinsic { caller: bob.clone(), call: transfer(&alice, 1) },
+			support::Extrinsic { caller: alice.clone(), call: transfer(&bob, 20) },
+			support::Extrinsic { caller: alice.clone(), call: transfer(&bob, 1000) },
+		];
+		let block: types::Block = support::Block { header: support::Header { block_number: 1, author: "alice".to_string() }, extrinsics };
+		runtime.execute_block(block).unwrap();
+		assert_eq!(runtime.balances.balance(&alice), 76);
+		assert_eq!(runtime.balances.balance(&bob), 20);
+		assert_eq!(runtime.balances.balance(&pot), 52);
+	}
+
+	#[test]
+	fn spends_stop_when_the_spend_period_overflows() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		let pot = Pallet::<Runtime>::account_id();
+		runtime.balances.set_balance(&alice, 100);
+		Pallet::on_unbalanced(&mut runtime, 50);
+		Pallet::propose_spend(&mut runtime, alice.clone(), 10, alice.clone()).unwrap();
+		Pallet::propose_spend(&mut runtime, alice.clone(), 10, alice.clone()).unwrap();
+		runtime.treasury.approve_proposal(RawOrigin::Root, 0).unwrap();
+
+		// The spend period which follows `u32::MAX - 1` overflows, so it is the last one.
+		runtime.treasury.next_spend.put(Some(u32::MAX - 1));
+		<Pallet<Runtime> as Hooks<_, _>>::on_initialize(&mut runtime, u32::MAX - 1);
+		assert_eq!(runtime.balances.balance(&pot), 40);
+		assert_eq!(runtime.treasury.next_spend.get(), None);
+
+		runtime.treasury.approve_proposal(RawOrigin::Root, 1).unwrap();
+		<Pallet<Runtime> as Hooks<_, _>>::on_initialize(&mut runtime, u32::MAX);
+		assert_eq!(runtime.balances.balance(&pot), 40);
+	}
+}
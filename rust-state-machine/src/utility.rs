@@ -0,0 +1,195 @@
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use crate::support::ensure::OriginFor;
+use crate::support::{self, Dispatch, DispatchError, DispatchResult, GetPallet};
+
+/// The configuration of the Utility Module.
+///
+/// Batches are dispatched through the runtime, so the runtime itself must be able to dispatch the
+/// `RuntimeCall`s, and must be `Clone` so that `batch_all` can revert its changes.
+pub trait Config:
+	crate::system::Config
+	+ Clone
+	+ Dispatch<Caller = OriginFor<Self>, Call = <Self as Config>::RuntimeCall>
+	+ GetPallet<Pallet<Self>>
+{
+	/// The aggregated call type of the runtime, which is what batches are made of.
+	type RuntimeCall: Debug + Clone;
+}
+
+/// The events emitted by the Utility Module.
+#[macros::event]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+	/// A `batch` was interrupted by the call at `index` failing with `error`.
+	BatchInterrupted { index: usize, error: DispatchError },
+	/// Every call of a batch was dispatched successfully.
+	BatchCompleted,
+	/// Every call of a `force_batch` was dispatched, but some of them failed.
+	BatchCompletedWithErrors,
+	/// A single call of a `force_batch` was dispatched successfully.
+	ItemCompleted,
+	/// A single call of a `force_batch` failed with `error`.
+	ItemFailed { error: DispatchError },
+}
+
+/// This is the Utility Module.
+/// It allows accounts to dispatch multiple calls with a single extrinsic. It has nothing in its
+/// storage but its version.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The events emitted by this pallet in the current block, oldest first.
+	events: Vec<Event>,
+	_config: PhantomData<T>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the events emitted by this pallet in the current block, oldest first.
+	pub fn events(&self) -> &[Event] {
+		&self.events
+	}
+
+	/// Record an `event` emitted by this pallet.
+	fn deposit_event(runtime: &mut T, event: Event) {
+		runtime.pallet_mut().events.push(event);
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Dispatch `calls` with the `origin` of the batch, one after the other.
+	/// The batch stops at the first call which fails, without reverting the calls which were
+	/// already dispatched. This is reported with a `BatchInterrupted` event rather than an error.
+	pub fn batch(
+		runtime: &mut T,
+		origin: OriginFor<T>,
+		#[runtime_call] calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult {
+		for (index, call) in calls.into_iter().enumerate() {
+			if let Err(error) = runtime.dispatch(origin.clone(), call) {
+				Self::deposit_event(runtime, Event::BatchInterrupted { index, error });
+				return Ok(());
+			}
+		}
+
+		Self::deposit_event(runtime, Event::BatchCompleted);
+		Ok(())
+	}
+
+	/// Dispatch `calls` with the `origin` of the batch, one after the other.
+	/// If any of the calls fails, every change made by the batch is reverted, and the error of
+	/// that call is returned.
+	pub fn batch_all(
+		runtime: &mut T,
+		origin: OriginFor<T>,
+		#[runtime_call] calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult {
+		support::transactional(runtime, |runtime| -> DispatchResult {
+			for call in calls {
+				runtime.dispatch(origin.clone(), call)?;
+			}
+			Ok(())
+		})?;
+
+		Self::deposit_event(runtime, Event::BatchCompleted);
+		Ok(())
+	}
+
+	/// Dispatch `calls` with the `origin` of the batch, one after the other.
+	/// Every call is dispatched even if some of them fail, and the outcome of each call is
+	/// reported with an `ItemCompleted` or `ItemFailed` event.
+	pub fn force_batch(
+		runtime: &mut T,
+		origin: OriginFor<T>,
+		#[runtime_call] calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult {
+		let mut failed = false;
+		for call in calls {
+			match runtime.dispatch(origin.clone(), call) {
+				Ok(()) => Self::deposit_event(runtime, Event::ItemCompleted),
+				Err(error) => {
+					failed = true;
+					Self::deposit_event(runtime, Event::ItemFailed { error });
+				},
+			}
+		}
+
+		let event = if failed { Event::BatchCompletedWithErrors } else { Event::BatchCompleted };
+		Self::deposit_event(runtime, event);
+		Ok(())
+	}
+}
+
+impl<T: Config> support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, Pallet};
+	use crate::support::ensure::RawOrigin;
+	use crate::{balances, Runtime, RuntimeCall};
+
+	fn transfer(to: &str, amount: u128) -> RuntimeCall {
+		RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount })
+	}
+
+	fn setup() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		runtime
+	}
+
+	#[test]
+	fn batch_stops_at_first_error() {
+		let mut runtime = setup();
+		let calls = vec![transfer("bob", 30), transfer("bob", 100), transfer("charlie", 30)];
+
+		Pallet::batch(&mut runtime, RawOrigin::Signed("alice".to_string()), calls).unwrap();
+
+		// The first transfer is kept, and the last one is never dispatched.
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 30);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 0);
+		assert_eq!(
+			runtime.utility.events(),
+			&[Event::BatchInterrupted { index: 1, error: "Not enough funds.".into() }]
+		);
+	}
+
+	#[test]
+	fn batch_all_is_reverted_on_error() {
+		let mut runtime = setup();
+		let calls = vec![transfer("bob", 30), transfer("bob", 100)];
+
+		let res = Pallet::batch_all(&mut runtime, RawOrigin::Signed("alice".to_string()), calls);
+		assert_eq!(res, Err("Not enough funds.".into()));
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+
+		let calls = vec![transfer("bob", 30), transfer("charlie", 30)];
+		Pallet::batch_all(&mut runtime, RawOrigin::Signed("alice".to_string()), calls).unwrap();
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 40);
+		assert_eq!(runtime.utility.events(), &[Event::BatchCompleted]);
+	}
+
+	#[test]
+	fn force_batch_reports_every_call() {
+		let mut runtime = setup();
+		let calls = vec![transfer("bob", 30), transfer("bob", 100), transfer("charlie", 30)];
+
+		// Batches can also be nested in other batches through `RuntimeCall`.
+		let call = RuntimeCall::utility(super::Call::force_batch { calls });
+		Pallet::batch(&mut runtime, RawOrigin::Signed("alice".to_string()), vec![call]).unwrap();
+
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 40);
+		assert_eq!(
+			runtime.utility.events(),
+			&[
+				Event::ItemCompleted,
+				Event::ItemFailed { error: "Not enough funds.".into() },
+				Event::ItemCompleted,
+				Event::BatchCompletedWithErrors,
+				Event::BatchCompleted,
+			]
+		);
+	}
+}
@@ -0,0 +1,277 @@
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, Zero};
+use crate::balances::LockIdentifier;
+use crate::support::storage::Encode;
+use crate::support::{DispatchResult, GetPallet};
+
+/// The identifier of the lock this pallet places on vesting accounts.
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+/// The configuration of the Vesting Module.
+///
+/// Vesting funds are locked in the balances pallet, and unlock as the block number of the system
+/// pallet advances, so the runtime must give access to both.
+pub trait Config:
+	crate::balances::Config
+	+ Clone
+	+ GetPallet<Pallet<Self>>
+	+ GetPallet<crate::balances::Pallet<Self>>
+	+ GetPallet<crate::system::Pallet<Self>>
+{
+	/// The maximum number of vesting schedules an account can have at once.
+	const MAX_VESTING_SCHEDULES: u32;
+	/// The minimum amount of a vested transfer, so that filling the schedules of an account is
+	/// costly.
+	const MIN_VESTED_TRANSFER: Self::Balance;
+
+	/// Convert a block number into a balance, to compute how much has vested after some blocks.
+	fn block_number_to_balance(block_number: Self::BlockNumber) -> Self::Balance;
+}
+
+/// The vesting schedule of an account.
+#[derive(Debug, Clone)]
+pub struct VestingInfo<T: Config> {
+	/// The amount locked when the schedule was created.
+	pub locked: T::Balance,
+	/// The amount which unlocks with every block.
+	pub per_block: T::Balance,
+	/// The block from which the funds start to unlock.
+	pub starting_block: T::BlockNumber,
+}
+
+impl<T: Config> Encode for VestingInfo<T>
+where
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.locked.encode_to(dest);
+		self.per_block.encode_to(dest);
+		self.starting_block.encode_to(dest);
+	}
+}
+
+impl<T: Config> VestingInfo<T> {
+	/// Get the amount of this schedule which is still locked at block `now`.
+	pub fn locked_at(&self, now: T::BlockNumber) -> T::Balance {
+		let now = T::block_number_to_balance(now);
+		let start = T::block_number_to_balance(self.starting_block);
+		let elapsed = now.checked_sub(&start).unwrap_or(Zero::zero());
+
+		// If this overflows, the schedule must have fully vested.
+		let vested = self.per_block.checked_mul(&elapsed).unwrap_or(self.locked);
+		self.locked.checked_sub(&vested).unwrap_or(Zero::zero())
+	}
+}
+
+/// This is the Vesting Module.
+/// It locks funds of an account, and unlocks them linearly over time according to schedules. An
+/// account can have up to `T::MAX_VESTING_SCHEDULES` schedules at once.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// A map from an account to its vesting schedules, oldest first.
+	vesting: StorageMap<T::AccountId, Vec<VestingInfo<T>>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the vesting schedules of `who`, oldest first.
+	pub fn vesting(&self, who: &T::AccountId) -> &[VestingInfo<T>] {
+		self.vesting.try_get(who).map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	/// Get the amount (if any) of the funds of `who` which are still vesting at the current block,
+	/// over all of their schedules.
+	/// The lock on the balance of `who` is only reduced to this amount when `vest` is called.
+	pub fn vesting_balance(runtime: &T, who: &T::AccountId) -> Option<T::Balance> {
+		let now = GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number();
+		let pallet: &Self = runtime.pallet();
+		let schedules = pallet.vesting.try_get(who)?;
+		// The locked amounts of the schedules are checked not to overflow when they are added.
+		Some(schedules.iter().fold(Zero::zero(), |total, schedule| total + schedule.locked_at(now)))
+	}
+
+	/// Give `who` another vesting schedule, locking `schedule.locked` of their balance.
+	/// This function will return an error if `who` already has `T::MAX_VESTING_SCHEDULES`
+	/// schedules, or if the schedule would never unlock anything.
+	pub fn add_vesting_schedule(
+		runtime: &mut T,
+		who: &T::AccountId,
+		schedule: VestingInfo<T>,
+	) -> DispatchResult {
+		if schedule.locked.is_zero() || schedule.per_block.is_zero() {
+			return Err("Invalid vesting schedule".into());
+		}
+
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.vesting.try_mutate(who.clone(), |schedules| {
+			let schedules = schedules.get_or_insert_with(Vec::new);
+			if schedules.len() >= T::MAX_VESTING_SCHEDULES as usize {
+				return Err("Too many vesting schedules");
+			}
+			schedules
+				.iter()
+				.try_fold(schedule.locked, |total, schedule| total.checked_add(&schedule.locked))
+				.ok_or("Overflow")?;
+			schedules.push(schedule);
+			Ok(())
+		})?;
+
+		Self::update_lock(runtime, who)
+	}
+
+	/// Reduce the lock on the balance of `who` to the amount still vesting, removing the schedules
+	/// which fully vested, and the lock once everything has vested.
+	fn update_lock(runtime: &mut T, who: &T::AccountId) -> DispatchResult {
+		let locked = Self::vesting_balance(runtime, who).ok_or("No vesting schedule")?;
+
+		let now = GetPallet::<crate::system::Pallet<T>>::pallet(runtime).block_number();
+		let pallet: &mut Self = runtime.pallet_mut();
+		pallet.vesting.mutate(who.clone(), |schedules| {
+			if let Some(schedules) = schedules {
+				schedules.retain(|schedule| !schedule.locked_at(now).is_zero());
+			}
+		});
+
+		if locked.is_zero() {
+			let pallet: &mut Self = runtime.pallet_mut();
+			pallet.vesting.remove(who);
+			let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+			balances.remove_lock(VESTING_ID, who);
+		} else {
+			let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+			balances.set_lock(VESTING_ID, who, locked);
+		}
+
+		Ok(())
+	}
+}
+
+#[macros::call(errors(
+	"Amount is below the minimum vested transfer",
+	"No vesting schedule",
+	"Invalid vesting schedule",
+	"Too many vesting schedules",
+	"Overflow",
+	"Not enough funds.",
+	"Funds are locked.",
+))]
+impl<T: Config> Pallet<T> {
+	/// Unlock the funds of the `caller` which have vested so far.
+	/// This function will return an error if the caller has no vesting schedule.
+	pub fn vest(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+		Self::update_lock(runtime, &caller)
+	}
+
+	/// Transfer `locked` from the `caller` to `target`, and lock it in a new vesting schedule which
+	/// unlocks `per_block` with every block from `starting_block`.
+	/// This function will return an error if `locked` is below `T::MIN_VESTED_TRANSFER`, or if the
+	/// schedule cannot be added to `target`, in which case nothing is transferred.
+	pub fn vested_transfer(
+		runtime: &mut T,
+		caller: T::AccountId,
+		target: T::AccountId,
+		locked: T::Balance,
+		per_block: T::Balance,
+		starting_block: T::BlockNumber,
+	) -> DispatchResult {
+		if locked < T::MIN_VESTED_TRANSFER {
+			return Err("Amount is below the minimum vested transfer".into());
+		}
+
+		crate::support::transactional(runtime, |runtime| {
+			let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+			balances.transfer(caller, target.clone(), locked)?;
+
+			Self::add_vesting_schedule(runtime, &target, VestingInfo { locked, per_block, starting_block })
+		})
+	}
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::Pallet;
+	use crate::{support, types, Runtime};
+
+	fn run_to_block(runtime: &mut Runtime, block_number: u32) {
+		while runtime.system.block_number() < block_number {
+			let header = support::Header { block_number: runtime.system.block_number() + 1, author: "alice".to_string() };
+			let block: types::Block = support::Block { header, extrinsics: vec![] };
+			runtime.execute_block(block).unwrap();
+		}
+	}
+
+	#[test]
+	fn vesting_unlocks_linearly() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		runtime.balances.set_balance(&alice, 200);
+
+		// 100 unlocks over 10 blocks, starting from block 2.
+		Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 100, 10, 2).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), 100);
+		assert_eq!(runtime.balances.locked_balance(&bob), 100);
+
+		run_to_block(&mut runtime, 5);
+		assert_eq!(Pallet::vesting_balance(&runtime, &bob), Some(70));
+		// The lock is only reduced once `bob` vests.
+		assert_eq!(runtime.balances.locked_balance(&bob), 100);
+
+		Pallet::vest(&mut runtime, bob.clone()).unwrap();
+		assert_eq!(runtime.balances.locked_balance(&bob), 70);
+		assert_eq!(runtime.balances.transfer(bob.clone(), alice.clone(), 40), Err("Funds are locked.".into()));
+		runtime.balances.transfer(bob.clone(), alice.clone(), 30).unwrap();
+
+		run_to_block(&mut runtime, 12);
+		assert_eq!(Pallet::vesting_balance(&runtime, &bob), Some(0));
+		Pallet::vest(&mut runtime, bob.clone()).unwrap();
+		assert_eq!(runtime.balances.locked_balance(&bob), 0);
+		assert!(runtime.vesting.vesting(&bob).is_empty());
+		assert_eq!(Pallet::vest(&mut runtime, bob), Err("No vesting schedule".into()));
+	}
+
+	#[test]
+	fn several_schedules_are_bounded() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		runtime.balances.set_balance(&alice, 200);
+
+		// Dust cannot be used to fill the schedules of an account.
+		assert_eq!(
+			Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 1, 1, 0),
+			Err("Amount is below the minimum vested transfer".into())
+		);
+		Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 30, 10, 2).unwrap();
+		Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 20, 5, 0).unwrap();
+		Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 10, 10, 10).unwrap();
+		assert_eq!(
+			Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 10, 10, 0),
+			Err("Too many vesting schedules".into())
+		);
+		assert_eq!(runtime.balances.locked_balance(&bob), 60);
+
+		// Vesting removes the schedules which fully vested, which frees their slot.
+		run_to_block(&mut runtime, 4);
+		assert_eq!(Pallet::vesting_balance(&runtime, &bob), Some(20));
+		Pallet::vest(&mut runtime, bob.clone()).unwrap();
+		assert_eq!(runtime.vesting.vesting(&bob).len(), 2);
+		assert_eq!(runtime.balances.locked_balance(&bob), 20);
+		Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 10, 10, 4).unwrap();
+		assert_eq!(runtime.balances.locked_balance(&bob), 30);
+	}
+
+	#[test]
+	fn failed_schedule_reverts_the_transfer() {
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		runtime.balances.set_balance(&alice, 200);
+		let schedule = super::VestingInfo { locked: u128::MAX, per_block: 1, starting_block: 0 };
+		Pallet::add_vesting_schedule(&mut runtime, &bob, schedule).unwrap();
+
+		// The locked amounts of `bob` would overflow, so the funds stay with `alice`.
+		assert_eq!(Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 30, 10, 0), Err("Overflow".into()));
+		assert_eq!(runtime.balances.balance(&alice), 200);
+		assert_eq!(runtime.balances.balance(&bob), 0);
+	}
+}